@@ -1,6 +1,7 @@
 use core::cmp::Ordering;
 use crate::square::{Square, EMPTY_SQUARE};
 use crate::engine::*;
+use crate::movegen::is_legal;
 use crate::piece::Piece;
 use crate::position::*;
 
@@ -106,6 +107,24 @@ impl BoardBuilder {
     self
   }
 
+  /// Set the column of the rook used for kingside castling (Chess960).
+  pub fn set_kingside_rook(mut self, color: Color, col: i32) -> Self {
+    match color {
+      WHITE => self.board.white_castling_rights.kingside_rook = col,
+      BLACK => self.board.black_castling_rights.kingside_rook = col,
+    }
+    self
+  }
+
+  /// Set the column of the rook used for queenside castling (Chess960).
+  pub fn set_queenside_rook(mut self, color: Color, col: i32) -> Self {
+    match color {
+      WHITE => self.board.white_castling_rights.queenside_rook = col,
+      BLACK => self.board.black_castling_rights.queenside_rook = col,
+    }
+    self
+  }
+
   pub fn set_en_passant(mut self, position: Option<Position>) -> Self {
     self.board.en_passant = position;
     self
@@ -125,6 +144,10 @@ impl BoardBuilder {
 pub struct CastlingRights {
   kingside: bool,
   queenside: bool,
+  // starting columns of the castling rooks,
+  // only differ from the h and a files in Chess960
+  kingside_rook: i32,
+  queenside_rook: i32,
 }
 
 impl Default for CastlingRights {
@@ -132,6 +155,8 @@ impl Default for CastlingRights {
     Self {
       kingside: true,
       queenside: true,
+      kingside_rook: 7,
+      queenside_rook: 0,
     }
   }
 }
@@ -145,6 +170,16 @@ impl CastlingRights {
     self.queenside
   }
 
+  /// Column of the rook used for kingside castling.
+  pub fn get_kingside_rook(&self) -> i32 {
+    self.kingside_rook
+  }
+
+  /// Column of the rook used for queenside castling.
+  pub fn get_queenside_rook(&self) -> i32 {
+    self.queenside_rook
+  }
+
   fn disable_kingside(&mut self) {
     self.kingside = false
   }
@@ -508,14 +543,14 @@ impl Board {
   /// Otherwise, return false.
   #[inline]
   pub fn has_piece(&self, pos: Position) -> bool {
-    self.get_piece(pos) != None
+    self.get_piece(pos).is_some()
   }
 
   /// If a square at a given position has no piece, return true.
   /// Otherwise, return false.
   #[inline]
   pub fn has_no_piece(&self, pos: Position) -> bool {
-    self.get_piece(pos) == None
+    self.get_piece(pos).is_none()
   }
 
   /// If there is a king on the board, return the position that it sits on.
@@ -604,6 +639,10 @@ impl Board {
 
       result.add_piece(piece.move_to(to));
 
+      let home_row = match piece.get_color() {
+        WHITE => 0,
+        BLACK => 7,
+      };
       let castling_rights = match piece.get_color() {
         WHITE => &mut result.white_castling_rights,
         BLACK => &mut result.black_castling_rights,
@@ -611,67 +650,127 @@ impl Board {
 
       if piece.is_king() {
         castling_rights.disable_all();
-      } else if piece.is_queenside_rook() {
-        castling_rights.disable_queenside();
-      } else if piece.is_kingside_rook() {
-        castling_rights.disable_kingside();
+      } else if piece.is_rook() && from.get_row() == home_row {
+        if from.get_col() == castling_rights.queenside_rook {
+          castling_rights.disable_queenside();
+        } else if from.get_col() == castling_rights.kingside_rook {
+          castling_rights.disable_kingside();
+        }
+      }
+
+      // capturing a rook on its home square takes away that castling right
+      if let Some(Piece::Rook(enemy_color, _)) = self.get_piece(to) {
+        let (enemy_row, enemy_rights) = match enemy_color {
+          WHITE => (0, &mut result.white_castling_rights),
+          BLACK => (7, &mut result.black_castling_rights),
+        };
+        if to.get_row() == enemy_row {
+          if to.get_col() == enemy_rights.queenside_rook {
+            enemy_rights.disable_queenside();
+          } else if to.get_col() == enemy_rights.kingside_rook {
+            enemy_rights.disable_kingside();
+          }
+        }
       }
     }
 
     result
   }
 
-  /// Can a given player castle kingside?
-  pub fn can_kingside_castle(&self, color: Color) -> bool {
-    let right_of_king = Position::king_pos(color).next_right();
-    match color {
-      WHITE => {
-        self.has_no_piece(Position::new(0, 5))
-          && self.has_no_piece(Position::new(0, 6))
-          && self.get_piece(Position::new(0, 7))
-            == Some(Piece::Rook(color, Position::new(0, 7)))
-          && self.white_castling_rights.can_kingside_castle()
-          && !self.is_in_check(color)
-          && !self.is_threatened(right_of_king, color)
-          && !self.is_threatened(right_of_king.next_right(), color)
-      }
-      BLACK => {
-        self.has_no_piece(Position::new(7, 5))
-          && self.has_no_piece(Position::new(7, 6))
-          && self.get_piece(Position::new(7, 7))
-            == Some(Piece::Rook(color, Position::new(7, 7)))
-          && self.black_castling_rights.can_kingside_castle()
-          && !self.is_in_check(color)
-          && !self.is_threatened(right_of_king, color)
-          && !self.is_threatened(right_of_king.next_right(), color)
+  /// Get the king and rook (from, to) positions for castling.
+  ///
+  /// The king always lands on the g or c file, and the rook next to it
+  /// on the f or d file, wherever they started (as in Chess960).
+  fn castling_positions(
+    &self,
+    color: Color,
+    kingside: bool,
+  ) -> Option<(Position, Position, Position, Position)> {
+    let row = match color {
+      WHITE => 0,
+      BLACK => 7,
+    };
+    let rights = self.get_castling_rights(color);
+    let king_from = self.get_king_pos(color)?;
+    if king_from.get_row() != row {
+      return None;
+    }
+    let (rook_col, king_col, rook_to_col) = match kingside {
+      true => (rights.kingside_rook, 6, 5),
+      false => (rights.queenside_rook, 2, 3),
+    };
+    Some((
+      king_from,
+      Position::new(row, king_col),
+      Position::new(row, rook_col),
+      Position::new(row, rook_to_col),
+    ))
+  }
+
+  /// Can a given player castle on the given side?
+  fn can_castle(&self, color: Color, kingside: bool) -> bool {
+    let rights = self.get_castling_rights(color);
+    let has_right = match kingside {
+      true => rights.can_kingside_castle(),
+      false => rights.can_queenside_castle(),
+    };
+    if !has_right {
+      return false;
+    }
+    let (king_from, king_to, rook_from, rook_to) = match self.castling_positions(color, kingside) {
+      Some(positions) => positions,
+      None => return false,
+    };
+    // rook must be on the castling side of the king
+    if self.get_piece(rook_from) != Some(Piece::Rook(color, rook_from))
+      || (kingside && rook_from.get_col() <= king_from.get_col())
+      || (!kingside && rook_from.get_col() >= king_from.get_col())
+    {
+      return false;
+    }
+
+    // squares traveled by king and rook must be empty,
+    // except for the castling king and rook themselves
+    let row = king_from.get_row();
+    let span = |a: Position, b: Position| a.get_col().min(b.get_col())..=a.get_col().max(b.get_col());
+    for col in span(king_from, king_to).chain(span(rook_from, rook_to)) {
+      let pos = Position::new(row, col);
+      if pos != king_from && pos != rook_from && self.has_piece(pos) {
+        return false;
       }
     }
+
+    // king cannot castle out of, through or into check
+    let mut without_king = *self;
+    *without_king.get_square(king_from) = EMPTY_SQUARE;
+    !span(king_from, king_to).any(|col| without_king.is_threatened(Position::new(row, col), color))
+  }
+
+  /// Can a given player castle kingside?
+  pub fn can_kingside_castle(&self, color: Color) -> bool {
+    self.can_castle(color, true)
   }
 
   /// Can a given player castle queenside?
   pub fn can_queenside_castle(&self, color: Color) -> bool {
-    match color {
-      WHITE => {
-        self.has_no_piece(Position::new(0, 1))
-          && self.has_no_piece(Position::new(0, 2))
-          && self.has_no_piece(Position::new(0, 3))
-          && self.get_piece(Position::new(0, 0))
-            == Some(Piece::Rook(color, Position::new(0, 0)))
-          && self.white_castling_rights.can_queenside_castle()
-          && !self.is_in_check(color)
-          && !self.is_threatened(Position::queen_pos(color), color)
-      }
-      BLACK => {
-        self.has_no_piece(Position::new(7, 1))
-          && self.has_no_piece(Position::new(7, 2))
-          && self.has_no_piece(Position::new(7, 3))
-          && self.get_piece(Position::new(7, 0))
-            == Some(Piece::Rook(color, Position::new(7, 0)))
-          && self.black_castling_rights.can_queenside_castle()
-          && !self.is_in_check(color)
-          && !self.is_threatened(Position::queen_pos(color), color)
+    self.can_castle(color, false)
+  }
+
+  /// Move the king and rook for castling, without checking legality.
+  fn castle(&self, color: Color, kingside: bool) -> Self {
+    let mut result = *self;
+    if let Some((king_from, king_to, rook_from, rook_to)) = self.castling_positions(color, kingside) {
+      result.en_passant = None;
+      *result.get_square(king_from) = EMPTY_SQUARE;
+      *result.get_square(rook_from) = EMPTY_SQUARE;
+      result.add_piece(Piece::King(color, king_to));
+      result.add_piece(Piece::Rook(color, rook_to));
+      match color {
+        WHITE => result.white_castling_rights.disable_all(),
+        BLACK => result.black_castling_rights.disable_all(),
       }
     }
+    result
   }
 
   pub fn get_castling_rights(&self, color: Color) -> CastlingRights {
@@ -683,26 +782,38 @@ impl Board {
 
   pub(crate) fn is_legal_move(&self, m: Move, player_color: Color) -> bool {
     match m {
-      Move::KingSideCastle => self.can_kingside_castle(player_color),
-      Move::QueenSideCastle => self.can_queenside_castle(player_color),
+      Move::KingSideCastle => {
+        self.can_kingside_castle(player_color)
+          && !self.castle(player_color, true).is_in_check(player_color)
+      }
+      Move::QueenSideCastle => {
+        self.can_queenside_castle(player_color)
+          && !self.castle(player_color, false).is_in_check(player_color)
+      }
+      Move::Piece(_, _) | Move::Promotion(_, _, _) => {
+        self.is_pseudo_legal_move(m, player_color) && !self.apply_move(m).is_in_check(player_color)
+      }
+      Move::Resign => true,
+    }
+  }
+
+  /// Could the piece make this move if it were allowed to leave its own king in check?
+  /// Castling is checked as a whole by is_legal_move, so it never counts.
+  pub(crate) fn is_pseudo_legal_move(&self, m: Move, player_color: Color) -> bool {
+    match m {
       Move::Piece(from, to) => match self.get_piece(from) {
         Some(Piece::Pawn(c, pos)) => {
           let piece = Piece::Pawn(c, pos);
-          ((if let Some(en_passant) = self.en_passant {
+          (if let Some(en_passant) = self.en_passant {
             (en_passant == from.pawn_up(player_color).next_left()
-              || en_passant == from.pawn_up(player_color).next_right()
-                && en_passant == to)
+              || en_passant == from.pawn_up(player_color).next_right())
+              && en_passant == to
               && c == player_color
           } else {
             false
-          }) || piece.is_legal_move(to, self) && piece.get_color() == player_color)
-            && !self.apply_move(m).is_in_check(player_color)
-        }
-        Some(piece) => {
-          piece.is_legal_move(to, self)
-            && piece.get_color() == player_color
-            && !self.apply_move(m).is_in_check(player_color)
+          }) || piece.is_legal_move(to, self) && piece.get_color() == player_color
         }
+        Some(piece) => piece.is_legal_move(to, self) && piece.get_color() == player_color,
         _ => false,
       },
       Move::Promotion(from, to, promotion) => {
@@ -715,12 +826,11 @@ impl Board {
               // regular piece checks
               && piece.is_legal_move(to, self)
               && piece.get_color() == player_color
-              && !self.apply_move(m).is_in_check(player_color)
           }
           _ => false,
         }
       }
-      Move::Resign => true,
+      Move::KingSideCastle | Move::QueenSideCastle | Move::Resign => false,
     }
   }
 
@@ -737,29 +847,20 @@ impl Board {
 
     pieces.sort();
 
-    if pieces.len() == 0 {
-      false
-    } else if pieces.len() == 1 && pieces[0].is_king() {
-      false
-    } else if pieces.len() == 2 && pieces[0].is_king() && pieces[1].is_knight() {
-      false
-    } else if pieces.len() == 2 && pieces[0].is_king() && pieces[1].is_bishop() {
-      false
-    } else if pieces.len() == 3
-      && pieces[0].is_king()
-      && pieces[1].is_knight()
-      && pieces[2].is_knight()
-    {
-      false
-    } else if pieces.len() == 3
-      && pieces[0].is_king()
-      && pieces[1].is_bishop()
-      && pieces[2].is_bishop()
-    {
-      false
-    } else {
-      true
-    }
+    let insufficient = pieces.is_empty()
+      || (pieces.len() == 1 && pieces[0].is_king())
+      || (pieces.len() == 2 && pieces[0].is_king() && pieces[1].is_knight())
+      || (pieces.len() == 2 && pieces[0].is_king() && pieces[1].is_bishop())
+      || (pieces.len() == 3
+        && pieces[0].is_king()
+        && pieces[1].is_knight()
+        && pieces[2].is_knight())
+      || (pieces.len() == 3
+        && pieces[0].is_king()
+        && pieces[1].is_bishop()
+        && pieces[2].is_bishop());
+
+    !insufficient
   }
 
   /// Does the respective player have insufficient material?
@@ -769,10 +870,11 @@ impl Board {
   }
 
   /// Is the current player in stalemate?
+  ///
+  /// Insufficient material is left to the caller, which has to look at
+  /// both sides' material together.
   pub fn is_stalemate(&self) -> bool {
-    (self.get_legal_moves().is_empty() && !self.is_in_check(self.get_current_player_color()))
-      || (self.has_insufficient_material(self.turn)
-        && self.has_insufficient_material(!self.turn))
+    self.get_legal_moves().is_empty() && !self.is_in_check(self.get_current_player_color())
   }
 
   /// Is the current player in checkmate?
@@ -787,32 +889,10 @@ impl Board {
     self
   }
 
-  fn apply_move(&self, m: Move) -> Self {
+  pub(crate) fn apply_move(&self, m: Move) -> Self {
     match m {
-      Move::KingSideCastle => {
-        if let Some(king_pos) = self.get_king_pos(self.turn) {
-          let rook_pos = match self.turn {
-            WHITE => Position::new(0, 7),
-            BLACK => Position::new(7, 7),
-          };
-          self.move_piece(king_pos, rook_pos.next_left(), None)
-            .move_piece(rook_pos, king_pos.next_right(), None)
-        } else {
-          *self
-        }
-      }
-      Move::QueenSideCastle => {
-        if let Some(king_pos) = self.get_king_pos(self.turn) {
-          let rook_pos = match self.turn {
-            WHITE => Position::new(0, 0),
-            BLACK => Position::new(7, 0),
-          };
-          self.move_piece(king_pos, king_pos.next_left().next_left(), None)
-            .move_piece(rook_pos, king_pos.next_left(), None)
-        } else {
-          *self
-        }
-      }
+      Move::KingSideCastle => self.castle(self.turn, true),
+      Move::QueenSideCastle => self.castle(self.turn, false),
 
       Move::Piece(from, to) => {
         let mut result = self.move_piece(from, to, None);
@@ -843,7 +923,7 @@ impl Board {
 
     if m == Move::Resign {
       GameResult::Victory(!current_color)
-    } else if self.is_legal_move(m, current_color) {
+    } else if is_legal(self, m) {
       let next_turn = self.apply_move(m).change_turn();
       if next_turn.is_checkmate() {
         GameResult::Victory(current_color)
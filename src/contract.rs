@@ -1,43 +1,343 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-  to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, Storage
+  to_binary, Addr, BankMsg, Binary, Coin, Deps, DepsMut, Env, Event, MessageInfo, Order, Reply, Response, StdError,
+  StdResult, Storage, SubMsg, SubMsgResult, Uint128, WasmMsg,
 };
-use cw2::set_contract_version;
-use cw_storage_plus::Bound;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::{Bound, Index, MultiIndex, PrimaryKey};
+use std::collections::BTreeMap;
 
-use crate::cwchess::{CwChessAction, CwChessColor, CwChessGame, CwChessGameOver};
+use crate::cwchess::{
+  chess960_fen, game_seed, odds_fen, validate_fen, CwChessAction, CwChessBoard, CwChessColor, CwChessGame, CwChessGameOver, GameRecord, OddsType, DEFAULT_FEN,
+};
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, GameSummary, InstantiateMsg, QueryMsg, RatingSummary};
+use crate::msg::{AnnotationSummary, ClockSummary, CompletionHookMsg, ContractStats, ExecuteMsg, FenValidation, GameDetailed, GameMetrics, GameRatings, GameSummary, HeadToHeadSummary, InstantiateMsg, LatestMoveSummary, MatchSummary, MigrateMsg, MoveSummary, MoveValidation, OpeningSummary, PlayerCounts, PlayerGameRating, PlayerStatsSummary, QueryMsg, RatingHistoryPoint, RatingSummary, SimulatedMove, StatusFilter, TimedOutGame, TournamentStanding, TurnInfoSummary};
 use crate::state::{
-  get_challenges_map, get_games_map, merge_iters, next_challenge_id,
-  next_game_id, Challenge, State, STATE, RATINGS
+  add_active_game, add_challenge, add_finished_game, add_player, add_started_game, ANNOTATIONS, get_challenges_map, get_games_map, has_rating, head_to_head_key,
+  merge_iters, next_challenge_id, next_game_id, remove_active_game, remove_challenge, status_key, treasury, Challenge, Config, PlayerStats, State, TimeControl, ACTIVE_GAMES, CONFIG,
+  CHALLENGE_DEDUP, HEAD_TO_HEAD, FINISHED_GAME_COUNT, GAME_ID, LEGACY_RATINGS, OPEN_CHALLENGE_COUNT, PLAYER_COUNT, PLAYER_STATS, STATE,
+  LEGACY_CONFIG, LEGACY_RATING_HISTORY, STORED_CONFIG, RATINGS, RATING_HISTORY, REMATCHES
+};
+use crate::elo::{elo_with_games, EloRating, EloConfig, Outcomes};
+use crate::matches::{next_match_id, Match, MATCHES, MATCH_GAMES, MAX_MATCH_GAMES};
+use crate::queue::{balanced_color, best_opponent, QueueEntry, QUEUE, QUEUED_PLAYERS};
+use crate::tournament::{
+  game_points, next_tournament_id, pairings, round_finished, standings, swiss_pairings, Tournament,
+  TournamentFormat, TournamentRecord, TOURNAMENTS, TOURNAMENT_GAMES, TOURNAMENT_RECORDS,
 };
-use crate::elo::{elo, EloRating, EloConfig, Outcomes};
+use crate::util::is_bare_promotion;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "cosmos-chess";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
-const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const DEFAULT_MAX_OPEN_CHALLENGES: u64 = 10;
+// ~1 week @ ~10 blocks/minute
+const DEFAULT_ABANDON_THRESHOLD: u64 = 100_800;
+const DEFAULT_PROVISIONAL_GAMES: u64 = 10;
+const DEFAULT_K_FACTOR: u64 = 32;
+const DEFAULT_HIGH_RATING_THRESHOLD: u64 = 2400;
+const DEFAULT_RATING_FLOOR: u64 = 100;
+// ~30 days @ ~10 blocks/minute
+const DEFAULT_DECAY_AFTER_BLOCKS: u64 = 432_000;
+const DEFAULT_DECAY_AMOUNT: u64 = 10;
+// ~1 week @ ~10 blocks/minute
+const DEFAULT_INACTIVITY_BLOCKS: u64 = 100_800;
+// ~30 days @ ~10 blocks/minute
+const DEFAULT_MAX_BLOCK_LIMIT: u64 = 432_000;
+// ~1 minute @ ~10 blocks/minute, less is not enough to play a move
+const MIN_BLOCK_LIMIT: u64 = 10;
+// ~3 minutes, ~10 minutes and ~1 hour @ ~10 blocks/minute
+const DEFAULT_BULLET_MAX_BLOCKS: u64 = 30;
+const DEFAULT_BLITZ_MAX_BLOCKS: u64 = 100;
+const DEFAULT_RAPID_MAX_BLOCKS: u64 = 600;
+// query pagination
+const DEFAULT_LIMIT: u32 = 25;
+const MAX_LIMIT: u32 = 50;
+const MAX_IDS: usize = 25;
+const MAX_BATCH_ACTIONS: usize = 20;
+const MAX_ANNOTATION_LENGTH: usize = 280;
+// reply id of the completion hook submessage
+const COMPLETION_HOOK_REPLY_ID: u64 = 1;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
   deps: DepsMut,
   _env: Env,
   info: MessageInfo,
-  _msg: InstantiateMsg,
+  msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+  let config = Config {
+    abandon_threshold: msg.abandon_threshold.unwrap_or(DEFAULT_ABANDON_THRESHOLD),
+    auto_queen_promotion: msg.auto_queen_promotion.unwrap_or(true),
+    blitz_max_blocks: msg.blitz_max_blocks.unwrap_or(DEFAULT_BLITZ_MAX_BLOCKS),
+    bullet_max_blocks: msg.bullet_max_blocks.unwrap_or(DEFAULT_BULLET_MAX_BLOCKS),
+    challenge_fee: msg.challenge_fee,
+    completion_hook: msg
+      .completion_hook
+      .map(|hook| deps.api.addr_validate(&hook))
+      .transpose()?,
+    decay_after_blocks: msg.decay_after_blocks.unwrap_or(DEFAULT_DECAY_AFTER_BLOCKS),
+    decay_amount: msg.decay_amount.unwrap_or(DEFAULT_DECAY_AMOUNT),
+    draw_offer_cooldown_blocks: msg.draw_offer_cooldown_blocks.unwrap_or(0),
+    high_rating_k_factor: msg.high_rating_k_factor.unwrap_or(DEFAULT_K_FACTOR),
+    high_rating_threshold: msg.high_rating_threshold.unwrap_or(DEFAULT_HIGH_RATING_THRESHOLD),
+    inactivity_blocks: msg.inactivity_blocks.unwrap_or(DEFAULT_INACTIVITY_BLOCKS),
+    k_factor: msg.k_factor.unwrap_or(DEFAULT_K_FACTOR),
+    max_block_limit: msg.max_block_limit.unwrap_or(DEFAULT_MAX_BLOCK_LIMIT),
+    max_open_challenges: msg
+      .max_open_challenges
+      .unwrap_or(DEFAULT_MAX_OPEN_CHALLENGES),
+    max_pairwise_challenges: msg.max_pairwise_challenges,
+    provisional_games: msg.provisional_games.unwrap_or(DEFAULT_PROVISIONAL_GAMES),
+    provisional_k_factor: msg.provisional_k_factor.unwrap_or(DEFAULT_K_FACTOR),
+    rapid_max_blocks: msg.rapid_max_blocks.unwrap_or(DEFAULT_RAPID_MAX_BLOCKS),
+    rate_draws: msg.rate_draws.unwrap_or(true),
+    rating_ceiling: msg.rating_ceiling,
+    rating_floor: msg.rating_floor.unwrap_or(DEFAULT_RATING_FLOOR),
+    refund_challenge_fee: msg.refund_challenge_fee.unwrap_or(false),
+    require_resign_confirmation: msg.require_resign_confirmation.unwrap_or(false),
+    starting_rating: msg.starting_rating.unwrap_or_else(|| EloRating::new().into()),
+    treasury: msg
+      .treasury
+      .map(|treasury| deps.api.addr_validate(&treasury))
+      .transpose()?,
+  };
+  check_rating_bounds(&config)?;
+  check_time_controls(&config)?;
+  set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
   let state = State {
     owner: info.sender.clone(),
+    pending_owner: None,
   };
-  set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
   STATE.save(deps.storage, &state)?;
+  CONFIG.save(deps.storage, &config)?;
 
   Ok(Response::new()
     .add_attribute("method", "instantiate")
     .add_attribute("owner", info.sender))
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+  let stored = get_contract_version(deps.storage)?;
+  if stored.contract != CONTRACT_NAME {
+    return Err(ContractError::InvalidMigration {});
+  }
+  let from_version = parse_version(&stored.version);
+  if from_version > parse_version(CONTRACT_VERSION) {
+    return Err(ContractError::InvalidMigration {});
+  }
+
+  // games and challenges only gained optional fields, which load as None,
+  // so state is the only thing to rewrite, plus indexes added since
+  migrate_state(deps.storage)?;
+  // the rest rewrite every game or player, so they only run when upgrading
+  // from storage written before the version that changed it
+  if from_version < parse_version("0.1.0") {
+    migrate_game_indexes(deps.storage)?;
+    migrate_draw_claims(deps.storage)?;
+    migrate_open_challenge_count(deps.storage)?;
+    migrate_ratings(deps.storage)?;
+    migrate_rating_history(deps.storage)?;
+    migrate_stats_counts(deps.storage)?;
+    migrate_player_counts(deps.storage)?;
+  }
+
+  set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+  Ok(Response::new()
+    .add_attribute("method", "migrate")
+    .add_attribute("from_version", stored.version)
+    .add_attribute("to_version", CONTRACT_VERSION))
+}
+
+// only completion hook failures come back here, the game has ended regardless
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+  match msg.result {
+    SubMsgResult::Err(err) => Ok(Response::new()
+      .add_attribute("action", "completion_hook_failed")
+      .add_attribute("error", err)),
+    SubMsgResult::Ok(_) => Ok(Response::new()),
+  }
+}
+
+// "1.2.3" as [1, 2, 3] for comparing versions
+fn parse_version(version: &str) -> Vec<u64> {
+  version
+    .split('.')
+    .map(|part| part.parse().unwrap_or_default())
+    .collect()
+}
+
+// move config out of state, filling in settings added since it was written
+fn migrate_state(store: &mut dyn Storage) -> StdResult<()> {
+  let stored = match STORED_CONFIG.may_load(store)? {
+    Some(stored) => stored,
+    None => LEGACY_CONFIG.load(store)?,
+  };
+  let config = Config {
+    abandon_threshold: stored.abandon_threshold.unwrap_or(DEFAULT_ABANDON_THRESHOLD),
+    auto_queen_promotion: stored.auto_queen_promotion.unwrap_or(true),
+    blitz_max_blocks: stored.blitz_max_blocks.unwrap_or(DEFAULT_BLITZ_MAX_BLOCKS),
+    bullet_max_blocks: stored.bullet_max_blocks.unwrap_or(DEFAULT_BULLET_MAX_BLOCKS),
+    challenge_fee: stored.challenge_fee,
+    completion_hook: stored.completion_hook,
+    decay_after_blocks: stored.decay_after_blocks.unwrap_or(DEFAULT_DECAY_AFTER_BLOCKS),
+    decay_amount: stored.decay_amount.unwrap_or(DEFAULT_DECAY_AMOUNT),
+    draw_offer_cooldown_blocks: stored.draw_offer_cooldown_blocks.unwrap_or(0),
+    high_rating_k_factor: stored.high_rating_k_factor.unwrap_or(DEFAULT_K_FACTOR),
+    high_rating_threshold: stored.high_rating_threshold.unwrap_or(DEFAULT_HIGH_RATING_THRESHOLD),
+    inactivity_blocks: stored.inactivity_blocks.unwrap_or(DEFAULT_INACTIVITY_BLOCKS),
+    k_factor: stored.k_factor.unwrap_or(DEFAULT_K_FACTOR),
+    max_block_limit: stored.max_block_limit.unwrap_or(DEFAULT_MAX_BLOCK_LIMIT),
+    max_open_challenges: stored
+      .max_open_challenges
+      .unwrap_or(DEFAULT_MAX_OPEN_CHALLENGES),
+    max_pairwise_challenges: stored.max_pairwise_challenges,
+    provisional_games: stored.provisional_games.unwrap_or(DEFAULT_PROVISIONAL_GAMES),
+    provisional_k_factor: stored.provisional_k_factor.unwrap_or(DEFAULT_K_FACTOR),
+    rapid_max_blocks: stored.rapid_max_blocks.unwrap_or(DEFAULT_RAPID_MAX_BLOCKS),
+    rate_draws: stored.rate_draws.unwrap_or(true),
+    rating_ceiling: stored.rating_ceiling,
+    rating_floor: stored.rating_floor.unwrap_or(DEFAULT_RATING_FLOOR),
+    refund_challenge_fee: stored.refund_challenge_fee.unwrap_or(false),
+    require_resign_confirmation: stored.require_resign_confirmation.unwrap_or(false),
+    starting_rating: stored.starting_rating.unwrap_or_else(|| EloRating::new().into()),
+    treasury: stored.treasury,
+  };
+  CONFIG.save(store, &config)?;
+  // the old combined state loads as just the owner, saving drops the rest
+  let state = STATE.load(store)?;
+  STATE.save(store, &state)
+}
+
+// index games for GetActiveGames, GetGamesByResult and GetGamesStartedBetween
+fn migrate_game_indexes(store: &mut dyn Storage) -> StdResult<()> {
+  let games_map = get_games_map();
+  let games = games_map
+    .range(store, None, None, Order::Ascending)
+    .collect::<StdResult<Vec<_>>>()?;
+  for (game_id, game) in games.iter() {
+    // saving again writes any missing index entries
+    games_map.save(store, *game_id, game)?;
+    if game.status.is_none() {
+      add_active_game(store, game)?;
+    }
+  }
+  Ok(())
+}
+
+// claimed draws were saved as DrawDeclared, which read as a declined offer,
+// they load as DrawClaimed but stay indexed under the old key until moved
+fn migrate_draw_claims(store: &mut dyn Storage) -> StdResult<()> {
+  let legacy_index: MultiIndex<String, CwChessGame, u64> =
+    MultiIndex::new(|_| "DrawDeclared".to_string(), "games", "games__status");
+  let games = legacy_index
+    .prefix("DrawDeclared".to_string())
+    .range(store, None, None, Order::Ascending)
+    .collect::<StdResult<Vec<_>>>()?;
+  let games_map = get_games_map();
+  for (game_id, game) in games {
+    legacy_index.remove(store, &game_id.joined_key(), &game)?;
+    // saving again writes the game as draw_claimed and indexes it under that
+    games_map.save(store, game_id, &game)?;
+  }
+  Ok(())
+}
+
+// count the open challenges once, later kept up to date on create and removal
+fn migrate_open_challenge_count(store: &mut dyn Storage) -> StdResult<()> {
+  let count = get_challenges_map()
+    .idx
+    .opponent
+    .prefix(Addr::unchecked("none"))
+    .keys_raw(store, None, None, Order::Ascending)
+    .count() as u64;
+  OPEN_CHALLENGE_COUNT.save(store, &count)
+}
+
+// ratings from before time controls become correspondence ratings
+fn migrate_ratings(store: &mut dyn Storage) -> StdResult<()> {
+  let ratings = LEGACY_RATINGS
+    .range(store, None, None, Order::Ascending)
+    .collect::<StdResult<Vec<_>>>()?;
+  for (addr, rating) in ratings {
+    RATINGS.save(store, (addr.clone(), TimeControl::Correspondence.as_str()), &rating)?;
+    LEGACY_RATINGS.remove(store, addr);
+  }
+  Ok(())
+}
+
+// history from before time controls moves to the time control of each game
+fn migrate_rating_history(store: &mut dyn Storage) -> StdResult<()> {
+  let config = CONFIG.load(store)?;
+  let history = LEGACY_RATING_HISTORY
+    .range(store, None, None, Order::Ascending)
+    .collect::<StdResult<Vec<_>>>()?;
+  for ((addr, game_id), rating) in history {
+    let game = get_games_map().load(store, game_id)?;
+    let time_control = config.time_control(game.block_limit);
+    RATING_HISTORY.save(store, (addr.clone(), time_control.as_str(), game_id), &rating)?;
+    LEGACY_RATING_HISTORY.remove(store, (addr, game_id));
+  }
+  Ok(())
+}
+
+// count finished games and players once, later kept up to date as they change
+fn migrate_stats_counts(store: &mut dyn Storage) -> StdResult<()> {
+  let mut finished_games = 0;
+  for item in get_games_map().range(store, None, None, Order::Ascending) {
+    if item?.1.status.is_some() {
+      finished_games += 1;
+    }
+  }
+  FINISHED_GAME_COUNT.save(store, &finished_games)?;
+  // keys are sorted by player, so each player's time controls are together
+  let mut players = 0;
+  let mut last_player = None;
+  for key in RATINGS.keys(store, None, None, Order::Ascending) {
+    let (player, _) = key?;
+    if last_player.as_ref() != Some(&player) {
+      players += 1;
+      last_player = Some(player);
+    }
+  }
+  PLAYER_COUNT.save(store, &players)
+}
+
+// count each player's challenges and games once, later kept up to date in PLAYER_STATS
+fn migrate_player_counts(store: &mut dyn Storage) -> StdResult<()> {
+  let mut counts: BTreeMap<Addr, PlayerStats> = BTreeMap::new();
+  for item in get_challenges_map().range(store, None, None, Order::Ascending) {
+    counts.entry(item?.1.created_by).or_default().challenges += 1;
+  }
+  for item in get_games_map().range(store, None, None, Order::Ascending) {
+    let game = item?.1;
+    let white = counts.entry(game.player1.clone()).or_default();
+    white.games_started += 1;
+    if game.player2 != game.player1 {
+      white.record_color(CwChessColor::White);
+      let black = counts.entry(game.player2).or_default();
+      black.games_started += 1;
+      black.record_color(CwChessColor::Black);
+    }
+  }
+  // players counted before keep their stats, with the counts set again
+  for addr in PLAYER_STATS.keys(store, None, None, Order::Ascending).collect::<StdResult<Vec<_>>>()? {
+    counts.entry(addr).or_default();
+  }
+  for (addr, counted) in counts {
+    let mut stats = PLAYER_STATS.may_load(store, addr.clone())?.unwrap_or_default();
+    stats.black_games = counted.black_games;
+    stats.challenges = counted.challenges;
+    stats.games_started = counted.games_started;
+    stats.white_games = counted.white_games;
+    PLAYER_STATS.save(store, addr, &stats)?;
+  }
+  Ok(())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
   deps: DepsMut,
@@ -49,48 +349,296 @@ pub fn execute(
     ExecuteMsg::AcceptChallenge { challenge_id } => {
       execute_accept_challenge(deps, env, info, challenge_id)
     }
+    ExecuteMsg::AcceptOwnership {} => execute_accept_ownership(deps, info),
     ExecuteMsg::CancelChallenge { challenge_id } => {
       execute_cancel_challenge(deps, info, challenge_id)
     }
+    ExecuteMsg::ClaimVictoryOnAbandonment { game_id } => {
+      execute_claim_victory_on_abandonment(deps, env, info, game_id)
+    }
     ExecuteMsg::CreateChallenge {
+      allow_self_play,
+      block_limit,
+      chess960,
+      dedup_key,
+      expires_in_blocks,
+      increment_blocks,
+      num_games,
+      odds,
+      opponent,
+      play_as,
+      start_fen,
+    } => execute_create_challenge(
+      deps,
+      env,
+      info,
+      allow_self_play,
       block_limit,
+      chess960,
+      dedup_key,
+      expires_in_blocks,
+      increment_blocks,
+      num_games,
+      odds,
       opponent,
       play_as,
-    } => execute_create_challenge(deps, env, info, block_limit, opponent, play_as),
+      start_fen,
+    ),
+    ExecuteMsg::AbortGame { game_id } => execute_abort_game(deps, env, info, game_id),
+    ExecuteMsg::AdminVoidGame { game_id } => execute_admin_void_game(deps, env, info, game_id),
+    ExecuteMsg::ImportGame {
+      pgn,
+      player1,
+      player2,
+    } => execute_import_game(deps, env, info, pgn, player1, player2),
+    ExecuteMsg::AnnotateMove {
+      game_id,
+      move_number,
+      text,
+    } => execute_annotate_move(deps, info, game_id, move_number, text),
+    ExecuteMsg::BatchTurn { actions, game_id } => execute_batch_turn(deps, env, info, actions, game_id),
+    ExecuteMsg::CreateTournament {
+      block_limit,
+      format,
+      name,
+      size,
+    } => execute_create_tournament(deps, info, block_limit, format, name, size),
+    ExecuteMsg::JoinTournament { tournament_id } => {
+      execute_join_tournament(deps, env, info, tournament_id)
+    }
     ExecuteMsg::DeclareTimeout { game_id } => execute_declare_timeout(deps, env, game_id),
+    ExecuteMsg::ClaimTimeoutWin { game_id } => execute_claim_timeout_win(deps, env, info, game_id),
+    ExecuteMsg::EnterQueue {
+      block_limit,
+      rating_range,
+    } => execute_enter_queue(deps, env, info, block_limit, rating_range),
+    ExecuteMsg::LeaveQueue {} => execute_leave_queue(deps, info),
+    ExecuteMsg::DecayInactiveRating { player } => execute_decay_inactive_rating(deps, env, player),
+    ExecuteMsg::ExpireChallenge { challenge_id } => {
+      execute_expire_challenge(deps, env, challenge_id)
+    }
+    ExecuteMsg::OfferRematch { game_id } => execute_offer_rematch(deps, env, info, game_id),
+    ExecuteMsg::ProposeNewOwner { new_owner } => execute_propose_new_owner(deps, info, new_owner),
+    ExecuteMsg::AgreeResult { game_id, result } => execute_agree_result(deps, env, info, game_id, result),
+    ExecuteMsg::Resign { confirm, game_id } => execute_resign(deps, env, info, confirm, game_id),
+    ExecuteMsg::SetPremove { game_id, move_str } => execute_set_premove(deps, info, game_id, move_str),
     ExecuteMsg::Turn { action, game_id } => execute_turn(deps, env, info, action, game_id),
+    ExecuteMsg::UpdateConfig {
+      abandon_threshold,
+      auto_queen_promotion,
+      blitz_max_blocks,
+      bullet_max_blocks,
+      challenge_fee,
+      completion_hook,
+      decay_after_blocks,
+      decay_amount,
+      draw_offer_cooldown_blocks,
+      high_rating_k_factor,
+      high_rating_threshold,
+      inactivity_blocks,
+      k_factor,
+      max_block_limit,
+      max_open_challenges,
+      max_pairwise_challenges,
+      provisional_games,
+      provisional_k_factor,
+      rapid_max_blocks,
+      rate_draws,
+      rating_ceiling,
+      rating_floor,
+      refund_challenge_fee,
+      require_resign_confirmation,
+      starting_rating,
+      treasury,
+    } => execute_update_config(
+      deps,
+      info,
+      abandon_threshold,
+      auto_queen_promotion,
+      blitz_max_blocks,
+      bullet_max_blocks,
+      challenge_fee,
+      completion_hook,
+      decay_after_blocks,
+      decay_amount,
+      draw_offer_cooldown_blocks,
+      high_rating_k_factor,
+      high_rating_threshold,
+      inactivity_blocks,
+      k_factor,
+      max_block_limit,
+      max_open_challenges,
+      max_pairwise_challenges,
+      provisional_games,
+      provisional_k_factor,
+      rapid_max_blocks,
+      rate_draws,
+      rating_ceiling,
+      rating_floor,
+      refund_challenge_fee,
+      require_resign_confirmation,
+      starting_rating,
+      treasury,
+    ),
   }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
   match msg {
     QueryMsg::GetGame {
       game_id
     } => to_binary(&query_get_game(deps, game_id)?),
+    QueryMsg::GetGameDetailed { game_id } => to_binary(&query_get_game_detailed(deps, env, game_id)?),
+    QueryMsg::GetOpponent {
+      game_id,
+      player,
+    } => to_binary(&query_get_opponent(deps, game_id, player)?),
+    QueryMsg::GetBoard {
+      game_id
+    } => to_binary(&query_get_board(deps, game_id)?),
+    QueryMsg::GetTournament {
+      tournament_id
+    } => to_binary(&query_get_tournament(deps, tournament_id)?),
+    QueryMsg::GetOpenChallengeCount {} => to_binary(&query_get_open_challenge_count(deps)?),
+    QueryMsg::GetContractStats {} => to_binary(&query_get_contract_stats(deps)?),
+    QueryMsg::GetConfig {} => to_binary(&query_get_config(deps)?),
+    QueryMsg::GetQueue {} => to_binary(&query_get_queue(deps)?),
+    QueryMsg::GetMatch {
+      match_id
+    } => to_binary(&query_get_match(deps, match_id)?),
+    QueryMsg::GetTournamentStandings {
+      tournament_id
+    } => to_binary(&query_get_tournament_standings(deps, tournament_id)?),
+    QueryMsg::GetLegalMoves {
+      game_id,
+      square,
+    } => to_binary(&query_get_legal_moves(deps, game_id, &square)?),
+    QueryMsg::GetPlayerStats {
+      player,
+      time_control,
+    } => to_binary(&query_get_player_stats(deps, &player, time_control)?),
+    QueryMsg::GetPlayerCounts { player } => to_binary(&query_get_player_counts(deps, &player)?),
+    QueryMsg::GetHeadToHead {
+      player_a,
+      player_b,
+    } => to_binary(&query_get_head_to_head(deps, &player_a, &player_b)?),
+    QueryMsg::GetClocks {
+      game_id
+    } => to_binary(&query_get_clocks(deps, env, game_id)?),
+    QueryMsg::GetGameHistory {
+      game_id
+    } => to_binary(&query_get_game_history(deps, game_id)?),
+    QueryMsg::GetFenAtMove { game_id, ply } => to_binary(&query_get_fen_at_move(deps, game_id, ply)?),
+    QueryMsg::GetGameMetrics {
+      game_id
+    } => to_binary(&query_get_game_metrics(deps, game_id)?),
+    QueryMsg::GetAnnotations {
+      game_id
+    } => to_binary(&query_get_annotations(deps, game_id)?),
+    QueryMsg::GetLatestMove {
+      game_id
+    } => to_binary(&query_get_latest_move(deps, game_id)?),
+    QueryMsg::ExportPgn {
+      game_id
+    } => to_binary(&query_export_pgn(deps, env, game_id)?),
+    QueryMsg::GetOpening {
+      game_id
+    } => to_binary(&query_get_opening(deps, game_id)?),
     QueryMsg::GetChallenge {
       challenge_id
     } => to_binary(&query_get_challenge(deps, challenge_id)?),
     QueryMsg::GetChallenges {
       after,
+      hide_expired,
+      limit,
+      max_block_limit,
+      min_block_limit,
+      player
+    } => to_binary(&query_get_challenges(
+      deps,
+      env,
+      after,
+      hide_expired,
+      limit,
+      max_block_limit,
+      min_block_limit,
+      player,
+    )?),
+    QueryMsg::GetIncomingChallenges {
+      after,
+      limit,
+      player
+    } => to_binary(&query_get_incoming_challenges(deps, after, limit, player)?),
+    QueryMsg::GetOutgoingChallenges {
+      after,
+      limit,
+      player
+    } => to_binary(&query_get_outgoing_challenges(deps, after, limit, player)?),
+    QueryMsg::GetGamesByIds {
+      ids
+    } => to_binary(&query_get_games_by_ids(deps, ids)?),
+    QueryMsg::GetActiveGames {
       player
-    } => to_binary(&query_get_challenges(deps, after, player)?),
+    } => to_binary(&query_get_active_games(deps, player)?),
+    QueryMsg::GetPlayerTurnGames { player } => to_binary(&query_get_player_turn_games(deps, player)?),
+    QueryMsg::GetTimedOutGames { after, limit } => {
+      to_binary(&query_get_timed_out_games(deps, env, after, limit)?)
+    }
+    QueryMsg::GetGamesByResult {
+      after,
+      limit,
+      result,
+    } => to_binary(&query_get_games_by_result(deps, after, limit, result)?),
+    QueryMsg::GetGamesStartedBetween {
+      from_block,
+      limit,
+      to_block,
+    } => to_binary(&query_get_games_started_between(deps, from_block, limit, to_block)?),
     QueryMsg::GetGames {
       after,
       game_over,
+      limit,
       player,
-    } => to_binary(&query_get_games(deps, after, game_over, player)?),
+      status_filter,
+    } => to_binary(&query_get_games(deps, after, game_over, limit, player, status_filter)?),
+    QueryMsg::ValidateFen {
+      fen
+    } => to_binary(&query_validate_fen(&fen)),
     QueryMsg::ValidMove {
       game_id,
       player,
       move_str,
     } => to_binary(&query_valid_move(deps, game_id, &player, &move_str)?),
+    QueryMsg::SimulateMove {
+      game_id,
+      move_str,
+    } => to_binary(&query_simulate_move(deps, game_id, &move_str)?),
     QueryMsg::GetRatings {
-    } => to_binary(&query_get_ratings(deps)?),
+      time_control
+    } => to_binary(&query_get_ratings(deps, time_control)?),
+    QueryMsg::GetRating {
+      player,
+      time_control
+    } => to_binary(&query_player_rating(deps, &player, time_control)?),
+    QueryMsg::GetRatingHistory {
+      after,
+      limit,
+      player,
+      time_control,
+    } => to_binary(&query_get_rating_history(deps, after, limit, player, time_control)?),
+    QueryMsg::GetGameRatings {
+      game_id
+    } => to_binary(&query_get_game_ratings(deps, game_id)?),
     QueryMsg::GetTurn {
       game_id,
       player,
     } => to_binary(&query_get_turn(deps, game_id, &player)?),
+    QueryMsg::GetTurnColor { game_id } => to_binary(&query_get_turn_color(deps, game_id)?),
+    QueryMsg::GetTurnInfo {
+      game_id,
+      player,
+    } => to_binary(&query_get_turn_info(deps, env, game_id, player)?),
   }
 }
 
@@ -106,7 +654,7 @@ fn execute_accept_challenge(
   // find challenge
   let challenge = match challenges_map.load(deps.storage, challenge_id) {
     Ok(challenge) => {
-      if challenge.created_by == player {
+      if challenge.created_by == player && !challenge.allow_self_play {
         return Err(ContractError::CannotPlaySelf {});
       }
       if let Some(opponent) = challenge.opponent.clone() {
@@ -114,6 +662,11 @@ fn execute_accept_challenge(
           return Err(ContractError::NotYourChallenge {});
         }
       }
+      if let Some(expires_at_block) = challenge.expires_at_block {
+        if block_start > expires_at_block {
+          return Err(ContractError::ChallengeExpired {});
+        }
+      }
       challenge
     }
     _ => {
@@ -121,38 +674,228 @@ fn execute_accept_challenge(
     }
   };
 
-  def_player_rating(deps.storage, &player)?;
+  def_player_rating(deps.storage, &player, challenge.block_limit)?;
 
-  // create game
-  let game_id = next_game_id(deps.storage)?;
+  // colors and chess960 positions are picked when the game starts,
+  // so the challenger can't know them in advance, and the seed is
+  // saved on the game so anyone can check them
+  let seed = game_seed(challenge_id, block_start, &challenge.created_by, &player);
   let (player1, player2) = CwChessGame::get_player_order(
     challenge.created_by.clone(),
     player,
-    challenge.play_as,
-    block_start,
+    challenge.play_as.clone(),
+    seed,
   );
-  // create game
-  let game = CwChessGame {
-    block_limit: challenge.block_limit,
+  let start_fen = match (&challenge.start_fen, challenge.chess960) {
+    (None, true) => Some(chess960_fen(seed)),
+    (start_fen, _) => start_fen.clone(),
+  };
+  let odds_start = odds_start_fen(&challenge, &player1)?;
+  remove_challenge(deps.storage, &challenge)?;
+  let fee_msg = release_challenge_fee(deps.storage, &challenge, true)?;
+
+  if let Some(num_games) = challenge.num_games.filter(|num_games| *num_games > 1) {
+    return Ok(start_match(
+      deps.storage,
+      block_start,
+      &challenge,
+      num_games,
+      player1,
+      player2,
+      seed,
+      start_fen,
+    )?
+    .add_messages(fee_msg));
+  }
+
+  let (game, game_started) = start_game(
+    deps.storage,
     block_start,
-    fen: DEFAULT_FEN.to_string(),
-    game_id,
+    player1.clone(),
+    player2.clone(),
+    challenge.block_limit,
+    challenge.increment_blocks,
+    Some(seed),
+    odds_start.or(start_fen),
+    challenge.odds.clone(),
+  )?;
+
+  Ok(Response::new()
+    .add_messages(fee_msg)
+    .add_attribute("action", "accept_challenge")
+    .add_attribute("challenge_id", challenge_id.to_string())
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute("player1", player1)
+    .add_attribute("player2", player2)
+    .add_event(game_started))
+}
+
+/// standard start without the odds piece on the creator's side,
+/// None when the challenge has no odds
+fn odds_start_fen(challenge: &Challenge, white: &Addr) -> Result<Option<String>, ContractError> {
+  let odds = match &challenge.odds {
+    Some(odds) => odds,
+    None => return Ok(None),
+  };
+  let color = match &challenge.created_by == white {
+    true => CwChessColor::White,
+    false => CwChessColor::Black,
+  };
+  let fen = odds_fen(odds, &color);
+  if validate_fen(fen).is_err() {
+    return Err(ContractError::InvalidFen {});
+  }
+  Ok(Some(fen.to_string()))
+}
+
+/// start every game of an accepted match challenge at once,
+/// player1 is white in the first game and colors alternate after that
+#[allow(clippy::too_many_arguments)]
+fn start_match(
+  store: &mut dyn Storage,
+  block_start: u64,
+  challenge: &Challenge,
+  num_games: u8,
+  player1: Addr,
+  player2: Addr,
+  seed: u64,
+  start_fen: Option<String>,
+) -> Result<Response, ContractError> {
+  let match_id = next_match_id(store)?;
+  let mut games = vec![];
+  let mut events = vec![];
+  for i in 0..num_games {
+    let (white, black) = if i % 2 == 0 {
+      (player1.clone(), player2.clone())
+    } else {
+      (player2.clone(), player1.clone())
+    };
+    // the creator gives the odds with either color
+    let odds_start = odds_start_fen(challenge, &white)?;
+    let (game, game_started) = start_game(
+      store,
+      block_start,
+      white,
+      black,
+      challenge.block_limit,
+      challenge.increment_blocks,
+      Some(seed),
+      odds_start.or_else(|| start_fen.clone()),
+      challenge.odds.clone(),
+    )?;
+    MATCH_GAMES.save(store, game.game_id, &match_id)?;
+    games.push(game.game_id);
+    events.push(game_started);
+  }
+  let game_match = Match {
+    finished: false,
+    games,
+    match_id,
     player1: player1.clone(),
     player2: player2.clone(),
-    moves: vec![],
-    status: None,
+    winner: None,
   };
-  // update storage
-  let games_map = get_games_map();
-  games_map.save(deps.storage, game_id, &game)?;
-  challenges_map.remove(deps.storage, challenge_id)?;
+  MATCHES.save(store, match_id, &game_match)?;
 
   Ok(Response::new()
     .add_attribute("action", "accept_challenge")
-    .add_attribute("challenge_id", challenge_id.to_string())
-    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("challenge_id", challenge.challenge_id.to_string())
+    .add_attribute("match_id", match_id.to_string())
     .add_attribute("player1", player1)
-    .add_attribute("player2", player2))
+    .add_attribute("player2", player2)
+    .add_events(events))
+}
+
+/// record the match winner once the last game of a match is finished
+fn advance_match(store: &mut dyn Storage, game: &CwChessGame) -> StdResult<Vec<Event>> {
+  let match_id = match MATCH_GAMES.may_load(store, game.game_id)? {
+    Some(match_id) => match_id,
+    None => return Ok(vec![]),
+  };
+  let mut game_match = MATCHES.load(store, match_id)?;
+  if game_match.finished {
+    return Ok(vec![]);
+  }
+  let games = load_match_games(store, &game_match)?;
+  if !round_finished(&games) {
+    return Ok(vec![]);
+  }
+
+  let (player1_points, player2_points) = game_match.score(&games);
+  game_match.finished = true;
+  game_match.winner = game_match.leader(&games);
+  MATCHES.save(store, match_id, &game_match)?;
+  Ok(vec![Event::new("match_ended")
+    .add_attribute("match_id", match_id.to_string())
+    .add_attribute("player1_points", player1_points.to_string())
+    .add_attribute("player2_points", player2_points.to_string())
+    .add_attribute("winner", game_match.winner.map(|winner| winner.to_string()).unwrap_or_default())])
+}
+
+fn load_match_games(store: &dyn Storage, game_match: &Match) -> StdResult<Vec<CwChessGame>> {
+  let games_map = get_games_map();
+  game_match
+    .games
+    .iter()
+    .map(|game_id| games_map.load(store, *game_id))
+    .collect()
+}
+
+/// create and save a new game, returning it with its game_started event
+#[allow(clippy::too_many_arguments)]
+fn start_game(
+  store: &mut dyn Storage,
+  block_start: u64,
+  player1: Addr,
+  player2: Addr,
+  block_limit: Option<u64>,
+  increment_blocks: Option<u64>,
+  seed: Option<u64>,
+  start_fen: Option<String>,
+  odds: Option<OddsType>,
+) -> StdResult<(CwChessGame, Event)> {
+  let game_id = next_game_id(store)?;
+  let game = CwChessGame {
+    block_limit,
+    block_start,
+    clocks: block_limit.map(|block_limit| (block_limit, block_limit)),
+    fen: start_fen.clone().unwrap_or_else(|| DEFAULT_FEN.to_string()),
+    game_id,
+    increment_blocks,
+    last_draw_offers: (None, None),
+    moves: vec![],
+    odds,
+    pending_result: None,
+    pending_takeback: None,
+    player1,
+    player2,
+    premove: None,
+    seed,
+    start_fen,
+    status: None,
+  };
+  get_games_map().save(store, game_id, &game)?;
+  add_active_game(store, &game)?;
+  add_started_game(store, &game)?;
+
+  let game_started = Event::new("game_started")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("player1", game.player1.clone())
+    .add_attribute("player2", game.player2.clone())
+    .add_attribute("block_start", block_start.to_string())
+    .add_attribute(
+      "block_limit",
+      block_limit
+        .map(|block_limit| block_limit.to_string())
+        .unwrap_or_else(|| "none".to_string()),
+    )
+    .add_attribute(
+      "seed",
+      seed
+        .map(|seed| seed.to_string())
+        .unwrap_or_else(|| "none".to_string()),
+    );
+  Ok((game, game_started))
 }
 
 fn execute_cancel_challenge(
@@ -173,185 +916,1669 @@ fn execute_cancel_challenge(
       return Err(ContractError::ChallengeNotFound {});
     }
   };
-  challenges_map.remove(deps.storage, challenge.challenge_id)?;
+  remove_challenge(deps.storage, &challenge)?;
 
   Ok(Response::new()
+    .add_messages(release_challenge_fee(deps.storage, &challenge, false)?)
     .add_attribute("action", "cancel_challenge")
     .add_attribute("challenge_id", challenge_id.to_string()))
 }
 
-/// save player rating
-fn def_player_rating(
-  storage: &mut dyn Storage,
-  addr: &Addr
-) -> StdResult<()> {
-  if let None = RATINGS.may_load(storage, addr.clone())? {
-    RATINGS.save(storage, addr.clone(), &EloRating::new().into())?;
-  };
-  Ok(())
-}
-
-fn execute_create_challenge(
+fn execute_abort_game(
   deps: DepsMut,
   env: Env,
   info: MessageInfo,
-  block_limit: Option<u64>,
-  opponent: Option<String>,
-  play_as: Option<CwChessColor>,
+  game_id: u64,
 ) -> Result<Response, ContractError> {
-  let block_created = env.block.height;
-  let challenge_id = next_challenge_id(deps.storage)?;
-  let created_by = info.sender;
-  let opponent = match opponent {
-    Some(addr) => {
-      let addr = deps.api.addr_validate(&addr)?;
-      if created_by == addr {
-        return Err(ContractError::CannotPlaySelf {});
+  // a voided pairing would hand the bracket slot or match game to one side
+  if TOURNAMENT_GAMES.has(deps.storage, game_id) || MATCH_GAMES.has(deps.storage, game_id) {
+    return Err(ContractError::CannotAbortEventGame {});
+  }
+  let game = get_games_map().update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+    match game {
+      None => Err(ContractError::GameNotFound {}),
+      Some(mut game) => {
+        game.abort(&info.sender)?;
+        Ok(game)
       }
-      Some(addr)
     }
-    None => None,
-  };
-  let challenge = Challenge {
-    block_created,
-    block_limit,
-    challenge_id,
-    created_by: created_by.clone(),
-    opponent: opponent.clone(),
-    play_as,
-  };
-  let challenges_map = get_challenges_map();
-  challenges_map.save(deps.storage, challenge_id, &challenge)?;
-
-  def_player_rating(deps.storage, &created_by)?;
-  if let Some(opponent) = &opponent {
-    def_player_rating(deps.storage, &opponent)?;
-  }
+  })?;
 
-  Ok(Response::new()
-    .add_attribute("action", "create_challenge")
-    .add_attribute("challenge_id", challenge_id.to_string())
-    .add_attribute("created_by", created_by)
-    .add_attribute(
-      "opponent",
-      opponent.unwrap_or_else(|| Addr::unchecked("none")),
-    ))
+  Ok(
+    Response::new()
+      .add_attribute("action", "abort_game")
+      .add_attribute("game_id", game.game_id.to_string())
+      .add_attribute("player", info.sender)
+      .add_events(finish_game(deps.storage, env.block.height, &game, &CwChessGameOver::Voided)?)
+      .add_submessages(completion_hook_msgs(deps.storage, &game, &CwChessGameOver::Voided)?),
+  )
 }
 
-fn execute_declare_timeout(
+/// owner escape hatch for stuck games, ends the game without changing ratings
+fn execute_admin_void_game(
   deps: DepsMut,
   env: Env,
+  info: MessageInfo,
   game_id: u64,
 ) -> Result<Response, ContractError> {
-  let games_map = get_games_map();
-  let height = env.block.height;
-  let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+  let state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
+  }
+  let game = get_games_map().update(deps.storage, game_id, |game| -> Result<_, ContractError> {
     match game {
       None => Err(ContractError::GameNotFound {}),
-      Some(mut game) => match game.check_timeout(height)? {
-        None => Err(ContractError::GameNotTimedOut {}),
-        _ => Ok(game),
-      },
+      Some(mut game) => {
+        game.void()?;
+        Ok(game)
+      }
     }
   })?;
 
-  Ok(Response::new()
-    .add_attribute("action", "declare_timeout")
-    .add_attribute("game_id", game.game_id.to_string()))
+  Ok(
+    Response::new()
+      .add_attribute("action", "admin_void_game")
+      .add_attribute("game_id", game.game_id.to_string())
+      .add_events(finish_game(deps.storage, env.block.height, &game, &CwChessGameOver::Voided)?)
+      .add_submessages(completion_hook_msgs(deps.storage, &game, &CwChessGameOver::Voided)?),
+  )
 }
 
-/// get the player's rating
-fn get_player_rating(
-  store:&mut dyn Storage,
-  addr: &Addr
-) -> StdResult<u64> {
-  if let Some(rating) = RATINGS.may_load(store, addr.clone())? {
-    Ok(rating)
-  } else {
-    Ok(EloRating::new().into())
+fn execute_import_game(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  pgn: String,
+  player1: String,
+  player2: String,
+) -> Result<Response, ContractError> {
+  let state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
   }
-}
+  let player1 = deps.api.addr_validate(&player1)?;
+  let player2 = deps.api.addr_validate(&player2)?;
+  let game_id = next_game_id(deps.storage)?;
+  let game = CwChessGame::from_pgn(game_id, env.block.height, &pgn, player1, player2)?;
+  // imported games are always finished
+  let status = game.status.clone().ok_or(ContractError::InvalidPgn {})?;
+  get_games_map().save(deps.storage, game_id, &game)?;
+  add_started_game(deps.storage, &game)?;
+  add_finished_game(deps.storage)?;
 
-/// update the player's rating
-fn update_player_rating(
-  store: &mut dyn Storage,
-  addr: &Addr,
-  rating: u64
-) -> StdResult<()> {
-  RATINGS.update(store, addr.clone(), |_| -> StdResult<u64> {
-    Ok(rating)
-  })?;
-  Ok(())
+  Ok(Response::new()
+    .add_attribute("action", "import_game")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("status", format!("{:?}", status)))
 }
 
-// update the players rating
-fn update_players_rating(
-  store: &mut dyn Storage,
-  game: &CwChessGame,
-  outcome: Outcomes,
-) -> StdResult<()> {
-  let player1 = &game.player1;
-  let player2 = &game.player2;
-
-  let (rate1, rate2) = elo(
-    &get_player_rating(store, player1)?.into(),
-    &get_player_rating(store, player2)?.into(),
-    &outcome,
-    &EloConfig::new(),
-  );
-  update_player_rating(store, player1, rate1.into())?;
-  update_player_rating(store, player2, rate2.into())?;
+/// let either player note a move that has been played
+fn execute_annotate_move(
+  deps: DepsMut,
+  info: MessageInfo,
+  game_id: u64,
+  move_number: u16,
+  text: String,
+) -> Result<Response, ContractError> {
+  let game = match get_games_map().may_load(deps.storage, game_id)? {
+    Some(game) => game,
+    None => return Err(ContractError::GameNotFound {}),
+  };
+  if game.player_color(&info.sender).is_none() {
+    return Err(ContractError::NotYourGame {});
+  }
+  if text.is_empty() || text.chars().count() > MAX_ANNOTATION_LENGTH {
+    return Err(ContractError::InvalidAnnotation {});
+  }
+  let moves_played = game.get_san_moves()?.len();
+  if move_number == 0 || move_number as usize > moves_played {
+    return Err(ContractError::MoveNotFound {});
+  }
+  ANNOTATIONS.save(deps.storage, (game_id, move_number), &text)?;
 
-  Ok(())
+  Ok(Response::new()
+    .add_attribute("action", "annotate_move")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("move_number", move_number.to_string())
+    .add_attribute("annotated_by", info.sender))
 }
 
-fn execute_turn(
+/// remove a challenge nobody accepted within abandon_threshold blocks
+fn execute_expire_challenge(
   deps: DepsMut,
   env: Env,
-  info: MessageInfo,
+  challenge_id: u64,
+) -> Result<Response, ContractError> {
+  let challenges_map = get_challenges_map();
+  let challenge = match challenges_map.may_load(deps.storage, challenge_id)? {
+    Some(challenge) => challenge,
+    None => return Err(ContractError::ChallengeNotFound {}),
+  };
+  let config = CONFIG.load(deps.storage)?;
+  if env.block.height < challenge.block_created.saturating_add(config.abandon_threshold) {
+    return Err(ContractError::ChallengeNotAbandoned {});
+  }
+  remove_challenge(deps.storage, &challenge)?;
+
+  Ok(Response::new()
+    .add_messages(release_challenge_fee(deps.storage, &challenge, false)?)
+    .add_attribute("action", "expire_challenge")
+    .add_attribute("challenge_id", challenge_id.to_string())
+    .add_attribute("created_by", challenge.created_by))
+}
+
+/// save player rating in the time control of block_limit
+fn def_player_rating(
+  storage: &mut dyn Storage,
+  addr: &Addr,
+  block_limit: Option<u64>,
+) -> Result<(), ContractError> {
+  let config = CONFIG.load(storage)?;
+  let time_control = config.time_control(block_limit);
+  if RATINGS.may_load(storage, (addr.clone(), time_control.as_str()))?.is_none() {
+    if !has_rating(storage, addr) {
+      add_player(storage)?;
+    }
+    RATINGS.save(storage, (addr.clone(), time_control.as_str()), &config.starting_rating)?;
+  };
+  Ok(())
+}
+
+/// block_limit must leave time to play, and not be absurdly long
+fn check_block_limit(
+  store: &dyn Storage,
+  block_limit: Option<u64>,
+  increment_blocks: Option<u64>,
+) -> Result<(), ContractError> {
+  match block_limit {
+    Some(block_limit) => {
+      let config = CONFIG.load(store)?;
+      if block_limit < MIN_BLOCK_LIMIT || block_limit > config.max_block_limit {
+        return Err(ContractError::InvalidBlockLimit {});
+      }
+    }
+    // increment only applies to a block_limit clock
+    None => {
+      if increment_blocks.is_some() {
+        return Err(ContractError::InvalidBlockLimit {});
+      }
+    }
+  }
+  Ok(())
+}
+
+/// limit number of open challenges per player
+fn check_open_challenges(
+  store: &dyn Storage,
+  addr: &Addr,
+) -> Result<(), ContractError> {
+  let config = CONFIG.load(store)?;
+  let open_challenges = get_challenges_map()
+    .idx
+    .created_by
+    .prefix(addr.clone())
+    .keys_raw(store, None, None, Order::Ascending)
+    .count() as u64;
+  if open_challenges >= config.max_open_challenges {
+    return Err(ContractError::TooManyOpenChallenges {});
+  }
+  Ok(())
+}
+
+/// limit number of challenges a player directs at the same opponent
+fn check_pairwise_challenges(
+  store: &dyn Storage,
+  addr: &Addr,
+  opponent: &Addr,
+) -> Result<(), ContractError> {
+  let max_pairwise_challenges = match CONFIG.load(store)?.max_pairwise_challenges {
+    Some(max_pairwise_challenges) => max_pairwise_challenges,
+    None => return Ok(()),
+  };
+  let mut pairwise_challenges = 0;
+  for item in get_challenges_map()
+    .idx
+    .created_by
+    .prefix(addr.clone())
+    .range(store, None, None, Order::Ascending)
+  {
+    if item?.1.opponent.as_ref() == Some(opponent) {
+      pairwise_challenges += 1;
+    }
+  }
+  if pairwise_challenges >= max_pairwise_challenges {
+    return Err(ContractError::TooManyPairwiseChallenges {});
+  }
+  Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute_create_challenge(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  allow_self_play: bool,
+  block_limit: Option<u64>,
+  chess960: bool,
+  dedup_key: Option<String>,
+  expires_in_blocks: Option<u64>,
+  increment_blocks: Option<u64>,
+  num_games: Option<u8>,
+  odds: Option<OddsType>,
+  opponent: Option<String>,
+  play_as: Option<CwChessColor>,
+  start_fen: Option<String>,
+) -> Result<Response, ContractError> {
+  let block_created = env.block.height;
+  let created_by = info.sender;
+  let funds = info.funds;
+  let challenges_map = get_challenges_map();
+  // a retried create gets the challenge made the first time, while it can still be accepted
+  if let Some(dedup_key) = &dedup_key {
+    let existing = match CHALLENGE_DEDUP.may_load(deps.storage, (created_by.clone(), dedup_key.clone()))? {
+      Some(challenge_id) => challenges_map.may_load(deps.storage, challenge_id)?,
+      None => None,
+    };
+    if let Some(challenge) = existing.filter(|c| c.expires_at_block.is_none_or(|block| block_created <= block)) {
+      // the first create paid the fee, so anything sent again goes back
+      let refund = (!funds.is_empty()).then(|| BankMsg::Send {
+        to_address: created_by.to_string(),
+        amount: funds,
+      });
+      return Ok(Response::new()
+        .add_messages(refund)
+        .add_attribute("action", "create_challenge")
+        .add_attribute("challenge_id", challenge.challenge_id.to_string())
+        .add_attribute("created_by", created_by)
+        .add_attribute(
+          "opponent",
+          challenge.opponent.unwrap_or_else(|| Addr::unchecked("none")),
+        )
+        .add_attribute("duplicate", "true"));
+    }
+  }
+  check_open_challenges(deps.storage, &created_by)?;
+  check_block_limit(deps.storage, block_limit, increment_blocks)?;
+  if let Some(fen) = &start_fen {
+    if validate_fen(fen).is_err() {
+      return Err(ContractError::InvalidFen {});
+    }
+  }
+  if let Some(num_games) = num_games {
+    if !(1..=MAX_MATCH_GAMES).contains(&num_games) {
+      return Err(ContractError::InvalidNumGames {});
+    }
+  }
+  // odds are given from the standard starting position
+  if odds.is_some() && (chess960 || start_fen.is_some()) {
+    return Err(ContractError::InvalidOdds {});
+  }
+  let expires_at_block = match expires_in_blocks {
+    Some(blocks) => Some(block_created.checked_add(blocks).ok_or(ContractError::InvalidExpiry {})?),
+    None => None,
+  };
+  let (fee, fee_msgs) = take_challenge_fee(deps.storage, &created_by, funds)?;
+
+  let challenge_id = next_challenge_id(deps.storage)?;
+  let opponent = match opponent {
+    Some(addr) => {
+      let addr = deps.api.addr_validate(&addr)?;
+      if created_by == addr && !allow_self_play {
+        return Err(ContractError::CannotPlaySelf {});
+      }
+      check_pairwise_challenges(deps.storage, &created_by, &addr)?;
+      Some(addr)
+    }
+    None => None,
+  };
+  let challenge = Challenge {
+    allow_self_play,
+    block_created,
+    block_limit,
+    challenge_id,
+    chess960,
+    created_by: created_by.clone(),
+    dedup_key: dedup_key.clone(),
+    expires_at_block,
+    fee,
+    increment_blocks,
+    num_games,
+    odds,
+    opponent: opponent.clone(),
+    play_as,
+    start_fen,
+  };
+  add_challenge(deps.storage, &challenge)?;
+
+  def_player_rating(deps.storage, &created_by, block_limit)?;
+  if let Some(opponent) = &opponent {
+    def_player_rating(deps.storage, opponent, block_limit)?;
+  }
+
+  Ok(Response::new()
+    .add_messages(fee_msgs)
+    .add_attribute("action", "create_challenge")
+    .add_attribute("challenge_id", challenge_id.to_string())
+    .add_attribute("created_by", created_by)
+    .add_attribute(
+      "opponent",
+      opponent.unwrap_or_else(|| Addr::unchecked("none")),
+    ))
+}
+
+/// charge the challenge fee from the creator's funds, returning the fee to hold
+/// on the challenge and the transfers paying the treasury and giving back change
+fn take_challenge_fee(
+  store: &dyn Storage,
+  created_by: &Addr,
+  funds: Vec<Coin>,
+) -> Result<(Option<Coin>, Vec<BankMsg>), ContractError> {
+  let config = CONFIG.load(store)?;
+  let fee = match config.challenge_fee() {
+    Some(fee) => fee.clone(),
+    None => return Ok((None, vec![])),
+  };
+  let paid = funds
+    .iter()
+    .filter(|coin| coin.denom == fee.denom)
+    .map(|coin| coin.amount)
+    .sum::<Uint128>();
+  if paid < fee.amount {
+    return Err(ContractError::ChallengeFeeNotPaid {});
+  }
+  let mut change = funds.into_iter().filter(|coin| coin.denom != fee.denom).collect::<Vec<_>>();
+  if paid > fee.amount {
+    change.push(Coin::new((paid - fee.amount).u128(), fee.denom.clone()));
+  }
+  let mut msgs = vec![];
+  if !change.is_empty() {
+    msgs.push(BankMsg::Send {
+      to_address: created_by.to_string(),
+      amount: change,
+    });
+  }
+  if config.refund_challenge_fee {
+    return Ok((Some(fee), msgs));
+  }
+  msgs.push(BankMsg::Send {
+    to_address: treasury(store)?.to_string(),
+    amount: vec![fee],
+  });
+  Ok((None, msgs))
+}
+
+/// pay out the fee held on a challenge that's gone, to the treasury
+/// if it was accepted and back to the creator if not
+fn release_challenge_fee(
+  store: &dyn Storage,
+  challenge: &Challenge,
+  accepted: bool,
+) -> StdResult<Option<BankMsg>> {
+  let fee = match &challenge.fee {
+    Some(fee) => fee.clone(),
+    None => return Ok(None),
+  };
+  let to_address = if accepted {
+    treasury(store)?.to_string()
+  } else {
+    challenge.created_by.to_string()
+  };
+  Ok(Some(BankMsg::Send {
+    to_address,
+    amount: vec![fee],
+  }))
+}
+
+fn execute_declare_timeout(
+  deps: DepsMut,
+  env: Env,
+  game_id: u64,
+) -> Result<Response, ContractError> {
+  settle_timeout(deps, env, game_id, None)
+}
+
+fn execute_claim_timeout_win(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  game_id: u64,
+) -> Result<Response, ContractError> {
+  settle_timeout(deps, env, game_id, Some(info.sender))
+}
+
+/// end a timed out game like any other finished game,
+/// claimant has to be the player who didn't time out
+fn settle_timeout(
+  deps: DepsMut,
+  env: Env,
+  game_id: u64,
+  claimant: Option<Addr>,
+) -> Result<Response, ContractError> {
+  let games_map = get_games_map();
+  let height = env.block.height;
+  let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+    let mut game = match game {
+      None => return Err(ContractError::GameNotFound {}),
+      Some(game) => game,
+    };
+    let claimant_color = match &claimant {
+      Some(claimant) => match game.player_color(claimant) {
+        None => return Err(ContractError::NotYourGame {}),
+        color => color,
+      },
+      None => None,
+    };
+    // the player to move is the one whose clock ran out
+    let timed_out_color = game.turn_color();
+    if game.check_timeout(height)?.is_none() {
+      return Err(ContractError::GameNotTimedOut {});
+    }
+    if claimant_color.is_some() && claimant_color == timed_out_color {
+      return Err(ContractError::CannotClaimOwnTurn {});
+    }
+    Ok(game)
+  })?;
+  let status = game.status.clone().unwrap_or(CwChessGameOver::Voided);
+  let action = match claimant {
+    Some(_) => "claim_timeout_win",
+    None => "declare_timeout",
+  };
+
+  Ok(Response::new()
+    .add_attribute("action", action)
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute("status", format!("{:?}", status))
+    .add_events(finish_game(deps.storage, height, &game, &status)?)
+    .add_submessages(completion_hook_msgs(deps.storage, &game, &status)?))
+}
+
+fn execute_offer_rematch(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  game_id: u64,
+) -> Result<Response, ContractError> {
+  let game = match get_games_map().may_load(deps.storage, game_id)? {
+    Some(game) => game,
+    None => return Err(ContractError::GameNotFound {}),
+  };
+  let created_by = info.sender;
+  // swap colors from the finished game
+  let (opponent, play_as) = match game.player_color(&created_by) {
+    Some(CwChessColor::White) => (game.player2.clone(), CwChessColor::Black),
+    Some(CwChessColor::Black) => (game.player1.clone(), CwChessColor::White),
+    None => return Err(ContractError::NotYourGame {}),
+  };
+  if game.status.is_none() {
+    return Err(ContractError::GameNotOver {});
+  }
+
+  // only one outstanding rematch offer per game
+  let challenges_map = get_challenges_map();
+  if let Some(challenge_id) = REMATCHES.may_load(deps.storage, game_id)? {
+    if challenges_map.may_load(deps.storage, challenge_id)?.is_some() {
+      return Err(ContractError::RematchAlreadyOffered {});
+    }
+  }
+  check_open_challenges(deps.storage, &created_by)?;
+
+  let challenge_id = next_challenge_id(deps.storage)?;
+  let challenge = Challenge {
+    allow_self_play: game.player1 == game.player2,
+    block_created: env.block.height,
+    block_limit: game.block_limit,
+    challenge_id,
+    chess960: false,
+    created_by: created_by.clone(),
+    dedup_key: None,
+    expires_at_block: None,
+    // rematches are free
+    fee: None,
+    increment_blocks: game.increment_blocks,
+    num_games: None,
+    // an odds game is followed by a standard one, its start_fen was made for the old colors
+    odds: None,
+    opponent: Some(opponent.clone()),
+    play_as: Some(play_as),
+    start_fen: game.start_fen.clone().filter(|_| game.odds.is_none()),
+  };
+  add_challenge(deps.storage, &challenge)?;
+  REMATCHES.save(deps.storage, game_id, &challenge_id)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "offer_rematch")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("challenge_id", challenge_id.to_string())
+    .add_attribute("created_by", created_by)
+    .add_attribute("opponent", opponent))
+}
+
+/// nudge an inactive player's ratings toward the starting rating,
+/// at most once per decay_after_blocks
+fn execute_decay_inactive_rating(
+  deps: DepsMut,
+  env: Env,
+  player: String,
+) -> Result<Response, ContractError> {
+  let player = deps.api.addr_validate(&player)?;
+  let config = CONFIG.load(deps.storage)?;
+  let height = env.block.height;
+  let mut stats = PLAYER_STATS.may_load(deps.storage, player.clone())?.unwrap_or_default();
+  // players without a rated game still have the starting rating
+  match stats.last_active_block() {
+    Some(last_active_block) if height >= last_active_block.saturating_add(config.decay_after_blocks) => {}
+    _ => return Err(ContractError::PlayerNotInactive {}),
+  }
+
+  let mut response = Response::new()
+    .add_attribute("action", "decay_inactive_rating")
+    .add_attribute("player", player.clone());
+  // every time control the player is rated in decays
+  for time_control in TimeControl::ALL {
+    let rating = match RATINGS.may_load(deps.storage, (player.clone(), time_control.as_str()))? {
+      Some(rating) => rating,
+      None => continue,
+    };
+    let decayed = match rating > config.starting_rating {
+      true => rating.saturating_sub(config.decay_amount).max(config.starting_rating),
+      false => rating
+        .checked_add(config.decay_amount)
+        .ok_or(ContractError::RatingOverflow {})?
+        .min(config.starting_rating),
+    };
+    let decayed = update_player_rating(deps.storage, &player, time_control, decayed, None)?;
+    response = response
+      .add_attribute("time_control", time_control.as_str())
+      .add_attribute("old_rating", rating.to_string())
+      .add_attribute("rating", decayed.to_string());
+  }
+  stats.last_decay_block = Some(height);
+  PLAYER_STATS.save(deps.storage, player, &stats)?;
+
+  Ok(response)
+}
+
+fn execute_claim_victory_on_abandonment(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  game_id: u64,
+) -> Result<Response, ContractError> {
+  let games_map = get_games_map();
+  let player = info.sender;
+  let inactivity_blocks = CONFIG.load(deps.storage)?.inactivity_blocks;
+  let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+    match game {
+      None => Err(ContractError::GameNotFound {}),
+      Some(mut game) => {
+        game.claim_abandonment(&player, env.block.height, inactivity_blocks)?;
+        Ok(game)
+      }
+    }
+  })?;
+
+  let mut response = Response::new()
+    .add_attribute("action", "claim_victory_on_abandonment")
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute(
+      "status",
+      game.status
+        .as_ref()
+        .map(|s| format!("{:?}", s))
+        .unwrap_or_default(),
+    );
+  if let Some(status) = &game.status {
+    response = response
+      .add_events(finish_game(deps.storage, env.block.height, &game, status)?)
+      .add_submessages(completion_hook_msgs(deps.storage, &game, status)?);
+  }
+
+  Ok(response)
+}
+
+// without auto-queen, a promotion has to name its piece
+fn check_promotion_piece(store: &dyn Storage, move_str: &str) -> Result<(), ContractError> {
+  if is_bare_promotion(move_str) && !CONFIG.load(store)?.auto_queen_promotion {
+    return Err(ContractError::PromotionRequired {});
+  }
+  Ok(())
+}
+
+// resigning needs an explicit confirm once the owner turns it on
+fn check_resign_confirmed(store: &dyn Storage, confirm: bool) -> Result<(), ContractError> {
+  if !confirm && CONFIG.load(store)?.require_resign_confirmation {
+    return Err(ContractError::ResignNotConfirmed {});
+  }
+  Ok(())
+}
+
+fn execute_resign(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  confirm: bool,
+  game_id: u64,
+) -> Result<Response, ContractError> {
+  check_resign_confirmed(deps.storage, confirm)?;
+  let games_map = get_games_map();
+  let player = info.sender;
+  let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+    match game {
+      None => Err(ContractError::GameNotFound {}),
+      Some(mut game) => {
+        game.resign(&player)?;
+        Ok(game)
+      }
+    }
+  })?;
+
+  let mut response = Response::new()
+    .add_attribute("action", "resign")
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute(
+      "status",
+      game.status
+        .as_ref()
+        .map(|s| format!("{:?}", s))
+        .unwrap_or_default(),
+    );
+  if let Some(status) = &game.status {
+    response = response
+      .add_events(finish_game(deps.storage, env.block.height, &game, status)?)
+      .add_submessages(completion_hook_msgs(deps.storage, &game, status)?);
+  }
+
+  Ok(response)
+}
+
+fn execute_agree_result(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  game_id: u64,
+  result: CwChessGameOver,
+) -> Result<Response, ContractError> {
+  let games_map = get_games_map();
+  let player = info.sender;
+  let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+    match game {
+      None => Err(ContractError::GameNotFound {}),
+      Some(mut game) => {
+        game.agree_result(&player, result.clone())?;
+        Ok(game)
+      }
+    }
+  })?;
+
+  let mut response = Response::new()
+    .add_attribute("action", "agree_result")
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute("result", format!("{:?}", result))
+    .add_attribute("agreed", game.status.is_some().to_string());
+  if let Some(status) = &game.status {
+    response = response
+      .add_events(finish_game(deps.storage, env.block.height, &game, status)?)
+      .add_submessages(completion_hook_msgs(deps.storage, &game, status)?);
+  }
+
+  Ok(response)
+}
+
+/// get the player's rating, the starting rating for new players
+fn get_player_rating(
+  store: &dyn Storage,
+  addr: &Addr,
+  time_control: TimeControl,
+) -> Result<u64, ContractError> {
+  if let Some(rating) = RATINGS.may_load(store, (addr.clone(), time_control.as_str()))? {
+    Ok(rating)
+  } else {
+    Ok(CONFIG.load(store)?.starting_rating)
+  }
+}
+
+/// update the player's rating, clamped to the configured floor and ceiling
+/// returns the saved rating
+// game_id is the rated game that led to the new rating, if any,
+// recorded in the player's rating history
+fn update_player_rating(
+  store: &mut dyn Storage,
+  addr: &Addr,
+  time_control: TimeControl,
+  rating: u64,
+  game_id: Option<u64>,
+) -> Result<u64, ContractError> {
+  let config = CONFIG.load(store)?;
+  // bounds are checked when set, so this only catches a corrupted config
+  let rating_ceiling = config.rating_ceiling.unwrap_or(u64::MAX);
+  if rating_ceiling < config.rating_floor {
+    return Err(ContractError::InvalidRatingBounds {});
+  }
+  let rating = rating.max(config.rating_floor).min(rating_ceiling);
+  if let Some(game_id) = game_id {
+    RATING_HISTORY.save(store, (addr.clone(), time_control.as_str(), game_id), &rating)?;
+  }
+  // the player may not have a rating entry yet
+  if !has_rating(store, addr) {
+    add_player(store)?;
+  }
+  RATINGS.save(store, (addr.clone(), time_control.as_str()), &rating)?;
+  Ok(rating)
+}
+
+// game outcome from player1's (white's) perspective
+// None for voided games, which don't count
+fn game_outcome(status: &CwChessGameOver) -> Option<Outcomes> {
+  match status {
+    CwChessGameOver::WhiteCheckmates |
+    CwChessGameOver::BlackResigns |
+    CwChessGameOver::BlackTimeout => Some(Outcomes::WIN),
+
+    CwChessGameOver::BlackCheckmates |
+    CwChessGameOver::WhiteResigns |
+    CwChessGameOver::WhiteTimeout => Some(Outcomes::LOSS),
+
+    CwChessGameOver::DrawAccepted |
+    CwChessGameOver::DrawClaimed |
+    CwChessGameOver::Stalemate |
+    CwChessGameOver::InsufficientMaterial => Some(Outcomes::DRAW),
+
+    CwChessGameOver::Voided => None,
+  }
+}
+
+// where game stands for player, None for voided games
+// self-play games are seen from player1's side
+fn player_status(game: &CwChessGame, player: &Addr) -> Option<StatusFilter> {
+  let status = match &game.status {
+    Some(status) => status,
+    None => return Some(StatusFilter::Ongoing),
+  };
+  let as_player1 = &game.player1 == player;
+  match game_outcome(status)? {
+    Outcomes::DRAW => Some(StatusFilter::Drawn),
+    Outcomes::WIN if as_player1 => Some(StatusFilter::Won),
+    Outcomes::LOSS if !as_player1 => Some(StatusFilter::Won),
+    _ => Some(StatusFilter::Lost),
+  }
+}
+
+// update the players rating
+// returns the new (player1, player2) ratings
+fn update_players_rating(
+  store: &mut dyn Storage,
+  height: u64,
+  game: &CwChessGame,
+  outcome: Outcomes,
+) -> Result<(u64, u64), ContractError> {
+  let player1 = &game.player1;
+  let player2 = &game.player2;
+  let mut stats1 = PLAYER_STATS.may_load(store, player1.clone())?.unwrap_or_default();
+  let mut stats2 = PLAYER_STATS.may_load(store, player2.clone())?.unwrap_or_default();
+
+  let config = CONFIG.load(store)?;
+  let time_control = config.time_control(game.block_limit);
+  let elo_config = EloConfig {
+    high_rating: config.high_rating_threshold,
+    high_rating_k: config.high_rating_k_factor,
+    k: config.k_factor,
+    provisional_games: config.provisional_games,
+    provisional_k: config.provisional_k_factor,
+  };
+
+  // odds games, and draws unless rate_draws is set, count in the stats but not the ratings
+  let unrated = game.odds.is_some() || (outcome == Outcomes::DRAW && !config.rate_draws);
+  let (rate1, rate2) = if unrated {
+    (
+      get_player_rating(store, player1, time_control)?,
+      get_player_rating(store, player2, time_control)?,
+    )
+  } else {
+    // k factor depends on each player's rating and games played before this one
+    let (rate1, rate2) = elo_with_games(
+      &get_player_rating(store, player1, time_control)?.into(),
+      stats1.games_played,
+      &get_player_rating(store, player2, time_control)?.into(),
+      stats2.games_played,
+      &outcome,
+      &elo_config,
+    );
+    (
+      update_player_rating(store, player1, time_control, rate1.into(), Some(game.game_id))?,
+      update_player_rating(store, player2, time_control, rate2.into(), Some(game.game_id))?,
+    )
+  };
+
+  stats1.record(&outcome);
+  stats2.record(&match outcome {
+    Outcomes::WIN => Outcomes::LOSS,
+    Outcomes::LOSS => Outcomes::WIN,
+    Outcomes::DRAW => Outcomes::DRAW,
+  });
+  stats1.last_game_block = Some(height);
+  stats2.last_game_block = Some(height);
+  PLAYER_STATS.save(store, player1.clone(), &stats1)?;
+  PLAYER_STATS.save(store, player2.clone(), &stats2)?;
+
+  Ok((rate1, rate2))
+}
+
+/// record a finished game in the players' head to head results
+fn update_head_to_head(
+  store: &mut dyn Storage,
+  game: &CwChessGame,
+  outcome: &Outcomes,
+) -> StdResult<()> {
+  let (key, swapped) = head_to_head_key(&game.player1, &game.player2);
+  let mut record = HEAD_TO_HEAD.may_load(store, key.clone())?.unwrap_or_default();
+  // outcome is from player1's perspective
+  match (outcome, swapped) {
+    (Outcomes::WIN, false) | (Outcomes::LOSS, true) => record.player_a_wins += 1,
+    (Outcomes::LOSS, false) | (Outcomes::WIN, true) => record.player_b_wins += 1,
+    (Outcomes::DRAW, _) => record.draws += 1,
+  }
+  record.last_game_id = game.game_id;
+  HEAD_TO_HEAD.save(store, key, &record)
+}
+
+/// record a finished game's results, returning the game_ended event
+/// and any tournament events
+fn finish_game(
+  store: &mut dyn Storage,
+  height: u64,
+  game: &CwChessGame,
+  status: &CwChessGameOver,
+) -> Result<Vec<Event>, ContractError> {
+  remove_active_game(store, game);
+  add_finished_game(store)?;
+  // self-play games are left unrated
+  let outcome = game_outcome(status).filter(|_| game.player1 != game.player2);
+  let ratings = match outcome {
+    Some(outcome) => {
+      update_head_to_head(store, game, &outcome)?;
+      update_players_rating(store, height, game, outcome)?
+    }
+    None => {
+      let time_control = CONFIG.load(store)?.time_control(game.block_limit);
+      (
+        get_player_rating(store, &game.player1, time_control)?,
+        get_player_rating(store, &game.player2, time_control)?,
+      )
+    }
+  };
+  let mut events = vec![game_ended_event(game, status, ratings)];
+  events.extend(advance_tournament(store, height, game)?);
+  events.extend(advance_match(store, game)?);
+  Ok(events)
+}
+
+/// tell the completion hook, if any, how the game ended
+/// a failing hook is caught in reply, so it can't keep games from finishing
+fn completion_hook_msgs(
+  store: &dyn Storage,
+  game: &CwChessGame,
+  status: &CwChessGameOver,
+) -> StdResult<Vec<SubMsg>> {
+  let hook = match CONFIG.load(store)?.completion_hook {
+    Some(hook) => hook,
+    None => return Ok(vec![]),
+  };
+  let winner = match game_outcome(status) {
+    Some(Outcomes::WIN) => Some(game.player1.clone()),
+    Some(Outcomes::LOSS) => Some(game.player2.clone()),
+    _ => None,
+  };
+  let msg = WasmMsg::Execute {
+    contract_addr: hook.into_string(),
+    msg: to_binary(&CompletionHookMsg::GameCompleted {
+      game_id: game.game_id,
+      player1: game.player1.clone(),
+      player2: game.player2.clone(),
+      status: status.clone(),
+      winner,
+    })?,
+    funds: vec![],
+  };
+  Ok(vec![SubMsg::reply_on_error(msg, COMPLETION_HOOK_REPLY_ID)])
+}
+
+/// start the next tournament round once every game of the current one is
+/// finished, or crown the champion after the last round
+fn advance_tournament(
+  store: &mut dyn Storage,
+  height: u64,
+  game: &CwChessGame,
+) -> StdResult<Vec<Event>> {
+  let tournament_id = match TOURNAMENT_GAMES.may_load(store, game.game_id)? {
+    Some(tournament_id) => tournament_id,
+    None => return Ok(vec![]),
+  };
+  let mut tournament = TOURNAMENTS.load(store, tournament_id)?;
+  let round = tournament.rounds.last().cloned().unwrap_or_default();
+  if tournament.champion.is_some() || !round.contains(&game.game_id) {
+    return Ok(vec![]);
+  }
+  record_tournament_result(store, tournament_id, game)?;
+
+  let games_map = get_games_map();
+  let games = round
+    .iter()
+    .map(|game_id| games_map.load(store, *game_id))
+    .collect::<StdResult<Vec<_>>>()?;
+  if !round_finished(&games) {
+    return Ok(vec![]);
+  }
+
+  let champion = match tournament.format {
+    TournamentFormat::Knockout => {
+      let winners = tournament.round_winners(&games).unwrap_or_default();
+      match winners.as_slice() {
+        [champion] => Some(champion.clone()),
+        _ => {
+          let events = start_tournament_round(store, height, &mut tournament, pairings(&winners))?;
+          TOURNAMENTS.save(store, tournament_id, &tournament)?;
+          return Ok(events);
+        }
+      }
+    }
+    TournamentFormat::Swiss { rounds } => {
+      if (tournament.rounds.len() as u64) < rounds {
+        let events = start_swiss_round(store, height, &mut tournament)?;
+        TOURNAMENTS.save(store, tournament_id, &tournament)?;
+        return Ok(events);
+      }
+      let records = load_tournament_records(store, &tournament)?;
+      standings(&records).first().map(|(player, _, _)| player.clone())
+    }
+  };
+
+  tournament.champion = champion.clone();
+  TOURNAMENTS.save(store, tournament_id, &tournament)?;
+  Ok(vec![Event::new("tournament_ended")
+    .add_attribute("tournament_id", tournament_id.to_string())
+    .add_attribute("champion", champion.map(|champion| champion.to_string()).unwrap_or_default())])
+}
+
+/// add a finished tournament game's points to both players' records
+fn record_tournament_result(store: &mut dyn Storage, tournament_id: u64, game: &CwChessGame) -> StdResult<()> {
+  let (white_points, black_points) = match game_points(game) {
+    Some(points) => points,
+    None => return Ok(()),
+  };
+  for (player, points) in [(&game.player1, white_points), (&game.player2, black_points)] {
+    TOURNAMENT_RECORDS.update(store, (tournament_id, player.clone()), |record| -> StdResult<_> {
+      let mut record = record.unwrap_or_default();
+      record.points += points;
+      Ok(record)
+    })?;
+  }
+  Ok(())
+}
+
+/// records of every player, in join order
+fn load_tournament_records(
+  store: &dyn Storage,
+  tournament: &Tournament,
+) -> StdResult<Vec<(Addr, TournamentRecord)>> {
+  tournament
+    .players
+    .iter()
+    .map(|player| {
+      let record = TOURNAMENT_RECORDS
+        .may_load(store, (tournament.tournament_id, player.clone()))?
+        .unwrap_or_default();
+      Ok((player.clone(), record))
+    })
+    .collect()
+}
+
+/// pair a swiss round by the current standings, scoring the bye up front
+fn start_swiss_round(
+  store: &mut dyn Storage,
+  height: u64,
+  tournament: &mut Tournament,
+) -> StdResult<Vec<Event>> {
+  let records = load_tournament_records(store, tournament)?;
+  let ranked: Vec<_> = standings(&records)
+    .into_iter()
+    .map(|(player, record, _)| (player, record))
+    .collect();
+  let (pairs, bye) = swiss_pairings(&ranked);
+  let mut events = start_tournament_round(store, height, tournament, pairs)?;
+  if let Some(player) = bye {
+    TOURNAMENT_RECORDS.update(store, (tournament.tournament_id, player.clone()), |record| -> StdResult<_> {
+      let mut record = record.unwrap_or_default();
+      record.byes += 1;
+      record.points += 2;
+      Ok(record)
+    })?;
+    events.push(Event::new("tournament_bye")
+      .add_attribute("tournament_id", tournament.tournament_id.to_string())
+      .add_attribute("round", tournament.rounds.len().to_string())
+      .add_attribute("player", player));
+  }
+  Ok(events)
+}
+
+/// create the games of a tournament round, returning their game_started events
+fn start_tournament_round(
+  store: &mut dyn Storage,
+  height: u64,
+  tournament: &mut Tournament,
+  pairs: Vec<(Addr, Addr)>,
+) -> StdResult<Vec<Event>> {
+  let tournament_id = tournament.tournament_id;
+  let mut game_ids = vec![];
+  let mut events = vec![];
+  for (i, (player_a, player_b)) in pairs.into_iter().enumerate() {
+    for (player, opponent) in [(&player_a, &player_b), (&player_b, &player_a)] {
+      TOURNAMENT_RECORDS.update(store, (tournament_id, player.clone()), |record| -> StdResult<_> {
+        let mut record = record.unwrap_or_default();
+        record.opponents.push(opponent.clone());
+        Ok(record)
+      })?;
+    }
+    let seed = game_seed(i as u64, height, &player_a, &player_b);
+    let (player1, player2) = CwChessGame::get_player_order(player_a, player_b, None, seed);
+    let (game, game_started) =
+      start_game(store, height, player1, player2, tournament.block_limit, None, Some(seed), None, None)?;
+    TOURNAMENT_GAMES.save(store, game.game_id, &tournament_id)?;
+    game_ids.push(game.game_id);
+    events.push(game_started.add_attribute("tournament_id", tournament_id.to_string()));
+  }
+  tournament.rounds.push(game_ids);
+  Ok(events)
+}
+
+fn execute_create_tournament(
+  deps: DepsMut,
+  info: MessageInfo,
+  block_limit: Option<u64>,
+  format: Option<TournamentFormat>,
+  name: String,
+  size: u64,
+) -> Result<Response, ContractError> {
+  let format = format.unwrap_or(TournamentFormat::Knockout);
+  Tournament::check_format(&format, size)?;
+  check_block_limit(deps.storage, block_limit, None)?;
+  let tournament_id = next_tournament_id(deps.storage)?;
+  let tournament = Tournament {
+    block_limit,
+    champion: None,
+    created_by: info.sender,
+    format,
+    name,
+    players: vec![],
+    rounds: vec![],
+    size,
+    tournament_id,
+  };
+  TOURNAMENTS.save(deps.storage, tournament_id, &tournament)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "create_tournament")
+    .add_attribute("tournament_id", tournament_id.to_string())
+    .add_attribute("size", size.to_string()))
+}
+
+fn execute_join_tournament(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  tournament_id: u64,
+) -> Result<Response, ContractError> {
+  let mut tournament = match TOURNAMENTS.may_load(deps.storage, tournament_id)? {
+    Some(tournament) => tournament,
+    None => return Err(ContractError::TournamentNotFound {}),
+  };
+  let player = info.sender;
+  def_player_rating(deps.storage, &player, tournament.block_limit)?;
+  // first round is paired as soon as the last player joins
+  let events = match (tournament.join(player.clone())?, &tournament.format) {
+    (true, TournamentFormat::Knockout) => {
+      let pairs = pairings(&tournament.players);
+      start_tournament_round(deps.storage, env.block.height, &mut tournament, pairs)?
+    }
+    (true, TournamentFormat::Swiss { .. }) => {
+      start_swiss_round(deps.storage, env.block.height, &mut tournament)?
+    }
+    (false, _) => vec![],
+  };
+  TOURNAMENTS.save(deps.storage, tournament_id, &tournament)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "join_tournament")
+    .add_attribute("tournament_id", tournament_id.to_string())
+    .add_attribute("player", player)
+    .add_events(events))
+}
+
+/// start a game against the closest rated compatible waiting player,
+/// or wait in the queue until one arrives
+fn execute_enter_queue(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  block_limit: Option<u64>,
+  rating_range: u64,
+) -> Result<Response, ContractError> {
+  let player = info.sender;
+  if QUEUED_PLAYERS.has(deps.storage, player.clone()) {
+    return Err(ContractError::AlreadyInQueue {});
+  }
+  check_block_limit(deps.storage, block_limit, None)?;
+  def_player_rating(deps.storage, &player, block_limit)?;
+  let time_control = CONFIG.load(deps.storage)?.time_control(block_limit);
+  let entry = QueueEntry {
+    block_entered: env.block.height,
+    block_limit,
+    player: player.clone(),
+    rating: get_player_rating(deps.storage, &player, time_control)?,
+    rating_range,
+  };
+
+  // only players within the entrant's range can be compatible
+  let min = Bound::inclusive((entry.rating.saturating_sub(rating_range), Addr::unchecked("")));
+  let max = entry
+    .rating
+    .checked_add(rating_range)
+    .and_then(|max| max.checked_add(1))
+    .map(|max| Bound::exclusive((max, Addr::unchecked(""))));
+  let waiting = QUEUE
+    .range(deps.storage, Some(min), max, Order::Ascending)
+    .map(|item| item.map(|(_, entry)| entry))
+    .collect::<StdResult<Vec<_>>>()?;
+  let opponent = match best_opponent(&entry, &waiting) {
+    Some(opponent) => opponent.clone(),
+    None => {
+      QUEUE.save(deps.storage, (entry.rating, player.clone()), &entry)?;
+      QUEUED_PLAYERS.save(deps.storage, player.clone(), &entry.rating)?;
+      return Ok(Response::new()
+        .add_attribute("action", "enter_queue")
+        .add_attribute("player", player)
+        .add_attribute("rating", entry.rating.to_string()));
+    }
+  };
+  QUEUE.remove(deps.storage, (opponent.rating, opponent.player.clone()));
+  QUEUED_PLAYERS.remove(deps.storage, opponent.player.clone());
+
+  // whoever has had white more often plays black, the seed decides when even.
+  // a player is dequeued once paired, so a queue pair starts one game per block
+  let surplus = |player: &Addr| -> StdResult<_> {
+    Ok(PLAYER_STATS.may_load(deps.storage, player.clone())?.unwrap_or_default().color_surplus())
+  };
+  let play_as = balanced_color(surplus(&opponent.player)?, surplus(&player)?);
+  let seed = game_seed(0, env.block.height, &opponent.player, &player);
+  let (player1, player2) = CwChessGame::get_player_order(opponent.player, player.clone(), play_as, seed);
+  let (game, game_started) = start_game(
+    deps.storage,
+    env.block.height,
+    player1,
+    player2,
+    block_limit,
+    None,
+    Some(seed),
+    None,
+    None,
+  )?;
+
+  Ok(Response::new()
+    .add_attribute("action", "enter_queue")
+    .add_attribute("player", player)
+    .add_attribute("rating", entry.rating.to_string())
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_event(game_started))
+}
+
+fn execute_leave_queue(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+  let player = info.sender;
+  let rating = match QUEUED_PLAYERS.may_load(deps.storage, player.clone())? {
+    Some(rating) => rating,
+    None => return Err(ContractError::NotInQueue {}),
+  };
+  QUEUE.remove(deps.storage, (rating, player.clone()));
+  QUEUED_PLAYERS.remove(deps.storage, player.clone());
+
+  Ok(Response::new()
+    .add_attribute("action", "leave_queue")
+    .add_attribute("player", player))
+}
+
+/// event for indexers when a game finishes
+fn game_ended_event(game: &CwChessGame, status: &CwChessGameOver, ratings: (u64, u64)) -> Event {
+  Event::new("game_ended")
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute("status", format!("{:?}", status))
+    .add_attribute("player1", game.player1.clone())
+    .add_attribute("player2", game.player2.clone())
+    .add_attribute("player1_rating", ratings.0.to_string())
+    .add_attribute("player2_rating", ratings.1.to_string())
+}
+
+fn execute_turn(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
   action: CwChessAction,
   game_id: u64,
 ) -> Result<Response, ContractError> {
+  // a resign action can't carry the confirmation, so it needs ExecuteMsg::Resign
+  if action == CwChessAction::Resign {
+    check_resign_confirmed(deps.storage, false)?;
+  }
+  if let CwChessAction::MakeMove(move_str) | CwChessAction::OfferDraw(move_str) = &action {
+    check_promotion_piece(deps.storage, move_str)?;
+  }
+  let draw_offer_cooldown_blocks = CONFIG.load(deps.storage)?.draw_offer_cooldown_blocks;
   let games_map = get_games_map();
   let height = env.block.height;
   let player = info.sender;
+  let mut moves_before = 0;
+  let mut premove = None;
   let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
     match game {
       None => Err(ContractError::GameNotFound {}),
       Some(mut game) => {
+        if let CwChessAction::OfferDraw(_) = action {
+          game.check_draw_offer_cooldown(&player, height, draw_offer_cooldown_blocks)?;
+        }
+        moves_before = game.moves.len();
         game.make_move(&player, (height, action.clone()))?;
+        premove = game.play_premove(height);
         Ok(game)
       }
     }
   })?;
+  record_move_time(deps.storage, &player, &game, moves_before)?;
+  if premove == Some(true) {
+    let opponent = match game.player_color(&player) {
+      Some(CwChessColor::White) => &game.player2,
+      _ => &game.player1,
+    };
+    record_move_time(deps.storage, opponent, &game, game.moves.len() - 1)?;
+  }
 
+  let mut response = Response::new()
+    .add_attribute("action", "turn")
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute(
+      "status",
+      game.status
+        .as_ref()
+        .map(|s| format!("{:?}", s))
+        .or_else(|| game.turn_color().map(|color| format!("{:?}", color)))
+        .unwrap_or_default(),
+    )
+    .add_attribute("check", game.in_check()?.to_string());
+  if let Some(played) = premove {
+    response = response.add_attribute("premove", if played { "played" } else { "dropped" });
+  }
   if let Some(status) = &game.status {
-    let outcome = match status {
-      CwChessGameOver::WhiteCheckmates |
-      CwChessGameOver::BlackResigns |
-      CwChessGameOver::BlackTimeout => Outcomes::WIN,
-
-      CwChessGameOver::BlackCheckmates |
-      CwChessGameOver::WhiteResigns |
-      CwChessGameOver::WhiteTimeout => Outcomes::LOSS,
-
-      CwChessGameOver::DrawAccepted |
-      CwChessGameOver::DrawDeclared |
-      CwChessGameOver::Stalemate => Outcomes::DRAW,
+    response = response
+      .add_events(finish_game(deps.storage, env.block.height, &game, status)?)
+      .add_submessages(completion_hook_msgs(deps.storage, &game, status)?);
+  }
+
+  Ok(response)
+}
+
+fn execute_set_premove(
+  deps: DepsMut,
+  info: MessageInfo,
+  game_id: u64,
+  move_str: String,
+) -> Result<Response, ContractError> {
+  check_promotion_piece(deps.storage, &move_str)?;
+  get_games_map().update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+    match game {
+      None => Err(ContractError::GameNotFound {}),
+      Some(mut game) => {
+        game.set_premove(&info.sender, move_str)?;
+        Ok(game)
+      }
+    }
+  })?;
+
+  Ok(Response::new()
+    .add_attribute("action", "set_premove")
+    .add_attribute("game_id", game_id.to_string())
+    .add_attribute("player", info.sender))
+}
+
+/// play several actions in one transaction, failing as a whole on any error
+fn execute_batch_turn(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  actions: Vec<CwChessAction>,
+  game_id: u64,
+) -> Result<Response, ContractError> {
+  if actions.is_empty() || actions.len() > MAX_BATCH_ACTIONS {
+    return Err(ContractError::InvalidBatchSize {});
+  }
+  if actions.contains(&CwChessAction::Resign) {
+    check_resign_confirmed(deps.storage, false)?;
+  }
+  for action in actions.iter() {
+    if let CwChessAction::MakeMove(move_str) | CwChessAction::OfferDraw(move_str) = action {
+      check_promotion_piece(deps.storage, move_str)?;
+    }
+  }
+  let draw_offer_cooldown_blocks = CONFIG.load(deps.storage)?.draw_offer_cooldown_blocks;
+  let games_map = get_games_map();
+  let height = env.block.height;
+  let player = info.sender;
+  let mut moves_before = vec![];
+  let mut premove = None;
+  let game = games_map.update(deps.storage, game_id, |game| -> Result<_, ContractError> {
+    match game {
+      None => Err(ContractError::GameNotFound {}),
+      Some(mut game) => {
+        for action in actions.iter() {
+          if let CwChessAction::OfferDraw(_) = action {
+            game.check_draw_offer_cooldown(&player, height, draw_offer_cooldown_blocks)?;
+          }
+          moves_before.push(game.moves.len());
+          game.make_move(&player, (height, action.clone()))?;
+        }
+        premove = game.play_premove(height);
+        Ok(game)
+      }
+    }
+  })?;
+  for moves_before in moves_before {
+    record_move_time(deps.storage, &player, &game, moves_before)?;
+  }
+  if premove == Some(true) {
+    let opponent = match game.player_color(&player) {
+      Some(CwChessColor::White) => &game.player2,
+      _ => &game.player1,
     };
-    update_players_rating(deps.storage, &game, outcome)?;
+    record_move_time(deps.storage, opponent, &game, game.moves.len() - 1)?;
+  }
+
+  let mut response = Response::new()
+    .add_attribute("action", "batch_turn")
+    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute("actions", actions.len().to_string())
+    .add_attribute(
+      "status",
+      game.status
+        .as_ref()
+        .map(|s| format!("{:?}", s))
+        .or_else(|| game.turn_color().map(|color| format!("{:?}", color)))
+        .unwrap_or_default(),
+    );
+  if let Some(played) = premove {
+    response = response.add_attribute("premove", if played { "played" } else { "dropped" });
+  }
+  if let Some(status) = &game.status {
+    response = response
+      .add_events(finish_game(deps.storage, env.block.height, &game, status)?)
+      .add_submessages(completion_hook_msgs(deps.storage, &game, status)?);
+  }
+
+  Ok(response)
+}
+
+/// add the blocks since the previous move to the player's stats, if a move was played
+/// the first move of a game has nothing to time it from, as with the clocks
+fn record_move_time(
+  store: &mut dyn Storage,
+  player: &Addr,
+  game: &CwChessGame,
+  moves_before: usize,
+) -> StdResult<()> {
+  let height = match game.moves.get(moves_before) {
+    Some((height, CwChessAction::MakeMove(_))) | Some((height, CwChessAction::OfferDraw(_))) => height,
+    _ => return Ok(()),
+  };
+  let previous = match moves_before.checked_sub(1).and_then(|i| game.moves.get(i)) {
+    Some((previous, _)) => previous,
+    None => return Ok(()),
+  };
+  PLAYER_STATS.update(store, player.clone(), |stats| -> StdResult<_> {
+    let mut stats = stats.unwrap_or_default();
+    stats.record_move(height.saturating_sub(*previous));
+    Ok(stats)
+  })?;
+  Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute_update_config(
+  deps: DepsMut,
+  info: MessageInfo,
+  abandon_threshold: Option<u64>,
+  auto_queen_promotion: Option<bool>,
+  blitz_max_blocks: Option<u64>,
+  bullet_max_blocks: Option<u64>,
+  challenge_fee: Option<Coin>,
+  completion_hook: Option<String>,
+  decay_after_blocks: Option<u64>,
+  decay_amount: Option<u64>,
+  draw_offer_cooldown_blocks: Option<u64>,
+  high_rating_k_factor: Option<u64>,
+  high_rating_threshold: Option<u64>,
+  inactivity_blocks: Option<u64>,
+  k_factor: Option<u64>,
+  max_block_limit: Option<u64>,
+  max_open_challenges: Option<u64>,
+  max_pairwise_challenges: Option<u64>,
+  provisional_games: Option<u64>,
+  provisional_k_factor: Option<u64>,
+  rapid_max_blocks: Option<u64>,
+  rate_draws: Option<bool>,
+  rating_ceiling: Option<u64>,
+  rating_floor: Option<u64>,
+  refund_challenge_fee: Option<bool>,
+  require_resign_confirmation: Option<bool>,
+  starting_rating: Option<u64>,
+  treasury: Option<String>,
+) -> Result<Response, ContractError> {
+  let state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
+  }
+  let mut config = CONFIG.load(deps.storage)?;
+  if let Some(abandon_threshold) = abandon_threshold {
+    config.abandon_threshold = abandon_threshold;
+  }
+  if let Some(auto_queen_promotion) = auto_queen_promotion {
+    config.auto_queen_promotion = auto_queen_promotion;
+  }
+  if let Some(blitz_max_blocks) = blitz_max_blocks {
+    config.blitz_max_blocks = blitz_max_blocks;
+  }
+  if let Some(bullet_max_blocks) = bullet_max_blocks {
+    config.bullet_max_blocks = bullet_max_blocks;
+  }
+  if let Some(challenge_fee) = challenge_fee {
+    config.challenge_fee = Some(challenge_fee);
+  }
+  if let Some(completion_hook) = completion_hook {
+    config.completion_hook = Some(deps.api.addr_validate(&completion_hook)?);
+  }
+  if let Some(decay_after_blocks) = decay_after_blocks {
+    config.decay_after_blocks = decay_after_blocks;
+  }
+  if let Some(decay_amount) = decay_amount {
+    config.decay_amount = decay_amount;
+  }
+  if let Some(draw_offer_cooldown_blocks) = draw_offer_cooldown_blocks {
+    config.draw_offer_cooldown_blocks = draw_offer_cooldown_blocks;
+  }
+  if let Some(high_rating_k_factor) = high_rating_k_factor {
+    config.high_rating_k_factor = high_rating_k_factor;
+  }
+  if let Some(high_rating_threshold) = high_rating_threshold {
+    config.high_rating_threshold = high_rating_threshold;
+  }
+  if let Some(inactivity_blocks) = inactivity_blocks {
+    config.inactivity_blocks = inactivity_blocks;
   }
+  if let Some(k_factor) = k_factor {
+    config.k_factor = k_factor;
+  }
+  if let Some(max_block_limit) = max_block_limit {
+    config.max_block_limit = max_block_limit;
+  }
+  if let Some(max_open_challenges) = max_open_challenges {
+    config.max_open_challenges = max_open_challenges;
+  }
+  if let Some(max_pairwise_challenges) = max_pairwise_challenges {
+    config.max_pairwise_challenges = Some(max_pairwise_challenges);
+  }
+  if let Some(provisional_games) = provisional_games {
+    config.provisional_games = provisional_games;
+  }
+  if let Some(provisional_k_factor) = provisional_k_factor {
+    config.provisional_k_factor = provisional_k_factor;
+  }
+  if let Some(rapid_max_blocks) = rapid_max_blocks {
+    config.rapid_max_blocks = rapid_max_blocks;
+  }
+  if let Some(rate_draws) = rate_draws {
+    config.rate_draws = rate_draws;
+  }
+  if let Some(rating_ceiling) = rating_ceiling {
+    config.rating_ceiling = Some(rating_ceiling);
+  }
+  if let Some(rating_floor) = rating_floor {
+    config.rating_floor = rating_floor;
+  }
+  if let Some(refund_challenge_fee) = refund_challenge_fee {
+    config.refund_challenge_fee = refund_challenge_fee;
+  }
+  if let Some(require_resign_confirmation) = require_resign_confirmation {
+    config.require_resign_confirmation = require_resign_confirmation;
+  }
+  if let Some(starting_rating) = starting_rating {
+    config.starting_rating = starting_rating;
+  }
+  if let Some(treasury) = treasury {
+    config.treasury = Some(deps.api.addr_validate(&treasury)?);
+  }
+  check_rating_bounds(&config)?;
+  check_time_controls(&config)?;
+  CONFIG.save(deps.storage, &config)?;
 
   Ok(Response::new()
-    .add_attribute("action", "turn")
-    .add_attribute("game_id", game.game_id.to_string())
+    .add_attribute("action", "update_config")
+    .add_attribute("abandon_threshold", config.abandon_threshold.to_string())
+    .add_attribute("auto_queen_promotion", config.auto_queen_promotion.to_string())
+    .add_attribute("blitz_max_blocks", config.blitz_max_blocks.to_string())
+    .add_attribute("bullet_max_blocks", config.bullet_max_blocks.to_string())
     .add_attribute(
-      "status",
-      game.status
+      "challenge_fee",
+      config
+        .challenge_fee()
+        .map(|fee| fee.to_string())
+        .unwrap_or_else(|| "none".to_string()),
+    )
+    .add_attribute(
+      "completion_hook",
+      config
+        .completion_hook
         .as_ref()
-        .map(|s| format!("{:?}", s))
-        .unwrap_or_else(|| format!("{:?}", game.turn_color())),
-    ))
+        .map(|hook| hook.to_string())
+        .unwrap_or_else(|| "none".to_string()),
+    )
+    .add_attribute("decay_after_blocks", config.decay_after_blocks.to_string())
+    .add_attribute("decay_amount", config.decay_amount.to_string())
+    .add_attribute("draw_offer_cooldown_blocks", config.draw_offer_cooldown_blocks.to_string())
+    .add_attribute("high_rating_k_factor", config.high_rating_k_factor.to_string())
+    .add_attribute("high_rating_threshold", config.high_rating_threshold.to_string())
+    .add_attribute("inactivity_blocks", config.inactivity_blocks.to_string())
+    .add_attribute("k_factor", config.k_factor.to_string())
+    .add_attribute("max_block_limit", config.max_block_limit.to_string())
+    .add_attribute("max_open_challenges", config.max_open_challenges.to_string())
+    .add_attribute(
+      "max_pairwise_challenges",
+      config
+        .max_pairwise_challenges
+        .map(|max_pairwise_challenges| max_pairwise_challenges.to_string())
+        .unwrap_or_else(|| "none".to_string()),
+    )
+    .add_attribute("provisional_games", config.provisional_games.to_string())
+    .add_attribute("provisional_k_factor", config.provisional_k_factor.to_string())
+    .add_attribute("rapid_max_blocks", config.rapid_max_blocks.to_string())
+    .add_attribute("rate_draws", config.rate_draws.to_string())
+    .add_attribute(
+      "rating_ceiling",
+      config
+        .rating_ceiling
+        .map(|rating_ceiling| rating_ceiling.to_string())
+        .unwrap_or_else(|| "none".to_string()),
+    )
+    .add_attribute("rating_floor", config.rating_floor.to_string())
+    .add_attribute("refund_challenge_fee", config.refund_challenge_fee.to_string())
+    .add_attribute(
+      "require_resign_confirmation",
+      config.require_resign_confirmation.to_string(),
+    )
+    .add_attribute("starting_rating", config.starting_rating.to_string())
+    .add_attribute("treasury", config.treasury.as_ref().unwrap_or(&state.owner)))
+}
+
+/// the floor can't be above the ceiling, or ratings couldn't be clamped,
+/// and new players have to start within them
+fn check_rating_bounds(config: &Config) -> Result<(), ContractError> {
+  let rating_ceiling = config.rating_ceiling.unwrap_or(u64::MAX);
+  if rating_ceiling < config.rating_floor
+    || !(config.rating_floor..=rating_ceiling).contains(&config.starting_rating)
+  {
+    return Err(ContractError::InvalidRatingBounds {});
+  }
+  Ok(())
+}
+
+/// each time control has to be at least as long as the one before it
+fn check_time_controls(config: &Config) -> Result<(), ContractError> {
+  if config.bullet_max_blocks > config.blitz_max_blocks || config.blitz_max_blocks > config.rapid_max_blocks {
+    return Err(ContractError::InvalidTimeControls {});
+  }
+  Ok(())
+}
+
+/// first step of an ownership transfer, the new owner still has to accept
+fn execute_propose_new_owner(
+  deps: DepsMut,
+  info: MessageInfo,
+  new_owner: String,
+) -> Result<Response, ContractError> {
+  let mut state = STATE.load(deps.storage)?;
+  if info.sender != state.owner {
+    return Err(ContractError::Unauthorized {});
+  }
+  let new_owner = deps.api.addr_validate(&new_owner)?;
+  state.pending_owner = Some(new_owner.clone());
+  STATE.save(deps.storage, &state)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "propose_new_owner")
+    .add_attribute("owner", state.owner)
+    .add_attribute("pending_owner", new_owner))
+}
+
+/// second step of an ownership transfer, by the proposed owner
+fn execute_accept_ownership(
+  deps: DepsMut,
+  info: MessageInfo,
+) -> Result<Response, ContractError> {
+  let mut state = STATE.load(deps.storage)?;
+  if state.pending_owner.as_ref() != Some(&info.sender) {
+    return Err(ContractError::Unauthorized {});
+  }
+  let previous_owner = state.owner;
+  state.owner = info.sender;
+  state.pending_owner = None;
+  STATE.save(deps.storage, &state)?;
+
+  Ok(Response::new()
+    .add_attribute("action", "accept_ownership")
+    .add_attribute("previous_owner", previous_owner)
+    .add_attribute("owner", state.owner))
 }
 
 fn query_get_challenge(deps: Deps, challenge_id: u64) -> StdResult<Challenge> {
@@ -361,20 +2588,352 @@ fn query_get_challenge(deps: Deps, challenge_id: u64) -> StdResult<Challenge> {
   Ok(challenge)
 }
 
-fn query_get_game(deps: Deps, game_id: u64) -> StdResult<CwChessGame> {
+fn query_get_game(deps: Deps, game_id: u64) -> StdResult<GameRecord> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+
+  Ok(GameRecord::plain(&game))
+}
+
+fn query_get_game_detailed(deps: Deps, env: Env, game_id: u64) -> StdResult<GameDetailed> {
+  let game = get_games_map().load(deps.storage, game_id)?;
+  let in_check = game.in_check().map_err(|e| StdError::generic_err(e.to_string()))?;
+
+  Ok(GameDetailed {
+    in_check,
+    move_count: game.moves.len() as u64,
+    pending_draw_offer: game.draw_offered(),
+    result: game.status.clone(),
+    timeout_block: game.timeout_block(),
+    timeout_pending: game.timeout_pending(env.block.height),
+    turn_color: game.turn_color(),
+    game,
+  })
+}
+
+fn query_get_opponent(deps: Deps, game_id: u64, player: String) -> StdResult<Addr> {
+  let player = deps.api.addr_validate(&player)?;
+  let game = get_games_map().load(deps.storage, game_id)?;
+  game
+    .opponent_of(&player)
+    .ok_or_else(|| StdError::generic_err(ContractError::NotYourGame {}.to_string()))
+}
+
+fn query_get_games_by_ids(deps: Deps, ids: Vec<u64>) -> StdResult<Vec<Option<GameRecord>>> {
+  if ids.len() > MAX_IDS {
+    return Err(StdError::generic_err(format!("at most {} ids", MAX_IDS)));
+  }
+  let games_map = get_games_map();
+  ids
+    .into_iter()
+    .map(|game_id| Ok(games_map.may_load(deps.storage, game_id)?.as_ref().map(GameRecord::plain)))
+    .collect()
+}
+
+fn query_get_game_history(deps: Deps, game_id: u64) -> StdResult<Vec<MoveSummary>> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+  let san_moves = match game.get_san_moves() {
+    Ok(san_moves) => san_moves,
+    Err(e) => return Err(StdError::generic_err(e.to_string())),
+  };
+
+  // number moves from 1, black's first move is 1...
+  let black_first = game.start_color() == CwChessColor::Black;
+  let mut previous_block = game.block_start;
+  let history = san_moves
+    .into_iter()
+    .enumerate()
+    .map(|(i, (block, color, san))| {
+      let blocks_elapsed = block.saturating_sub(previous_block);
+      previous_block = block;
+      MoveSummary {
+        block,
+        blocks_elapsed,
+        color,
+        move_number: (i as u64 + black_first as u64) / 2 + 1,
+        san,
+      }
+    })
+    .collect();
+  Ok(history)
+}
+
+fn query_get_game_metrics(deps: Deps, game_id: u64) -> StdResult<GameMetrics> {
+  let game = get_games_map().load(deps.storage, game_id)?;
+  let san_moves = match game.get_san_moves() {
+    Ok(san_moves) => san_moves,
+    Err(e) => return Err(StdError::generic_err(e.to_string())),
+  };
+  let mut metrics = GameMetrics {
+    longest_move_blocks: 0,
+    moves: san_moves.len() as u64,
+    same_block_moves: 0,
+    total_blocks: 0,
+  };
+  let mut previous_block = game.block_start;
+  for (i, (block, _, _)) in san_moves.iter().enumerate() {
+    if i > 0 && *block == previous_block {
+      metrics.same_block_moves += 1;
+    }
+    metrics.longest_move_blocks = metrics.longest_move_blocks.max(block.saturating_sub(previous_block));
+    previous_block = *block;
+  }
+  metrics.total_blocks = previous_block.saturating_sub(game.block_start);
+  Ok(metrics)
+}
+
+fn query_get_annotations(deps: Deps, game_id: u64) -> StdResult<Vec<AnnotationSummary>> {
+  // unknown games error rather than returning no annotations
+  get_games_map().load(deps.storage, game_id)?;
+  ANNOTATIONS
+    .prefix(game_id)
+    .range(deps.storage, None, None, Order::Ascending)
+    .map(|item| item.map(|(move_number, text)| AnnotationSummary { move_number, text }))
+    .collect()
+}
+
+fn query_get_latest_move(deps: Deps, game_id: u64) -> StdResult<LatestMoveSummary> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+  let latest_move = match game.get_san_moves() {
+    Ok(mut san_moves) => san_moves.pop(),
+    Err(e) => return Err(StdError::generic_err(e.to_string())),
+  };
+  Ok(LatestMoveSummary {
+    block: latest_move.as_ref().map(|(block, _, _)| *block),
+    fen: game.fen.clone(),
+    san: latest_move.map(|(_, _, san)| san),
+    turn_color: game.turn_color(),
+  })
+}
+
+fn query_get_clocks(deps: Deps, env: Env, game_id: u64) -> StdResult<Option<ClockSummary>> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+  Ok(
+    game
+      .get_clocks(env.block.height)
+      .map(|(white, black)| ClockSummary { black, white }),
+  )
+}
+
+fn query_get_head_to_head(
+  deps: Deps,
+  player_a: &str,
+  player_b: &str,
+) -> StdResult<HeadToHeadSummary> {
+  let addr_a = deps.api.addr_validate(player_a)?;
+  let addr_b = deps.api.addr_validate(player_b)?;
+  let (key, swapped) = head_to_head_key(&addr_a, &addr_b);
+  let record = HEAD_TO_HEAD.may_load(deps.storage, key)?;
+  let last_game_id = record.as_ref().map(|record| record.last_game_id);
+  let record = record.unwrap_or_default();
+  let (player_a_wins, player_b_wins) = match swapped {
+    false => (record.player_a_wins, record.player_b_wins),
+    true => (record.player_b_wins, record.player_a_wins),
+  };
+
+  Ok(HeadToHeadSummary {
+    draws: record.draws,
+    last_game_id,
+    player_a: addr_a.to_string(),
+    player_a_wins,
+    player_b: addr_b.to_string(),
+    player_b_wins,
+  })
+}
+
+fn query_get_open_challenge_count(deps: Deps) -> StdResult<u64> {
+  Ok(OPEN_CHALLENGE_COUNT.may_load(deps.storage)?.unwrap_or_default())
+}
+
+fn query_get_rating_history(
+  deps: Deps,
+  after: Option<u64>,
+  limit: Option<u32>,
+  player: String,
+  time_control: Option<TimeControl>,
+) -> StdResult<Vec<RatingHistoryPoint>> {
+  let player = deps.api.addr_validate(&player)?;
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  let time_control = time_control.unwrap_or_default();
+  RATING_HISTORY
+    .prefix((player, time_control.as_str()))
+    .range(deps.storage, after.map(Bound::exclusive), None, Order::Ascending)
+    .map(|item| item.map(|(game_id, rating)| RatingHistoryPoint { game_id, rating }))
+    .take(limit)
+    .collect()
+}
+
+fn query_get_game_ratings(deps: Deps, game_id: u64) -> StdResult<GameRatings> {
+  let game = get_games_map().load(deps.storage, game_id)?;
+  let time_control = CONFIG.load(deps.storage)?.time_control(game.block_limit);
+  let player_rating = |player: &Addr| -> StdResult<PlayerGameRating> {
+    let rating = get_player_rating(deps.storage, player, time_control)
+      .map_err(|e| StdError::generic_err(e.to_string()))?;
+    // rating after the player's previous game in the same time control
+    let rating_at_start = RATING_HISTORY
+      .prefix((player.clone(), time_control.as_str()))
+      .range(deps.storage, None, Some(Bound::exclusive(game_id)), Order::Descending)
+      .next()
+      .transpose()?
+      .map(|(_, rating)| rating);
+    Ok(PlayerGameRating {
+      player: player.to_string(),
+      rating,
+      rating_at_start,
+    })
+  };
+
+  Ok(GameRatings {
+    game_id,
+    player1: player_rating(&game.player1)?,
+    player2: player_rating(&game.player2)?,
+    time_control,
+  })
+}
+
+fn query_get_contract_stats(deps: Deps) -> StdResult<ContractStats> {
+  Ok(ContractStats {
+    finished_games: FINISHED_GAME_COUNT.may_load(deps.storage)?.unwrap_or_default(),
+    games: GAME_ID.may_load(deps.storage)?.unwrap_or_default(),
+    open_challenges: OPEN_CHALLENGE_COUNT.may_load(deps.storage)?.unwrap_or_default(),
+    players: PLAYER_COUNT.may_load(deps.storage)?.unwrap_or_default(),
+  })
+}
+
+fn query_get_config(deps: Deps) -> StdResult<Config> {
+  CONFIG.load(deps.storage)
+}
+
+fn query_get_queue(deps: Deps) -> StdResult<Vec<QueueEntry>> {
+  QUEUE
+    .range(deps.storage, None, None, Order::Ascending)
+    .map(|item| item.map(|(_, entry)| entry))
+    .collect()
+}
+
+fn query_get_match(deps: Deps, match_id: u64) -> StdResult<MatchSummary> {
+  let game_match = MATCHES.load(deps.storage, match_id)?;
+  let games = load_match_games(deps.storage, &game_match)?;
+  let (player1_points, player2_points) = game_match.score(&games);
+  Ok(MatchSummary {
+    finished: game_match.finished,
+    games: games.iter().map(GameSummary::from).collect(),
+    match_id,
+    player1: game_match.player1.to_string(),
+    player1_points,
+    player2: game_match.player2.to_string(),
+    player2_points,
+    winner: game_match.winner.map(|winner| winner.to_string()),
+  })
+}
+
+fn query_get_tournament(deps: Deps, tournament_id: u64) -> StdResult<Tournament> {
+  TOURNAMENTS.load(deps.storage, tournament_id)
+}
+
+fn query_get_tournament_standings(deps: Deps, tournament_id: u64) -> StdResult<Vec<TournamentStanding>> {
+  let tournament = TOURNAMENTS.load(deps.storage, tournament_id)?;
+  let records = load_tournament_records(deps.storage, &tournament)?;
+  Ok(
+    standings(&records)
+      .into_iter()
+      .map(|(player, record, buchholz)| TournamentStanding {
+        buchholz,
+        byes: record.byes,
+        games_played: (record.opponents.len() as u64),
+        player: player.to_string(),
+        points: record.points,
+      })
+      .collect(),
+  )
+}
+
+fn query_get_board(deps: Deps, game_id: u64) -> StdResult<CwChessBoard> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+  match game.get_board() {
+    Ok(board) => Ok(board),
+    Err(e) => Err(StdError::generic_err(e.to_string())),
+  }
+}
+
+fn query_get_legal_moves(deps: Deps, game_id: u64, square: &str) -> StdResult<Vec<String>> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+  match game.get_legal_moves(square) {
+    Ok(moves) => Ok(moves),
+    Err(e) => Err(StdError::generic_err(e.to_string())),
+  }
+}
+
+fn query_export_pgn(deps: Deps, env: Env, game_id: u64) -> StdResult<String> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+  match game.to_pgn(env.contract.address.as_str()) {
+    Ok(pgn) => Ok(pgn),
+    Err(e) => Err(StdError::generic_err(e.to_string())),
+  }
+}
+
+fn query_get_fen_at_move(deps: Deps, game_id: u64, ply: u64) -> StdResult<String> {
   let games_map = get_games_map();
   let game = games_map.load(deps.storage, game_id)?;
+  match game.fen_at_ply(ply as usize) {
+    Ok(fen) => Ok(fen),
+    Err(e) => Err(StdError::generic_err(e.to_string())),
+  }
+}
 
-  Ok(game)
+fn query_get_opening(deps: Deps, game_id: u64) -> StdResult<OpeningSummary> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+  match game.get_opening() {
+    Ok(Some((eco, name))) => Ok(OpeningSummary {
+      eco: Some(eco.to_string()),
+      name: name.to_string(),
+    }),
+    Ok(None) => Ok(OpeningSummary {
+      eco: None,
+      name: "Unknown".to_string(),
+    }),
+    Err(e) => Err(StdError::generic_err(e.to_string())),
+  }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn query_get_challenges(
   deps: Deps,
+  env: Env,
   after: Option<u64>,
+  hide_expired: Option<bool>,
+  limit: Option<u32>,
+  max_block_limit: Option<u64>,
+  min_block_limit: Option<u64>,
   player: Option<String>,
 ) -> StdResult<Vec<Challenge>> {
   let challenges_map = get_challenges_map();
   let after = after.map(Bound::exclusive);
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  let hide_expired = hide_expired.unwrap_or(false);
+  let visible = |challenge: &Challenge| -> bool {
+    !hide_expired
+      || challenge
+        .expires_at_block
+        .is_none_or(|expires_at_block| env.block.height <= expires_at_block)
+  };
+  // challenges without a block_limit only match when no range is given
+  let in_block_limit_range = |challenge: &Challenge| -> bool {
+    if min_block_limit.is_none() && max_block_limit.is_none() {
+      return true;
+    }
+    challenge.block_limit.is_some_and(|block_limit| {
+      min_block_limit.is_none_or(|min| block_limit >= min)
+        && max_block_limit.is_none_or(|max| block_limit <= max)
+    })
+  };
 
   let challenges = match player {
     None => {
@@ -385,7 +2944,11 @@ fn query_get_challenges(
         .range(deps.storage, after, None, Order::Ascending)
         .map(|result| -> Challenge { result.unwrap().1 });
 
-      open_challenges.take(25).collect::<Vec<_>>()
+      open_challenges
+        .filter(visible)
+        .filter(in_block_limit_range)
+        .take(limit)
+        .collect::<Vec<_>>()
     }
     Some(addr) => {
       let addr = deps.api.addr_validate(&addr)?;
@@ -405,7 +2968,9 @@ fn query_get_challenges(
       merge_iters(created_by, opponent, |c1, c2| -> bool {
         c1.challenge_id <= c2.challenge_id
       })
-      .take(25)
+      .filter(visible)
+      .filter(in_block_limit_range)
+      .take(limit)
       .collect::<Vec<_>>()
     }
   };
@@ -413,15 +2978,97 @@ fn query_get_challenges(
   Ok(challenges)
 }
 
+fn query_get_incoming_challenges(
+  deps: Deps,
+  after: Option<u64>,
+  limit: Option<u32>,
+  player: String,
+) -> StdResult<Vec<Challenge>> {
+  let challenges_map = get_challenges_map();
+  query_get_indexed_challenges(deps, &challenges_map.idx.opponent, after, limit, player)
+}
+
+fn query_get_outgoing_challenges(
+  deps: Deps,
+  after: Option<u64>,
+  limit: Option<u32>,
+  player: String,
+) -> StdResult<Vec<Challenge>> {
+  let challenges_map = get_challenges_map();
+  query_get_indexed_challenges(deps, &challenges_map.idx.created_by, after, limit, player)
+}
+
+// challenges of player in one index, by challenge id
+fn query_get_indexed_challenges(
+  deps: Deps,
+  index: &MultiIndex<Addr, Challenge, u64>,
+  after: Option<u64>,
+  limit: Option<u32>,
+  player: String,
+) -> StdResult<Vec<Challenge>> {
+  let addr = deps.api.addr_validate(&player)?;
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  index
+    .prefix(addr)
+    .range(deps.storage, after.map(Bound::exclusive), None, Order::Ascending)
+    .map(|result| result.map(|(_, challenge)| challenge))
+    .take(limit)
+    .collect()
+}
+
+fn query_get_games_by_result(
+  deps: Deps,
+  after: Option<u64>,
+  limit: Option<u32>,
+  result: CwChessGameOver,
+) -> StdResult<Vec<GameSummary>> {
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  get_games_map()
+    .idx
+    .status
+    .prefix(status_key(Some(&result)))
+    .range(deps.storage, after.map(Bound::exclusive), None, Order::Ascending)
+    .map(|result| result.map(|(_, game)| GameSummary::from(&game)))
+    .take(limit)
+    .collect()
+}
+
+fn query_get_games_started_between(
+  deps: Deps,
+  from_block: u64,
+  limit: Option<u32>,
+  to_block: u64,
+) -> StdResult<Vec<GameSummary>> {
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  get_games_map()
+    .idx
+    .block_start
+    .range(
+      deps.storage,
+      Some(Bound::inclusive((from_block, 0))),
+      Some(Bound::inclusive((to_block, u64::MAX))),
+      Order::Ascending,
+    )
+    .map(|result| result.map(|(_, game)| GameSummary::from(&game)))
+    .take(limit)
+    .collect()
+}
+
 fn query_get_games(
   deps: Deps,
   after: Option<u64>,
   game_over: Option<bool>,
+  limit: Option<u32>,
   player: Option<String>,
+  status_filter: Option<StatusFilter>,
 ) -> StdResult<Vec<GameSummary>> {
   let games_map = get_games_map();
   let after = after.map(Bound::exclusive);
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
   let game_over = game_over.unwrap_or(false);
+  if status_filter.is_some() && player.is_none() {
+    return Err(StdError::generic_err("status_filter needs a player"));
+  }
 
   let games = match player {
     None => {
@@ -432,7 +3079,7 @@ fn query_get_games(
       all_games
         .filter(|g| -> bool { game_over || g.status.is_none() })
         .map(|game| -> GameSummary { GameSummary::from(&game) })
-        .take(25)
+        .take(limit)
         .collect::<Vec<_>>()
     }
     Some(addr) => {
@@ -446,16 +3093,28 @@ fn query_get_games(
       let player2 = games_map
         .idx
         .player2
-        .prefix(addr)
+        .prefix(addr.clone())
         .range(deps.storage, after, None, Order::Ascending)
         .map(|result| -> CwChessGame { result.unwrap().1 });
 
+      // self-play games are in both indexes, so skip the second copy
+      let mut last_game_id = None;
       merge_iters(player1, player2, |g1, g2| -> bool {
         g1.game_id <= g2.game_id
       })
-      .filter(|g| -> bool { game_over || g.status.is_none() })
+      .filter(move |g| -> bool {
+        let is_repeat = last_game_id == Some(g.game_id);
+        last_game_id = Some(g.game_id);
+        !is_repeat
+      })
+      .filter(|g| -> bool {
+        match &status_filter {
+          Some(status_filter) => player_status(g, &addr) == Some(status_filter.clone()),
+          None => game_over || g.status.is_none(),
+        }
+      })
       .map(|game| -> GameSummary { GameSummary::from(&game) })
-      .take(25)
+      .take(limit)
       .collect::<Vec<_>>()
     }
   };
@@ -463,30 +3122,122 @@ fn query_get_games(
   Ok(games)
 }
 
+fn query_get_active_games(deps: Deps, player: String) -> StdResult<Vec<GameSummary>> {
+  let player = deps.api.addr_validate(&player)?;
+  let games_map = get_games_map();
+  ACTIVE_GAMES
+    .prefix(player)
+    .keys(deps.storage, None, None, Order::Ascending)
+    .map(|game_id| -> StdResult<GameSummary> {
+      Ok(GameSummary::from(&games_map.load(deps.storage, game_id?)?))
+    })
+    .collect()
+}
+
+fn query_get_player_turn_games(deps: Deps, player: String) -> StdResult<Vec<u64>> {
+  let player = deps.api.addr_validate(&player)?;
+  let games_map = get_games_map();
+  let mut game_ids = vec![];
+  for game_id in ACTIVE_GAMES.prefix(player.clone()).keys(deps.storage, None, None, Order::Ascending) {
+    let game_id = game_id?;
+    let game = games_map.load(deps.storage, game_id)?;
+    if game.get_turn(&player).map_err(|e| StdError::generic_err(e.to_string()))? {
+      game_ids.push(game_id);
+    }
+  }
+  Ok(game_ids)
+}
+
+// unfinished games are the ones indexed without a status
+fn query_get_timed_out_games(
+  deps: Deps,
+  env: Env,
+  after: Option<u64>,
+  limit: Option<u32>,
+) -> StdResult<Vec<TimedOutGame>> {
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  let height = env.block.height;
+  get_games_map()
+    .idx
+    .status
+    .prefix(status_key(None))
+    .range(deps.storage, after.map(Bound::exclusive), None, Order::Ascending)
+    .filter_map(|result| match result {
+      Ok((_, game)) if game.timeout_pending(height) => game.turn_color().map(|timed_out_color| {
+        Ok(TimedOutGame {
+          game: GameSummary::from(&game),
+          timed_out_color,
+        })
+      }),
+      Ok(_) => None,
+      Err(e) => Some(Err(e)),
+    })
+    .take(limit)
+    .collect()
+}
+
+fn query_validate_fen(fen: &str) -> FenValidation {
+  match validate_fen(fen) {
+    Ok(()) => FenValidation { error: None, valid: true },
+    Err(error) => FenValidation { error: Some(error), valid: false },
+  }
+}
+
 fn query_valid_move(
   deps: Deps,
   game_id: u64,
-  player: &String,
-  move_str: &String,
-) -> StdResult<bool> {
+  player: &str,
+  move_str: &str,
+) -> StdResult<MoveValidation> {
   // load the game
   let games_map = get_games_map();
   let game = games_map.load(deps.storage, game_id)?;
 
   // validate the player
-  let addr = deps.api.addr_validate(&player)?;
+  let addr = deps.api.addr_validate(player)?;
 
   // validate the move
-  match game.valid_move(&addr, move_str) {
-    Ok(valid) => Ok(valid),
-    Err(_) => Ok(false),
+  let reason = match game.valid_move(&addr, move_str) {
+    Ok(true) => check_promotion_piece(deps.storage, move_str).err(),
+    Ok(false) => Some(ContractError::InvalidMove {}),
+    Err(e @ ContractError::GameAlreadyOver {})
+    | Err(e @ ContractError::InvalidMove {})
+    | Err(e @ ContractError::KingInCheck {})
+    | Err(e @ ContractError::NotYourGame {})
+    | Err(e @ ContractError::NotYourTurn {}) => Some(e),
+    Err(e) => return Err(StdError::generic_err(e.to_string())),
+  };
+  Ok(MoveValidation {
+    legal: reason.is_none(),
+    reason: reason.map(|e| e.to_string()),
+  })
+}
+
+fn query_simulate_move(deps: Deps, game_id: u64, move_str: &str) -> StdResult<SimulatedMove> {
+  let game = get_games_map().load(deps.storage, game_id)?;
+  match game.simulate_move(move_str) {
+    Some(game) => Ok(SimulatedMove {
+      gives_check: game.in_check().map_err(|e| StdError::generic_err(e.to_string()))?,
+      is_game_over: game.status.clone(),
+      resulting_fen: Some(game.fen),
+      valid: true,
+    }),
+    None => Ok(SimulatedMove {
+      gives_check: false,
+      is_game_over: None,
+      resulting_fen: None,
+      valid: false,
+    }),
   }
 }
 
 fn query_get_ratings(
-  deps: Deps
+  deps: Deps,
+  time_control: Option<TimeControl>,
 ) -> StdResult<Vec<RatingSummary>> {
-  // iterate over them all
+  let provisional_games = CONFIG.load(deps.storage)?.provisional_games;
+  let time_control = time_control.unwrap_or_default();
+  // iterate over them all, keeping the time control's ratings
   let ratings: StdResult<Vec<_>> = RATINGS
     .range(
       deps.storage,
@@ -494,6 +3245,16 @@ fn query_get_ratings(
       None,
       Order::Ascending
     )
+    .filter(|result| {
+      result
+        .as_ref()
+        .map_or(true, |((_, key), _)| key == time_control.as_str())
+    })
+    .map(|result| -> StdResult<(Addr, u64, bool, TimeControl)> {
+      let ((addr, _), rating) = result?;
+      let stats = PLAYER_STATS.may_load(deps.storage, addr.clone())?.unwrap_or_default();
+      Ok((addr, rating, stats.games_played < provisional_games, time_control))
+    })
     .collect();
 
   match ratings {
@@ -503,20 +3264,94 @@ fn query_get_ratings(
   }
 }
 
+fn query_player_rating(
+  deps: Deps,
+  player: &str,
+  time_control: Option<TimeControl>,
+) -> StdResult<RatingSummary> {
+  let addr = deps.api.addr_validate(player)?;
+  let provisional_games = CONFIG.load(deps.storage)?.provisional_games;
+  let time_control = time_control.unwrap_or_default();
+  let stats = PLAYER_STATS.may_load(deps.storage, addr.clone())?.unwrap_or_default();
+  let rating = get_player_rating(deps.storage, &addr, time_control)
+    .map_err(|e| StdError::generic_err(e.to_string()))?;
+  Ok(RatingSummary::from((addr, rating, stats.games_played < provisional_games, time_control)))
+}
+
+fn query_get_player_stats(
+  deps: Deps,
+  player: &str,
+  time_control: Option<TimeControl>,
+) -> StdResult<PlayerStatsSummary> {
+  let addr = deps.api.addr_validate(player)?;
+  let provisional_games = CONFIG.load(deps.storage)?.provisional_games;
+  let stats = PLAYER_STATS.may_load(deps.storage, addr.clone())?.unwrap_or_default();
+  let rating = get_player_rating(deps.storage, &addr, time_control.unwrap_or_default())
+    .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+  Ok(PlayerStatsSummary {
+    average_move_blocks: stats.average_move_blocks(),
+    draws: stats.draws,
+    games_played: stats.games_played,
+    losses: stats.losses,
+    player: addr.to_string(),
+    provisional: stats.games_played < provisional_games,
+    rating,
+    wins: stats.wins,
+  })
+}
+
+fn query_get_player_counts(deps: Deps, player: &str) -> StdResult<PlayerCounts> {
+  let addr = deps.api.addr_validate(player)?;
+  let stats = PLAYER_STATS.may_load(deps.storage, addr)?.unwrap_or_default();
+  Ok(PlayerCounts {
+    challenges: stats.challenges,
+    games: stats.games_started,
+  })
+}
+
 fn query_get_turn(
   deps: Deps,
   game_id: u64,
-  player: &String,
+  player: &str,
 ) -> StdResult<bool> {
   let games_map = get_games_map();
   let game = games_map.load(deps.storage, game_id)?;
 
   // validate the player
-  let addr = deps.api.addr_validate(&player)?;  
+  let addr = deps.api.addr_validate(player)?;
+
+  game.get_turn(&addr).map_err(|e| StdError::generic_err(e.to_string()))
+}
+fn query_get_turn_color(deps: Deps, game_id: u64) -> StdResult<Option<CwChessColor>> {
+  Ok(get_games_map().load(deps.storage, game_id)?.turn_color())
+}
+
+fn query_get_turn_info(
+  deps: Deps,
+  env: Env,
+  game_id: u64,
+  player: Option<String>,
+) -> StdResult<TurnInfoSummary> {
+  let games_map = get_games_map();
+  let game = games_map.load(deps.storage, game_id)?;
+  let player = player.map(|player| deps.api.addr_validate(&player)).transpose()?;
 
-   // validate the move
-  Ok(match game.get_turn(&addr) {
-    Ok(turn) => turn,
-    Err(_) => false,
+  let turn_color = match game.turn_color() {
+    Some(turn_color) => turn_color,
+    None => return Err(StdError::generic_err(ContractError::GameAlreadyOver {}.to_string())),
+  };
+  let player_to_move = match turn_color {
+    CwChessColor::White => game.player1.clone(),
+    CwChessColor::Black => game.player2.clone(),
+  };
+  let timeout_block = game.timeout_block();
+
+  Ok(TurnInfoSummary {
+    blocks_remaining: timeout_block.map(|block| block.saturating_sub(env.block.height)),
+    is_player_turn: player.map(|player| player == player_to_move),
+    player_to_move: player_to_move.to_string(),
+    timeout_block,
+    turn_color,
   })
-}
\ No newline at end of file
+}
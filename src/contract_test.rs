@@ -1,20 +1,25 @@
 #[cfg(test)]
 mod tests {
-  use crate::contract::{execute, instantiate, query};
-  use crate::cwchess::{CwChessAction, CwChessColor, CwChessGame, CwChessGameOver};
+  use crate::contract::{execute, instantiate, migrate, query, reply};
+  use crate::cwchess::{chess960_fen, game_seed, odds_fen, validate_fen, CwChessAction, CwChessColor, CwChessGame, CwChessGameOver, OddsType};
   use crate::error::ContractError;
-  use crate::msg::{ExecuteMsg, GameSummary, InstantiateMsg, QueryMsg};
+  use crate::msg::{AnnotationSummary, ClockSummary, CompletionHookMsg, ContractStats, ExecuteMsg, FenValidation, GameDetailed, GameMetrics, GameRatings, GameSummary, HeadToHeadSummary, InstantiateMsg, LatestMoveSummary, MatchSummary, MigrateMsg, MoveSummary, MoveValidation, PlayerCounts, PlayerGameRating, PlayerStatsSummary, QueryMsg, RatingHistoryPoint, RatingSummary, SimulatedMove, StatusFilter, TimedOutGame, TournamentStanding, TurnInfoSummary};
+  use crate::queue::QueueEntry;
+  use crate::state::{get_games_map, Challenge, Config, TimeControl, CONFIG, LEGACY_RATINGS, LEGACY_RATING_HISTORY, RATINGS, RATING_HISTORY, STATE};
+  use crate::tournament::{Tournament, TournamentFormat};
 
   use cosmwasm_std::testing::{
     mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
   };
-  use cosmwasm_std::{coins, from_binary, Env};
+  use cosmwasm_std::{coins, from_binary, to_binary, Addr, BankMsg, Coin, Env, Reply, Storage, SubMsg, SubMsgResult, WasmMsg};
+  use cw2::{get_contract_version, set_contract_version};
+  use cw_storage_plus::{Index, MultiIndex, PrimaryKey};
 
   #[test]
   fn test_initialize() {
     let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
 
-    let msg = InstantiateMsg {};
+    let msg = InstantiateMsg::default();
     let info = mock_info("creator", &coins(1000, "earth"));
 
     // we can just call .unwrap() to assert this was a success
@@ -31,7 +36,7 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("owner", &[]),
-      InstantiateMsg {},
+      InstantiateMsg::default(),
     )
     .unwrap();
     // create challenge
@@ -39,11 +44,7 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("creator", &[]),
-      ExecuteMsg::CreateChallenge {
-        block_limit: None,
-        opponent: None,
-        play_as: None,
-      },
+      create_challenge(NewChallenge::default()),
     )
     .unwrap();
 
@@ -87,7 +88,7 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("owner", &[]),
-      InstantiateMsg {},
+      InstantiateMsg::default(),
     )
     .unwrap();
     // create challenge
@@ -95,11 +96,10 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("creator", &[]),
-      ExecuteMsg::CreateChallenge {
-        block_limit: None,
+      create_challenge(NewChallenge {
         opponent: Some("opponent".to_string()),
-        play_as: None,
-      },
+        ..NewChallenge::default()
+      }),
     )
     .unwrap();
 
@@ -138,7 +138,7 @@ mod tests {
   fn test_create_challenge() {
     let mut deps = mock_dependencies();
 
-    let msg = InstantiateMsg {};
+    let msg = InstantiateMsg::default();
     let mut env = mock_env();
     env.block.height = 123;
     let info = mock_info("owner", &coins(1000, "hello"));
@@ -147,11 +147,10 @@ mod tests {
     assert_eq!(0, init_res.messages.len());
 
     // create a challenge with an opponent
-    let msg = ExecuteMsg::CreateChallenge {
-      block_limit: None,
+    let msg = create_challenge(NewChallenge {
       opponent: Some("opponent".to_string()),
-      play_as: None,
-    };
+      ..NewChallenge::default()
+    });
     let mut env = mock_env();
     env.block.height = 456;
     let info = mock_info("creator", &[]);
@@ -174,7 +173,7 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("owner", &[]),
-      InstantiateMsg {},
+      InstantiateMsg::default(),
     )
     .unwrap();
     // create challenge
@@ -182,12 +181,11 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("black", &[]),
-      ExecuteMsg::CreateChallenge {
-        block_limit: None,
-        opponent: None,
+      create_challenge(NewChallenge {
         // creator is black
         play_as: Some(CwChessColor::Black),
-      },
+        ..NewChallenge::default()
+      }),
     )
     .unwrap();
     // opponent can accept
@@ -254,7 +252,7 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("owner", &[]),
-      InstantiateMsg {},
+      InstantiateMsg::default(),
     )
     .unwrap();
 
@@ -263,11 +261,10 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("one", &[]),
-      ExecuteMsg::CreateChallenge {
-        block_limit: None,
-        opponent: None,
+      create_challenge(NewChallenge {
         play_as: Some(CwChessColor::Black),
-      },
+        ..NewChallenge::default()
+      }),
     )
     .unwrap();
     execute(
@@ -286,7 +283,9 @@ mod tests {
         QueryMsg::GetGames {
           after: None,
           game_over: None,
+          limit: None,
           player: None,
+          status_filter: None,
         },
       )
       .unwrap(),
@@ -300,12 +299,11 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("two", &[]),
-      ExecuteMsg::CreateChallenge {
-        block_limit: None,
-        opponent: None,
+      create_challenge(NewChallenge {
         // creator is black
         play_as: Some(CwChessColor::Black),
-      },
+        ..NewChallenge::default()
+      }),
     )
     .unwrap();
     // opponent can accept
@@ -339,7 +337,9 @@ mod tests {
         QueryMsg::GetGames {
           after: None,
           game_over: None,
+          limit: None,
           player: Some("one".to_string()),
+          status_filter: None,
         },
       )
       .unwrap(),
@@ -365,7 +365,7 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("owner", &[]),
-      InstantiateMsg {},
+      InstantiateMsg::default(),
     )
     .unwrap();
     // create challenge
@@ -373,12 +373,11 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("black", &[]),
-      ExecuteMsg::CreateChallenge {
-        block_limit: None,
-        opponent: None,
+      create_challenge(NewChallenge {
         // creator is black
         play_as: Some(CwChessColor::Black),
-      },
+        ..NewChallenge::default()
+      }),
     )
     .unwrap();
     // opponent can accept
@@ -483,7 +482,7 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("owner", &[]),
-      InstantiateMsg {},
+      InstantiateMsg::default(),
     )
     .unwrap();
     // create game
@@ -491,12 +490,11 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("black", &[]),
-      ExecuteMsg::CreateChallenge {
-        block_limit: None,
-        opponent: None,
+      create_challenge(NewChallenge {
         // creator is black
         play_as: Some(CwChessColor::Black),
-      },
+        ..NewChallenge::default()
+      }),
     )
     .unwrap();
     execute(
@@ -559,6 +557,51 @@ mod tests {
     env
   }
 
+  // the CreateChallenge fields, so tests only set the ones they need
+  #[derive(Default)]
+  struct NewChallenge {
+    allow_self_play: bool,
+    block_limit: Option<u64>,
+    chess960: bool,
+    dedup_key: Option<String>,
+    expires_in_blocks: Option<u64>,
+    increment_blocks: Option<u64>,
+    num_games: Option<u8>,
+    odds: Option<OddsType>,
+    opponent: Option<String>,
+    play_as: Option<CwChessColor>,
+    start_fen: Option<String>,
+  }
+
+  fn create_challenge(challenge: NewChallenge) -> ExecuteMsg {
+    let NewChallenge {
+      allow_self_play,
+      block_limit,
+      chess960,
+      dedup_key,
+      expires_in_blocks,
+      increment_blocks,
+      num_games,
+      odds,
+      opponent,
+      play_as,
+      start_fen,
+    } = challenge;
+    ExecuteMsg::CreateChallenge {
+      allow_self_play,
+      block_limit,
+      chess960,
+      dedup_key,
+      expires_in_blocks,
+      increment_blocks,
+      num_games,
+      odds,
+      opponent,
+      play_as,
+      start_fen,
+    }
+  }
+
   #[test]
   fn test_timeout() {
     let mut deps = mock_dependencies();
@@ -568,7 +611,7 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("owner", &[]),
-      InstantiateMsg {},
+      InstantiateMsg::default(),
     )
     .unwrap();
     // create game with timeout
@@ -576,13 +619,13 @@ mod tests {
       deps.as_mut(),
       mock_env(),
       mock_info("black", &[]),
-      ExecuteMsg::CreateChallenge {
+      create_challenge(NewChallenge {
         // 300 blocks/per person @ ~10 blocks/minute => ~30 minutes/person
         block_limit: Some(300),
-        opponent: None,
         // creator is black
         play_as: Some(CwChessColor::Black),
-      },
+        ..NewChallenge::default()
+      }),
     )
     .unwrap();
     // game created at block 100
@@ -660,6 +703,7120 @@ mod tests {
     )
     .unwrap();
     assert_eq!(result.attributes[2].key, "status");
-    assert_eq!(result.attributes[2].value.contains("WhiteTimeout"), true);
+    assert!(result.attributes[2].value.contains("WhiteTimeout"));
+  }
+
+  #[test]
+  fn test_max_open_challenges() {
+    let mut deps = mock_dependencies();
+
+    // initialize with a limit of 3 open challenges
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        abandon_threshold: None,
+        auto_queen_promotion: None,
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: None,
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: None,
+        k_factor: None,
+        max_block_limit: None,
+        max_open_challenges: Some(3),
+        max_pairwise_challenges: None,
+        provisional_games: None,
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    )
+    .unwrap();
+
+    // create challenges up to the limit
+    for _ in 0..3 {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("creator", &[]),
+        create_challenge(NewChallenge::default()),
+      )
+      .unwrap();
+    }
+
+    // next challenge exceeds the limit
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      create_challenge(NewChallenge::default()),
+    );
+    match response.unwrap_err() {
+      ContractError::TooManyOpenChallenges { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // other players are not affected
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("other", &[]),
+      create_challenge(NewChallenge::default()),
+    )
+    .unwrap();
+
+    // accepting a challenge frees up a slot
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("other", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      create_challenge(NewChallenge::default()),
+    )
+    .unwrap();
+  }
+
+  #[test]
+  fn test_update_config() {
+    let mut deps = mock_dependencies();
+
+    // initialize with a limit of 1 open challenge
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        abandon_threshold: None,
+        auto_queen_promotion: None,
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: None,
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: None,
+        k_factor: None,
+        max_block_limit: None,
+        max_open_challenges: Some(1),
+        max_pairwise_challenges: None,
+        provisional_games: None,
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      create_challenge(NewChallenge::default()),
+    )
+    .unwrap();
+
+    // only owner can update config
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      ExecuteMsg::UpdateConfig {
+        abandon_threshold: None,
+        auto_queen_promotion: None,
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: None,
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: None,
+        k_factor: None,
+        max_block_limit: None,
+        max_open_challenges: Some(2),
+        max_pairwise_challenges: None,
+        provisional_games: None,
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // owner raises the limit
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::UpdateConfig {
+        abandon_threshold: None,
+        auto_queen_promotion: None,
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: None,
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: None,
+        k_factor: None,
+        max_block_limit: None,
+        max_open_challenges: Some(2),
+        max_pairwise_challenges: None,
+        provisional_games: None,
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    )
+    .unwrap();
+    let attr = response
+      .attributes
+      .iter()
+      .find(|attr| attr.key == "max_open_challenges")
+      .unwrap();
+    assert_eq!(&attr.value, "2");
+
+    // creator can now open a second challenge
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      create_challenge(NewChallenge::default()),
+    )
+    .unwrap();
+  }
+
+  #[test]
+  fn test_ownership_transfer() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let update_config = || ExecuteMsg::UpdateConfig {
+      abandon_threshold: None,
+      auto_queen_promotion: None,
+      blitz_max_blocks: None,
+      bullet_max_blocks: None,
+      challenge_fee: None,
+      completion_hook: None,
+      decay_after_blocks: None,
+      decay_amount: None,
+      draw_offer_cooldown_blocks: None,
+      high_rating_k_factor: None,
+      high_rating_threshold: None,
+      inactivity_blocks: None,
+      k_factor: None,
+      max_block_limit: None,
+      max_open_challenges: Some(2),
+      max_pairwise_challenges: None,
+      provisional_games: None,
+      provisional_k_factor: None,
+      rapid_max_blocks: None,
+      rate_draws: None,
+      rating_ceiling: None,
+      rating_floor: None,
+      refund_challenge_fee: None,
+      require_resign_confirmation: None,
+      starting_rating: None,
+      treasury: None,
+    };
+
+    // only owner can propose
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("other", &[]),
+      ExecuteMsg::ProposeNewOwner { new_owner: "other".to_string() },
+    );
+    match response.unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::ProposeNewOwner { new_owner: "new_owner".to_string() },
+    )
+    .unwrap();
+
+    // only the pending owner can accept
+    for sender in ["other", "owner"] {
+      let response = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(sender, &[]),
+        ExecuteMsg::AcceptOwnership {},
+      );
+      match response.unwrap_err() {
+        ContractError::Unauthorized { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+    // owner keeps control until the transfer is accepted
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update_config()).unwrap();
+
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("new_owner", &[]),
+      ExecuteMsg::AcceptOwnership {},
+    )
+    .unwrap();
+    let attr = response.attributes.iter().find(|attr| attr.key == "owner").unwrap();
+    assert_eq!(&attr.value, "new_owner");
+
+    // new owner is in control, previous owner isn't
+    execute(deps.as_mut(), mock_env(), mock_info("new_owner", &[]), update_config()).unwrap();
+    let response = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update_config());
+    match response.unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // proposal is used up
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("new_owner", &[]),
+      ExecuteMsg::AcceptOwnership {},
+    );
+    match response.unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_resign_message() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // create game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      create_challenge(NewChallenge {
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // only players can resign
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("other", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::NotYourGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // black can resign even though it is white's turn
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    let attrs = response.attributes;
+    assert_eq!(&attrs[0].value, "resign");
+    assert_eq!(&attrs[1].key, "game_id");
+    assert_eq!(&attrs[1].value, "1");
+    assert_eq!(&attrs[2].key, "status");
+    assert_eq!(&attrs[2].value, "BlackResigns");
+
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::BlackResigns));
+
+    // ratings updated in white's favor
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings { time_control: None }).unwrap(),
+    )
+    .unwrap();
+    let rating = |player: &str| -> u64 {
+      ratings.iter().find(|r| r.player == player).unwrap().rating
+    };
+    assert_eq!(rating("white"), 1016);
+    assert_eq!(rating("black"), 984);
+
+    // cannot resign after game over
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::GameAlreadyOver { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_offer_rematch() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // create game with time limit
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      create_challenge(NewChallenge {
+        block_limit: Some(300),
+        // creator is black
+        play_as: Some(CwChessColor::Black),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // cannot offer rematch while game is in progress
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::OfferRematch { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::GameNotOver { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+
+    // only players can offer a rematch
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("other", &[]),
+      ExecuteMsg::OfferRematch { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::NotYourGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // white offers rematch, to play as black
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::OfferRematch { game_id: 1 },
+    )
+    .unwrap();
+    let challenge = from_binary::<Challenge>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetChallenge { challenge_id: 2 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(challenge.block_limit, Some(300));
+    assert_eq!(challenge.created_by, "white");
+    assert_eq!(challenge.opponent, Some(Addr::unchecked("black")));
+    assert_eq!(challenge.play_as, Some(CwChessColor::Black));
+
+    // only one outstanding rematch offer per game
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::OfferRematch { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::RematchAlreadyOffered { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // opponent accepts rematch with swapped colors
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 2 },
+    )
+    .unwrap();
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 2 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.block_limit, Some(300));
+    assert_eq!(game.player1, "black");
+    assert_eq!(game.player2, "white");
+  }
+
+  #[test]
+  fn test_start_fen() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    // cannot create challenge with an invalid position
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::Black),
+        start_fen: Some("4k3/8/8/8/8/8/8/3KK3 w - - 0 1".to_string()),
+        ..NewChallenge::default()
+      }),
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidFen { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // king and pawn endgame with black to move
+    let start_fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1".to_string();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::Black),
+        start_fen: Some(start_fen.clone()),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.fen, start_fen);
+    assert_eq!(game.start_fen, Some(start_fen));
+    assert_eq!(game.turn_color(), Some(CwChessColor::Black));
+
+    // black moves first
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("e4"),
+        game_id: 1,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::NotYourTurn { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("Kd7"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.turn_color(), Some(CwChessColor::White));
+  }
+
+  #[test]
+  fn test_chess960() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    // chess960 challenge
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        chess960: true,
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    // position is decided on accept
+    execute(
+      deps.as_mut(),
+      block_env(1000),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    let seed = game_seed(1, 1000, &Addr::unchecked("white"), &Addr::unchecked("black"));
+    assert_eq!(game.seed, Some(seed));
+    let start_fen = chess960_fen(seed);
+    assert_eq!(game.fen, start_fen);
+    assert_eq!(game.start_fen, Some(start_fen));
+    assert_eq!(game.turn_color(), Some(CwChessColor::White));
+
+    // explicit start fen takes precedence
+    let start_fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".to_string();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        chess960: true,
+        play_as: Some(CwChessColor::White),
+        start_fen: Some(start_fen.clone()),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(1000),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 2 },
+    )
+    .unwrap();
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 2 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.fen, start_fen);
+  }
+
+  #[test]
+  fn test_game_history() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(100),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    for (height, player, action) in [
+      (101, "white", "e4"),
+      (103, "black", "d5"),
+      (104, "white", "exd5"),
+      (110, "black", "Qxd5"),
+      (111, "white", "Nc3"),
+    ] {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(action),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+
+    let history = from_binary::<Vec<MoveSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGameHistory { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    let summary = |block, blocks_elapsed, color, move_number, san: &str| MoveSummary {
+      block,
+      blocks_elapsed,
+      color,
+      move_number,
+      san: san.to_string(),
+    };
+    assert_eq!(
+      history,
+      vec![
+        summary(101, 1, CwChessColor::White, 1, "e4"),
+        summary(103, 2, CwChessColor::Black, 1, "d5"),
+        summary(104, 1, CwChessColor::White, 2, "exd5"),
+        summary(110, 6, CwChessColor::Black, 2, "Qxd5"),
+        summary(111, 1, CwChessColor::White, 3, "Nc3"),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_export_pgn() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // scholar's mate
+    for (player, action) in [
+      ("white", "e4"),
+      ("black", "e5"),
+      ("white", "Bc4"),
+      ("black", "Nc6"),
+      ("white", "Qh5"),
+      ("black", "Nf6"),
+      ("white", "Qxf7"),
+    ] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(action),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+
+    let pgn = from_binary::<String>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::ExportPgn { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+      pgn,
+      [
+        "[Event \"cosmos-chess game 1\"]",
+        "[Site \"cosmos2contract\"]",
+        "[Date \"????.??.??\"]",
+        "[Round \"-\"]",
+        "[White \"white\"]",
+        "[Black \"black\"]",
+        "[Result \"1-0\"]",
+        "",
+        "1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0",
+        "",
+      ]
+      .join("\n")
+    );
+  }
+
+  #[test]
+  fn test_increment_clocks() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    // 100 blocks per player, plus 10 blocks per move
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        block_limit: Some(100),
+        increment_blocks: Some(10),
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(0),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let clocks = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, height| {
+      from_binary::<Option<ClockSummary>>(
+        &query(deps.as_ref(), block_env(height), QueryMsg::GetClocks { game_id: 1 }).unwrap(),
+      )
+      .unwrap()
+    };
+    assert_eq!(clocks(&deps, 50), Some(ClockSummary { black: 100, white: 100 }));
+
+    // clock starts at first move
+    for (height, player, action) in [(1, "white", "e4"), (51, "black", "e5"), (151, "white", "Nf3")] {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(action),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+    // white: 100 + 10 - 100 + 10, black: 100 - 50 + 10 - 49 running
+    assert_eq!(clocks(&deps, 200), Some(ClockSummary { black: 11, white: 20 }));
+    assert_eq!(clocks(&deps, 400), Some(ClockSummary { black: 0, white: 20 }));
+
+    // black runs out of blocks
+    let result = execute(
+      deps.as_mut(),
+      block_env(212),
+      mock_info("black", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("Nc6"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    assert_eq!(result.attributes[2].key, "status");
+    assert!(result.attributes[2].value.contains("BlackTimeout"));
+  }
+
+  #[test]
+  fn test_expire_challenge() {
+    let mut deps = mock_dependencies();
+
+    // initialize, challenges abandoned after 1000 blocks
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        abandon_threshold: Some(1000),
+        auto_queen_promotion: None,
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: None,
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: None,
+        k_factor: None,
+        max_block_limit: None,
+        max_open_challenges: None,
+        max_pairwise_challenges: None,
+        provisional_games: None,
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(100),
+      mock_info("creator", &[]),
+      create_challenge(NewChallenge::default()),
+    )
+    .unwrap();
+
+    // not abandoned yet
+    let response = execute(
+      deps.as_mut(),
+      block_env(1099),
+      mock_info("anyone", &[]),
+      ExecuteMsg::ExpireChallenge { challenge_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::ChallengeNotAbandoned { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // anyone can expire it after the threshold
+    let response = execute(
+      deps.as_mut(),
+      block_env(1100),
+      mock_info("anyone", &[]),
+      ExecuteMsg::ExpireChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(response.attributes[0].value, "expire_challenge");
+    assert!(query(deps.as_ref(), mock_env(), QueryMsg::GetChallenge { challenge_id: 1 }).is_err());
+
+    // already removed
+    let response = execute(
+      deps.as_mut(),
+      block_env(1100),
+      mock_info("anyone", &[]),
+      ExecuteMsg::ExpireChallenge { challenge_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::ChallengeNotFound { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_challenge_expiry() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    // play me within 1000 blocks
+    for challenge_id in 1..=2 {
+      execute(
+        deps.as_mut(),
+        block_env(100),
+        mock_info("creator", &[]),
+        create_challenge(NewChallenge {
+          expires_in_blocks: Some(1000),
+          opponent: Some("opponent".to_string()),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      let challenge = from_binary::<Challenge>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetChallenge { challenge_id }).unwrap(),
+      )
+      .unwrap();
+      assert_eq!(challenge.expires_at_block, Some(1100));
+    }
+
+    // last block to accept
+    execute(
+      deps.as_mut(),
+      block_env(1100),
+      mock_info("opponent", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // past the deadline
+    let response = execute(
+      deps.as_mut(),
+      block_env(1101),
+      mock_info("opponent", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 2 },
+    );
+    match response.unwrap_err() {
+      ContractError::ChallengeExpired { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // expired challenges can be filtered out
+    let get_challenges = |hide_expired| {
+      from_binary::<Vec<Challenge>>(
+        &query(
+          deps.as_ref(),
+          block_env(1101),
+          QueryMsg::GetChallenges {
+            after: None,
+            hide_expired,
+            limit: None,
+            max_block_limit: None,
+            min_block_limit: None,
+            player: Some("opponent".to_string()),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    assert_eq!(get_challenges(None).len(), 1);
+    assert_eq!(get_challenges(Some(true)).len(), 0);
+  }
+
+  #[test]
+  fn test_pagination_limit() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    // 60 open challenges, the first 55 accepted as games
+    for i in 1..=60 {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&format!("creator{}", i), &[]),
+        create_challenge(NewChallenge::default()),
+      )
+      .unwrap();
+    }
+    for challenge_id in 1..=55 {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("acceptor", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    }
+
+    let get_games = |after, limit, player: Option<&str>| {
+      from_binary::<Vec<GameSummary>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetGames {
+            after,
+            game_over: None,
+            limit,
+            player: player.map(String::from),
+            status_filter: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .iter()
+      .map(|game| game.game_id)
+      .collect::<Vec<_>>()
+    };
+    // default, clamped and explicit limits
+    assert_eq!(get_games(None, None, None).len(), 25);
+    assert_eq!(get_games(None, Some(100), None).len(), 50);
+    assert_eq!(get_games(Some(10), Some(5), None), vec![11, 12, 13, 14, 15]);
+    assert_eq!(get_games(Some(50), Some(10), None), vec![51, 52, 53, 54, 55]);
+    assert_eq!(get_games(Some(20), Some(3), Some("acceptor")), vec![21, 22, 23]);
+
+    let get_challenges = |after, limit| {
+      from_binary::<Vec<Challenge>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetChallenges {
+            after,
+            hide_expired: None,
+            limit,
+            max_block_limit: None,
+            min_block_limit: None,
+            player: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .iter()
+      .map(|challenge| challenge.challenge_id)
+      .collect::<Vec<_>>()
+    };
+    assert_eq!(get_challenges(None, None), vec![56, 57, 58, 59, 60]);
+    assert_eq!(get_challenges(None, Some(2)), vec![56, 57]);
+    assert_eq!(get_challenges(Some(57), Some(2)), vec![58, 59]);
+  }
+
+  #[test]
+  fn test_get_games_by_ids() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    // games 1 and 2
+    for i in 1..=2 {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&format!("creator{}", i), &[]),
+        create_challenge(NewChallenge::default()),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("acceptor", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: i },
+      )
+      .unwrap();
+    }
+
+    // results line up with the ids, None for missing games
+    let games = from_binary::<Vec<Option<CwChessGame>>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetGamesByIds { ids: vec![2, 7, 1, 2] },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+      games.iter().map(|game| game.as_ref().map(|game| game.game_id)).collect::<Vec<_>>(),
+      vec![Some(2), None, Some(1), Some(2)]
+    );
+
+    // too many ids
+    assert!(query(
+      deps.as_ref(),
+      mock_env(),
+      QueryMsg::GetGamesByIds { ids: (1..=26).collect() },
+    )
+    .is_err());
+    assert_eq!(
+      from_binary::<Vec<Option<CwChessGame>>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetGamesByIds { ids: (1..=25).collect() },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .len(),
+      25
+    );
+  }
+
+  #[test]
+  fn test_game_events() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        block_limit: Some(300),
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    let response = execute(
+      deps.as_mut(),
+      block_env(100),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(response.events.len(), 1);
+    let event = &response.events[0];
+    assert_eq!(event.ty, "game_started");
+    let attributes = event
+      .attributes
+      .iter()
+      .map(|attr| (attr.key.as_str(), attr.value.as_str()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      attributes,
+      vec![
+        ("game_id", "1"),
+        ("player1", "white"),
+        ("player2", "black"),
+        ("block_start", "100"),
+        ("block_limit", "300"),
+        ("seed", &game_seed(1, 100, &Addr::unchecked("white"), &Addr::unchecked("black")).to_string()),
+      ]
+    );
+
+    // fool's mate
+    let mut response = None;
+    for (player, action) in [("white", "f3"), ("black", "e5"), ("white", "g4"), ("black", "Qh4")] {
+      let r = execute(
+        deps.as_mut(),
+        block_env(101),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(action),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+      if action != "Qh4" {
+        assert!(r.events.is_empty());
+      }
+      response = Some(r);
+    }
+    let response = response.unwrap();
+    assert_eq!(response.events.len(), 1);
+    let event = &response.events[0];
+    assert_eq!(event.ty, "game_ended");
+    let attributes = event
+      .attributes
+      .iter()
+      .map(|attr| (attr.key.as_str(), attr.value.as_str()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      attributes,
+      vec![
+        ("game_id", "1"),
+        ("status", "BlackCheckmates"),
+        ("player1", "white"),
+        ("player2", "black"),
+        ("player1_rating", "984"),
+        ("player2_rating", "1016"),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_head_to_head() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    // three games, creator plays white
+    for (game_id, white, black) in [(1, "alice", "bob"), (2, "bob", "alice"), (3, "alice", "bob")] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(white, &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(black, &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: game_id },
+      )
+      .unwrap();
+    }
+    // alice beats bob, then bob beats alice
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("bob", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("alice", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 2 },
+    )
+    .unwrap();
+    // and they draw
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("alice", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::OfferDraw("e4".to_string()),
+        game_id: 3,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("bob", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::AcceptDraw,
+        game_id: 3,
+      },
+    )
+    .unwrap();
+
+    let head_to_head = |player_a: &str, player_b: &str| {
+      from_binary::<HeadToHeadSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetHeadToHead {
+            player_a: player_a.to_string(),
+            player_b: player_b.to_string(),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    let summary = head_to_head("bob", "alice");
+    assert_eq!(
+      summary,
+      HeadToHeadSummary {
+        draws: 1,
+        last_game_id: Some(3),
+        player_a: "bob".to_string(),
+        player_a_wins: 1,
+        player_b: "alice".to_string(),
+        player_b_wins: 1,
+      }
+    );
+    // same record either way around
+    let summary = head_to_head("alice", "bob");
+    assert_eq!((summary.player_a_wins, summary.player_b_wins, summary.draws), (1, 1, 1));
+
+    // players that never met
+    let summary = head_to_head("alice", "carol");
+    assert_eq!(summary.last_game_id, None);
+    assert_eq!((summary.player_a_wins, summary.player_b_wins, summary.draws), (0, 0, 0));
+  }
+
+  #[test]
+  fn test_provisional_ratings() {
+    let mut deps = mock_dependencies();
+
+    // ratings are provisional for the first game
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        abandon_threshold: None,
+        auto_queen_promotion: None,
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: None,
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: None,
+        k_factor: None,
+        max_block_limit: None,
+        max_open_challenges: None,
+        max_pairwise_challenges: None,
+        provisional_games: Some(1),
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    )
+    .unwrap();
+
+    // alice beats bob as white, then carol beats bob as black
+    for (game_id, winner, loser, play_as) in [(1, "alice", "bob", CwChessColor::White), (2, "carol", "bob", CwChessColor::Black)] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(winner, &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(play_as),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(loser, &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: game_id },
+      )
+      .unwrap();
+      if game_id == 2 {
+        let ratings = from_binary::<Vec<RatingSummary>>(
+          &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings { time_control: None }).unwrap(),
+        )
+        .unwrap();
+        let provisional = |player: &str| -> bool {
+          ratings.iter().find(|r| r.player == player).unwrap().provisional
+        };
+        assert!(!provisional("bob"));
+        assert!(provisional("carol"));
+      }
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(loser, &[]),
+        ExecuteMsg::Resign { confirm: false, game_id },
+      )
+      .unwrap();
+    }
+
+    let stats = |player: &str| {
+      from_binary::<PlayerStatsSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetPlayerStats {
+            player: player.to_string(),
+            time_control: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    // both provisional in the first game
+    assert_eq!(stats("alice").rating, 1016);
+    // bob was established, carol provisional, so bob's rating didn't move
+    let bob = stats("bob");
+    assert_eq!(bob.rating, 984);
+    assert_eq!((bob.games_played, bob.wins, bob.losses, bob.draws), (2, 0, 2, 0));
+    assert!(!bob.provisional);
+    let carol = stats("carol");
+    assert_eq!(carol.rating, 1016);
+    assert!(!carol.provisional);
+
+    // players without games are provisional
+    let dave = stats("dave");
+    assert_eq!(dave.rating, 1000);
+    assert_eq!(dave.games_played, 0);
+    assert!(dave.provisional);
+  }
+
+  #[test]
+  fn test_k_factor_config() {
+    let mut deps = mock_dependencies();
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        high_rating_k_factor: Some(10),
+        high_rating_threshold: Some(1010),
+        k_factor: Some(20),
+        provisional_games: Some(1),
+        provisional_k_factor: Some(40),
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap();
+
+    // alice beats bob twice
+    for game_id in [1, 2] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), ExecuteMsg::AcceptChallenge { challenge_id: game_id }).unwrap();
+      execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), ExecuteMsg::Resign { confirm: false, game_id }).unwrap();
+
+      let rating = |player: &str| {
+        from_binary::<PlayerStatsSummary>(
+          &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetPlayerStats {
+              player: player.to_string(),
+              time_control: None,
+            },
+          )
+          .unwrap(),
+        )
+        .unwrap()
+        .rating
+      };
+      match game_id {
+        // both provisional, k 40
+        1 => assert_eq!((rating("alice"), rating("bob")), (1020, 980)),
+        // alice is past the high rating threshold, k 10 against bob's 20
+        _ => assert_eq!((rating("alice"), rating("bob")), (1024, 971)),
+      }
+    }
+  }
+
+  #[test]
+  fn test_block_limit_bounds() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        abandon_threshold: None,
+        auto_queen_promotion: None,
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: None,
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: None,
+        k_factor: None,
+        max_block_limit: Some(1000),
+        max_open_challenges: None,
+        max_pairwise_challenges: None,
+        provisional_games: None,
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    )
+    .unwrap();
+
+    for (block_limit, increment_blocks, valid) in [
+      (Some(0), None, false),
+      (Some(9), None, false),
+      (Some(10), None, true),
+      (Some(1000), Some(5), true),
+      (Some(1001), None, false),
+      (None, Some(5), false),
+      (None, None, true),
+    ] {
+      let response = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("creator", &[]),
+        create_challenge(NewChallenge {
+          block_limit,
+          increment_blocks,
+          ..NewChallenge::default()
+        }),
+      );
+      match (response, valid) {
+        (Ok(_), true) => {}
+        (Err(ContractError::InvalidBlockLimit {}), false) => {}
+        (response, _) => panic!("unexpected response for {:?}: {:?}", block_limit, response),
+      }
+    }
+  }
+
+  #[test]
+  fn test_claim_victory_on_abandonment() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        abandon_threshold: None,
+        auto_queen_promotion: None,
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: None,
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: Some(100),
+        k_factor: None,
+        max_block_limit: None,
+        max_open_challenges: None,
+        max_pairwise_challenges: None,
+        provisional_games: None,
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    )
+    .unwrap();
+    // create game
+    execute(
+      deps.as_mut(),
+      block_env(1),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(1),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::MakeMove("e4".to_string()),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+
+    // black has not been inactive long enough
+    let response = execute(
+      deps.as_mut(),
+      block_env(109),
+      mock_info("white", &[]),
+      ExecuteMsg::ClaimVictoryOnAbandonment { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::GameNotAbandoned { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // player to move cannot claim
+    let response = execute(
+      deps.as_mut(),
+      block_env(110),
+      mock_info("black", &[]),
+      ExecuteMsg::ClaimVictoryOnAbandonment { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::CannotClaimOwnTurn { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // only participants can claim
+    let response = execute(
+      deps.as_mut(),
+      block_env(110),
+      mock_info("other", &[]),
+      ExecuteMsg::ClaimVictoryOnAbandonment { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::NotYourGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // white claims victory
+    let response = execute(
+      deps.as_mut(),
+      block_env(110),
+      mock_info("white", &[]),
+      ExecuteMsg::ClaimVictoryOnAbandonment { game_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(response.events.len(), 1);
+    assert_eq!(response.events[0].ty, "game_ended");
+
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::BlackResigns));
+
+    // cannot claim a finished game
+    let response = execute(
+      deps.as_mut(),
+      block_env(200),
+      mock_info("white", &[]),
+      ExecuteMsg::ClaimVictoryOnAbandonment { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::GameAlreadyOver { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_admin_void_game() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // create game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // only the owner can void
+    for sender in ["white", "black", "other"] {
+      let response = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(sender, &[]),
+        ExecuteMsg::AdminVoidGame { game_id: 1 },
+      );
+      match response.unwrap_err() {
+        ContractError::Unauthorized { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::AdminVoidGame { game_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(response.events[0].ty, "game_ended");
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::Voided));
+
+    // ratings and stats are untouched
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings { time_control: None }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(ratings.len(), 2);
+    assert!(ratings.iter().all(|rating| rating.rating == 1000));
+    let stats = from_binary::<PlayerStatsSummary>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPlayerStats { player: "white".to_string(), time_control: None },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(stats.games_played, 0);
+
+    // cannot void twice or void a missing game
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::AdminVoidGame { game_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::GameAlreadyOver { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::AdminVoidGame { game_id: 2 },
+    );
+    match response.unwrap_err() {
+      ContractError::GameNotFound { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_migrate() {
+    let mut deps = mock_dependencies();
+
+    // state as written by an early release, config was kept alongside the owner
+    deps.storage.set(b"state", br#"{"owner":"owner","max_open_challenges":5}"#);
+    set_contract_version(&mut deps.storage, "cosmos-chess", "0.0.0").unwrap();
+    // a rating from before time controls
+    LEGACY_RATINGS.save(&mut deps.storage, Addr::unchecked("player"), &1200).unwrap();
+
+    let response = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    let attr = response.attributes.iter().find(|attr| attr.key == "from_version").unwrap();
+    assert_eq!(&attr.value, "0.0.0");
+    let version = get_contract_version(&deps.storage).unwrap();
+    assert_eq!(version.contract, "cosmos-chess");
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+
+    // config moves to its own item and what's missing is filled with defaults
+    let state = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.owner, "owner");
+    assert_eq!(state.pending_owner, None);
+    assert_eq!(deps.storage.get(b"state").unwrap(), br#"{"owner":"owner","pending_owner":null}"#);
+    let config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(config.max_open_challenges, 5);
+    assert_eq!(config.rapid_max_blocks, 600);
+    assert!(config.rate_draws);
+
+    // the old rating is now the correspondence rating
+    assert!(!LEGACY_RATINGS.has(&deps.storage, Addr::unchecked("player")));
+    assert_eq!(
+      RATINGS.load(&deps.storage, (Addr::unchecked("player"), TimeControl::Correspondence.as_str())).unwrap(),
+      1200
+    );
+    let stats = from_binary::<ContractStats>(&query(deps.as_ref(), mock_env(), QueryMsg::GetContractStats {}).unwrap()).unwrap();
+    assert_eq!(stats.players, 1);
+
+    // migrating again keeps existing config
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::UpdateConfig {
+        abandon_threshold: None,
+        auto_queen_promotion: None,
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: None,
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: None,
+        k_factor: None,
+        max_block_limit: None,
+        max_open_challenges: Some(3),
+        max_pairwise_challenges: None,
+        provisional_games: None,
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    )
+    .unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(CONFIG.load(&deps.storage).unwrap().max_open_challenges, 3);
+
+    // storage written by this version isn't scanned again
+    LEGACY_RATINGS.save(&mut deps.storage, Addr::unchecked("player"), &1300).unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert!(LEGACY_RATINGS.has(&deps.storage, Addr::unchecked("player")));
+
+    // no downgrades or migrating from other contracts
+    set_contract_version(&mut deps.storage, "cosmos-chess", "99.0.0").unwrap();
+    match migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err() {
+      ContractError::InvalidMigration { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    set_contract_version(&mut deps.storage, "other-contract", "0.0.0").unwrap();
+    match migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err() {
+      ContractError::InvalidMigration { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_tournament() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    // bracket size must be a power of two
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("organizer", &[]),
+      ExecuteMsg::CreateTournament {
+        block_limit: None,
+        format: None,
+        name: "cup".to_string(),
+        size: 3,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidTournamentSize { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("a", &[]),
+      ExecuteMsg::JoinTournament { tournament_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::TournamentNotFound { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("organizer", &[]),
+      ExecuteMsg::CreateTournament {
+        block_limit: None,
+        format: None,
+        name: "cup".to_string(),
+        size: 4,
+      },
+    )
+    .unwrap();
+    for player in ["a", "b", "c"] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::JoinTournament { tournament_id: 1 },
+      )
+      .unwrap();
+    }
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("a", &[]),
+      ExecuteMsg::JoinTournament { tournament_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::AlreadyInTournament { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // last player fills the bracket and starts the first round
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("d", &[]),
+      ExecuteMsg::JoinTournament { tournament_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(response.events.len(), 2);
+    assert!(response.events.iter().all(|event| event.ty == "game_started"));
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("e", &[]),
+      ExecuteMsg::JoinTournament { tournament_id: 1 },
+    );
+    match response.unwrap_err() {
+      ContractError::TournamentFull { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    let get_tournament = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<Tournament>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetTournament { tournament_id: 1 }).unwrap(),
+      )
+      .unwrap()
+    };
+    let tournament = get_tournament(&deps);
+    assert_eq!(tournament.rounds, vec![vec![1, 2]]);
+    assert_eq!(tournament.champion, None);
+
+    // white resigns every game, so black advances
+    let mut winners = vec![];
+    for game_id in [1, 2] {
+      let game = from_binary::<CwChessGame>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id }).unwrap(),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(game.player1.as_str(), &[]),
+        ExecuteMsg::Resign { confirm: false, game_id },
+      )
+      .unwrap();
+      winners.push(game.player2);
+    }
+
+    // final is created once the round is finished
+    let tournament = get_tournament(&deps);
+    assert_eq!(tournament.rounds, vec![vec![1, 2], vec![3]]);
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 3 }).unwrap(),
+    )
+    .unwrap();
+    let mut finalists = vec![game.player1.clone(), game.player2.clone()];
+    finalists.sort();
+    winners.sort();
+    assert_eq!(finalists, winners);
+
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info(game.player1.as_str(), &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 3 },
+    )
+    .unwrap();
+    assert!(response.events.iter().any(|event| event.ty == "tournament_ended"));
+    let tournament = get_tournament(&deps);
+    assert_eq!(tournament.champion, Some(game.player2));
+    assert_eq!(tournament.rounds.len(), 2);
+  }
+
+  #[test]
+  fn test_swiss_tournament() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    // more rounds than opponents (plus a bye)
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("organizer", &[]),
+      ExecuteMsg::CreateTournament {
+        block_limit: None,
+        format: Some(TournamentFormat::Swiss { rounds: 4 }),
+        name: "swiss".to_string(),
+        size: 3,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidTournamentRounds { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // odd size, so someone sits out every round
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("organizer", &[]),
+      ExecuteMsg::CreateTournament {
+        block_limit: None,
+        format: Some(TournamentFormat::Swiss { rounds: 3 }),
+        name: "swiss".to_string(),
+        size: 3,
+      },
+    )
+    .unwrap();
+    let mut response = None;
+    for player in ["a", "b", "c"] {
+      response = Some(
+        execute(
+          deps.as_mut(),
+          mock_env(),
+          mock_info(player, &[]),
+          ExecuteMsg::JoinTournament { tournament_id: 1 },
+        )
+        .unwrap(),
+      );
+    }
+    let response = response.unwrap();
+    assert_eq!(response.events[0].ty, "game_started");
+    assert_eq!(response.events[1].ty, "tournament_bye");
+
+    // white resigns every game
+    let mut pairs = vec![];
+    for game_id in 1..=3 {
+      let tournament = from_binary::<Tournament>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetTournament { tournament_id: 1 }).unwrap(),
+      )
+      .unwrap();
+      assert_eq!(tournament.rounds.len() as u64, game_id);
+      assert_eq!(tournament.champion, None);
+      let game = from_binary::<CwChessGame>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id }).unwrap(),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(game.player1.as_str(), &[]),
+        ExecuteMsg::Resign { confirm: false, game_id },
+      )
+      .unwrap();
+      let mut pair = vec![game.player1.to_string(), game.player2.to_string()];
+      pair.sort();
+      pairs.push(pair);
+    }
+    // no rematches, so every pair met once
+    pairs.sort();
+    assert_eq!(
+      pairs,
+      vec![
+        vec!["a".to_string(), "b".to_string()],
+        vec!["a".to_string(), "c".to_string()],
+        vec!["b".to_string(), "c".to_string()],
+      ]
+    );
+
+    let standings = from_binary::<Vec<TournamentStanding>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetTournamentStandings { tournament_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(standings.len(), 3);
+    for standing in standings.iter() {
+      assert_eq!(standing.byes, 1);
+      assert_eq!(standing.games_played, 2);
+    }
+    // 3 games and 3 byes, worth a point each
+    assert_eq!(standings.iter().map(|standing| standing.points).sum::<u64>(), 12);
+    assert!(standings.windows(2).all(|pair| {
+      (pair[0].points, pair[0].buchholz) >= (pair[1].points, pair[1].buchholz)
+    }));
+
+    let tournament = from_binary::<Tournament>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetTournament { tournament_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(tournament.rounds.len(), 3);
+    assert_eq!(tournament.champion, Some(Addr::unchecked(standings[0].player.as_str())));
+  }
+
+  #[test]
+  fn test_get_active_games() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // white plays black twice, and other once
+    for (challenge_id, opponent) in [(1, "black"), (2, "black"), (3, "other")] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(opponent, &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    }
+    let active_game_ids = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      from_binary::<Vec<GameSummary>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetActiveGames {
+            player: player.to_string(),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .iter()
+      .map(|game| game.game_id)
+      .collect::<Vec<_>>()
+    };
+    assert_eq!(active_game_ids(&deps, "white"), vec![1, 2, 3]);
+    assert_eq!(active_game_ids(&deps, "black"), vec![1, 2]);
+    assert_eq!(active_game_ids(&deps, "other"), vec![3]);
+
+    // fool's mate finishes game 1 for both players
+    for (player, action) in [("white", "f3"), ("black", "e5"), ("white", "g4"), ("black", "Qh4")] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(action),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+    assert_eq!(active_game_ids(&deps, "white"), vec![2, 3]);
+    assert_eq!(active_game_ids(&deps, "black"), vec![2]);
+
+    // as does resigning
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("other", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 3 },
+    )
+    .unwrap();
+    assert_eq!(active_game_ids(&deps, "white"), vec![2]);
+    assert_eq!(active_game_ids(&deps, "other"), Vec::<u64>::new());
+  }
+
+  #[test]
+  fn test_get_turn_info() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // 100 blocks per player, plus 10 blocks per move
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        block_limit: Some(100),
+        increment_blocks: Some(10),
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(0),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let turn_info = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, height, player: Option<&str>| {
+      query(
+        deps.as_ref(),
+        block_env(height),
+        QueryMsg::GetTurnInfo {
+          game_id: 1,
+          player: player.map(|player| player.to_string()),
+        },
+      )
+      .map(|binary| from_binary::<TurnInfoSummary>(&binary).unwrap())
+    };
+
+    // clock starts at first move
+    assert_eq!(
+      turn_info(&deps, 50, Some("white")).unwrap(),
+      TurnInfoSummary {
+        blocks_remaining: None,
+        is_player_turn: Some(true),
+        player_to_move: "white".to_string(),
+        timeout_block: None,
+        turn_color: CwChessColor::White,
+      }
+    );
+
+    for (height, player, action) in [(1, "white", "e4"), (51, "black", "e5")] {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(action),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+    // white has 100 + 10 blocks from the move at 51
+    assert_eq!(
+      turn_info(&deps, 101, Some("black")).unwrap(),
+      TurnInfoSummary {
+        blocks_remaining: Some(60),
+        is_player_turn: Some(false),
+        player_to_move: "white".to_string(),
+        timeout_block: Some(161),
+        turn_color: CwChessColor::White,
+      }
+    );
+    let info = turn_info(&deps, 200, None).unwrap();
+    assert_eq!(info.blocks_remaining, Some(0));
+    assert_eq!(info.is_player_turn, None);
+
+    // finished games have no turn
+    execute(
+      deps.as_mut(),
+      block_env(102),
+      mock_info("white", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    assert!(turn_info(&deps, 102, None).is_err());
+  }
+
+  #[test]
+  fn test_incoming_outgoing_challenges() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    for (creator, opponent) in [("alice", Some("bob")), ("carol", Some("alice")), ("alice", None)] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(creator, &[]),
+        create_challenge(NewChallenge {
+          opponent: opponent.map(|opponent| opponent.to_string()),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+    }
+    let challenge_ids = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, msg| {
+      from_binary::<Vec<Challenge>>(&query(deps.as_ref(), mock_env(), msg).unwrap())
+        .unwrap()
+        .iter()
+        .map(|challenge| challenge.challenge_id)
+        .collect::<Vec<_>>()
+    };
+
+    let incoming = |player: &str| QueryMsg::GetIncomingChallenges {
+      after: None,
+      limit: None,
+      player: player.to_string(),
+    };
+    let outgoing = |player: &str| QueryMsg::GetOutgoingChallenges {
+      after: None,
+      limit: None,
+      player: player.to_string(),
+    };
+    assert_eq!(challenge_ids(&deps, incoming("alice")), vec![2]);
+    assert_eq!(challenge_ids(&deps, outgoing("alice")), vec![1, 3]);
+    assert_eq!(challenge_ids(&deps, incoming("bob")), vec![1]);
+    assert_eq!(challenge_ids(&deps, outgoing("bob")), Vec::<u64>::new());
+    assert_eq!(challenge_ids(&deps, outgoing("carol")), vec![2]);
+
+    // paging
+    assert_eq!(
+      challenge_ids(
+        &deps,
+        QueryMsg::GetOutgoingChallenges {
+          after: Some(1),
+          limit: Some(1),
+          player: "alice".to_string(),
+        }
+      ),
+      vec![3]
+    );
+  }
+
+  #[test]
+  fn test_rating_bounds() {
+    let mut deps = mock_dependencies();
+
+    // floor can't be above the ceiling
+    let response = instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        rating_ceiling: Some(900),
+        rating_floor: Some(950),
+        ..InstantiateMsg::default()
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidRatingBounds { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        rating_ceiling: Some(1050),
+        rating_floor: Some(950),
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap();
+    let rating = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      from_binary::<PlayerStatsSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetPlayerStats {
+            player: player.to_string(),
+            time_control: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .rating
+    };
+
+    // loser resigns game after game
+    for game_id in 1..=20 {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("winner", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("loser", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: game_id },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("loser", &[]),
+        ExecuteMsg::Resign { confirm: false, game_id },
+      )
+      .unwrap();
+      assert!(rating(&deps, "loser") >= 950);
+      assert!(rating(&deps, "winner") <= 1050);
+    }
+    assert_eq!(rating(&deps, "loser"), 950);
+    assert_eq!(rating(&deps, "winner"), 1050);
+  }
+
+  #[test]
+  fn test_validate_fen() {
+    let deps = mock_dependencies();
+    let validate = |fen: &str| {
+      from_binary::<FenValidation>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::ValidateFen { fen: fen.to_string() }).unwrap(),
+      )
+      .unwrap()
+    };
+
+    assert_eq!(
+      validate("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"),
+      FenValidation { error: None, valid: true }
+    );
+    // structurally broken
+    let validation = validate("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    assert!(!validation.valid);
+    assert_eq!(validation.error, Some("placement must have eight ranks".to_string()));
+    // well formed, but black has no king
+    let validation = validate("8/8/8/8/8/8/8/K7 w - - 0 1");
+    assert!(!validation.valid);
+    assert_eq!(validation.error, Some("black must have exactly one king".to_string()));
+  }
+
+  #[test]
+  fn test_move_time_stats() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(0),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let average_move_blocks = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      from_binary::<PlayerStatsSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetPlayerStats {
+            player: player.to_string(),
+            time_control: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .average_move_blocks
+    };
+
+    // the first move has nothing to time it from
+    let moves = [(10, "white", "e4"), (15, "black", "e5"), (35, "white", "Nf3"), (36, "black", "Nc6")];
+    for (height, player, action) in moves {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(action),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+      if height == 10 {
+        assert_eq!(average_move_blocks(&deps, "white"), None);
+      }
+    }
+    // white: 20 blocks for Nf3, black: 5 + 1 blocks
+    assert_eq!(average_move_blocks(&deps, "white"), Some(20));
+    assert_eq!(average_move_blocks(&deps, "black"), Some(3));
+
+    // takeback requests aren't moves
+    execute(
+      deps.as_mut(),
+      block_env(100),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::RequestTakeback,
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    assert_eq!(average_move_blocks(&deps, "white"), Some(20));
+  }
+
+  #[test]
+  fn test_batch_turn() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let get_game = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<CwChessGame>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+      )
+      .unwrap()
+    };
+
+    for actions in [vec![], vec![CwChessAction::RequestTakeback; 21]] {
+      let response = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        ExecuteMsg::BatchTurn { actions, game_id: 1 },
+      );
+      match response.unwrap_err() {
+        ContractError::InvalidBatchSize { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+
+    // second move is out of turn, so nothing is applied
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::BatchTurn {
+        actions: vec![CwChessAction::from("e4"), CwChessAction::from("d4")],
+        game_id: 1,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::NotYourTurn { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    assert!(get_game(&deps).moves.is_empty());
+
+    // a move and a takeback request, both allowed for the sender
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::BatchTurn {
+        actions: vec![CwChessAction::from("e4"), CwChessAction::RequestTakeback],
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    assert_eq!(response.attributes[2].value, "2");
+    let game = get_game(&deps);
+    assert_eq!(game.moves.len(), 1);
+    assert_eq!(game.pending_takeback, Some(CwChessColor::White));
+
+    // illegal moves fail the batch
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::BatchTurn {
+        actions: vec![CwChessAction::from("e4")],
+        game_id: 1,
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidMove { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(get_game(&deps).moves.len(), 1);
+  }
+
+  #[test]
+  fn test_get_latest_move() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let latest_move = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<LatestMoveSummary>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetLatestMove { game_id: 1 }).unwrap(),
+      )
+      .unwrap()
+    };
+
+    assert_eq!(
+      latest_move(&deps),
+      LatestMoveSummary {
+        block: None,
+        fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+        san: None,
+        turn_color: Some(CwChessColor::White),
+      }
+    );
+
+    for (height, player, action) in [(10, "white", "e4"), (12, "black", "Nf6")] {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(action),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+    let summary = latest_move(&deps);
+    assert_eq!(summary.block, Some(12));
+    assert_eq!(summary.san, Some("Nf6".to_string()));
+    assert_eq!(summary.turn_color, Some(CwChessColor::White));
+    assert!(summary.fen.starts_with("rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w"));
+  }
+
+  #[test]
+  fn test_starting_rating() {
+    let mut deps = mock_dependencies();
+
+    // new players have to start within the rating bounds
+    let response = instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        rating_floor: Some(1600),
+        starting_rating: Some(1500),
+        ..InstantiateMsg::default()
+      },
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidRatingBounds { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        starting_rating: Some(1500),
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings { time_control: None }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(ratings.len(), 2);
+    assert!(ratings.iter().all(|rating| rating.rating == 1500));
+    // players without a game yet
+    let stats = from_binary::<PlayerStatsSummary>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPlayerStats {
+          player: "newcomer".to_string(),
+          time_control: None,
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(stats.rating, 1500);
+
+    // a decisive game moves ratings from 1500
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings { time_control: None }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(ratings.iter().map(|rating| rating.rating).sum::<u64>(), 3000);
+    assert!(ratings.iter().any(|rating| rating.rating > 1500));
+  }
+
+  #[test]
+  fn test_get_rating() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let rating = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      from_binary::<RatingSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetRating {
+            player: player.to_string(),
+            time_control: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+
+    // unknown players have the starting rating
+    assert_eq!(
+      rating(&deps, "white"),
+      RatingSummary {
+        player: "white".to_string(),
+        provisional: true,
+        rating: 1000,
+        time_control: TimeControl::Correspondence,
+      }
+    );
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+
+    // matches the full ratings list
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings { time_control: None }).unwrap(),
+    )
+    .unwrap();
+    for player in ["white", "black"] {
+      let summary = rating(&deps, player);
+      assert!(ratings.contains(&summary));
+      assert_ne!(summary.rating, 1000);
+    }
+  }
+
+  #[test]
+  fn test_decay_inactive_rating() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        decay_after_blocks: Some(100),
+        decay_amount: Some(10),
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    let rating = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      from_binary::<RatingSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetRating {
+            player: player.to_string(),
+            time_control: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .rating
+    };
+    let decay = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, height, player: &str| {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info("anyone", &[]),
+        ExecuteMsg::DecayInactiveRating {
+          player: player.to_string(),
+        },
+      )
+    };
+    let white = rating(&deps, "white");
+    let black = rating(&deps, "black");
+    assert!(white > 1010 && black < 990);
+
+    // active players and players without games don't decay
+    for (height, player) in [(50, "white"), (109, "black"), (500, "newcomer")] {
+      match decay(&mut deps, height, player).unwrap_err() {
+        ContractError::PlayerNotInactive { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+    assert_eq!(rating(&deps, "white"), white);
+    assert_eq!(rating(&deps, "black"), black);
+
+    // both move toward the starting rating
+    decay(&mut deps, 110, "white").unwrap();
+    decay(&mut deps, 110, "black").unwrap();
+    assert_eq!(rating(&deps, "white"), white - 10);
+    assert_eq!(rating(&deps, "black"), black + 10);
+
+    // once per decay_after_blocks
+    match decay(&mut deps, 150, "white").unwrap_err() {
+      ContractError::PlayerNotInactive { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // never past the starting rating
+    for height in [210, 310, 410] {
+      decay(&mut deps, height, "white").unwrap();
+    }
+    assert_eq!(rating(&deps, "white"), 1000);
+
+    // a decay amount too large to add is an error, not a wrapped rating
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::UpdateConfig {
+        abandon_threshold: None,
+        auto_queen_promotion: None,
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: Some(u64::MAX),
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: None,
+        k_factor: None,
+        max_block_limit: None,
+        max_open_challenges: None,
+        max_pairwise_challenges: None,
+        provisional_games: None,
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    )
+    .unwrap();
+    match decay(&mut deps, 510, "black").unwrap_err() {
+      ContractError::RatingOverflow { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(rating(&deps, "black"), black + 10);
+  }
+
+  #[test]
+  fn test_get_games_by_result() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // Qf7 stalemates in game 1, game 2 is resigned and game 3 goes on
+    for (challenge_id, start_fen) in [(1, Some("7k/8/6Q1/8/8/8/8/K7 w - - 0 1")), (2, None), (3, None)] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          start_fen: start_fen.map(|fen| fen.to_string()),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    }
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("Qf7"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 2 },
+    )
+    .unwrap();
+
+    let game_ids = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, result| {
+      from_binary::<Vec<GameSummary>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetGamesByResult {
+            after: None,
+            limit: None,
+            result,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .iter()
+      .map(|game| game.game_id)
+      .collect::<Vec<_>>()
+    };
+    assert_eq!(game_ids(&deps, CwChessGameOver::Stalemate), vec![1]);
+    assert_eq!(game_ids(&deps, CwChessGameOver::WhiteResigns), vec![2]);
+    assert_eq!(game_ids(&deps, CwChessGameOver::WhiteCheckmates), Vec::<u64>::new());
+  }
+
+  #[test]
+  fn test_match() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let challenge = |num_games| create_challenge(NewChallenge {
+      num_games: Some(num_games),
+      play_as: Some(CwChessColor::White),
+      ..NewChallenge::default()
+    });
+    for num_games in [0, 11] {
+      match execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), challenge(num_games)).unwrap_err() {
+        ContractError::InvalidNumGames { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), challenge(3)).unwrap();
+    let response = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("bob", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    assert!(response.attributes.iter().any(|attr| attr.key == "match_id" && attr.value == "1"));
+
+    let get_match = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<MatchSummary>(&query(deps.as_ref(), mock_env(), QueryMsg::GetMatch { match_id: 1 }).unwrap())
+        .unwrap()
+    };
+    // colors alternate
+    let summary = get_match(&deps);
+    let colors: Vec<_> = summary
+      .games
+      .iter()
+      .map(|game| (game.game_id, game.player1.as_str(), game.player2.as_str()))
+      .collect();
+    assert_eq!(colors, vec![(1, "alice", "bob"), (2, "bob", "alice"), (3, "alice", "bob")]);
+    assert!(!summary.finished);
+
+    let resign = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player, game_id| {
+      execute(deps.as_mut(), mock_env(), mock_info(player, &[]), ExecuteMsg::Resign { confirm: false, game_id }).unwrap()
+    };
+    resign(&mut deps, "bob", 1);
+    resign(&mut deps, "bob", 2);
+    let summary = get_match(&deps);
+    assert_eq!((summary.player1_points, summary.player2_points), (4, 0));
+    assert_eq!(summary.games[0].status, Some(CwChessGameOver::BlackResigns));
+    assert_eq!(summary.games[1].status, Some(CwChessGameOver::WhiteResigns));
+    assert_eq!((summary.finished, summary.winner), (false, None));
+
+    let response = resign(&mut deps, "alice", 3);
+    assert!(response.events.iter().any(|event| event.ty == "match_ended"));
+    let summary = get_match(&deps);
+    assert_eq!((summary.player1_points, summary.player2_points), (4, 2));
+    assert_eq!((summary.finished, summary.winner), (true, Some("alice".to_string())));
+
+    // every game is rated
+    let stats = from_binary::<PlayerStatsSummary>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPlayerStats {
+          player: "bob".to_string(),
+          time_control: None,
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!((stats.games_played, stats.wins, stats.losses), (3, 1, 2));
+  }
+
+  #[test]
+  fn test_self_play() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let challenge = |allow_self_play, opponent: Option<&str>| create_challenge(NewChallenge {
+      allow_self_play,
+      opponent: opponent.map(|opponent| opponent.to_string()),
+      ..NewChallenge::default()
+    });
+    // rejected without the flag, mock storage keeps the id the failed create used
+    match execute(deps.as_mut(), mock_env(), mock_info("tester", &[]), challenge(false, Some("tester"))).unwrap_err() {
+      ContractError::CannotPlaySelf { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    execute(deps.as_mut(), mock_env(), mock_info("tester", &[]), challenge(false, None)).unwrap();
+    match execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("tester", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 2 },
+    )
+    .unwrap_err()
+    {
+      ContractError::CannotPlaySelf { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    execute(deps.as_mut(), mock_env(), mock_info("tester", &[]), challenge(true, Some("tester"))).unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("tester", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 3 },
+    )
+    .unwrap();
+    // the same address moves for both colors, still one move per turn
+    for (height, san) in [(1, "e4"), (2, "e5"), (3, "Nf3")] {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info("tester", &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(san),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+    let game: CwChessGame =
+      from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap()).unwrap();
+    assert_eq!((game.player1.as_str(), game.player2.as_str()), ("tester", "tester"));
+    assert_eq!(game.turn_color(), Some(CwChessColor::Black));
+
+    // and the game is unrated
+    execute(
+      deps.as_mut(),
+      block_env(4),
+      mock_info("tester", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    let rating: RatingSummary = from_binary(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetRating {
+          player: "tester".to_string(),
+          time_control: None,
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(rating.rating, 1000);
+  }
+
+  #[test]
+  fn test_turn_check_attribute() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // Qh5 and Qxg6 check along the opened diagonal, the other moves are quiet
+    let moves = [
+      ("white", "e4", "false"),
+      ("black", "f6", "false"),
+      ("white", "Qh5", "true"),
+      ("black", "g6", "false"),
+      ("white", "Qxg6", "true"),
+      ("black", "hxg6", "false"),
+    ];
+    for (height, (player, san, check)) in moves.iter().enumerate() {
+      let response = execute(
+        deps.as_mut(),
+        block_env(height as u64 + 1),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(*san),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+      let attribute = response.attributes.iter().find(|attr| attr.key == "check").unwrap();
+      assert_eq!(attribute.value, *check, "{}", san);
+    }
+  }
+
+  #[test]
+  fn test_open_challenge_count() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        abandon_threshold: Some(10),
+        auto_queen_promotion: None,
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap();
+    let create = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, opponent: Option<&str>| {
+      execute(
+        deps.as_mut(),
+        block_env(1),
+        mock_info("creator", &[]),
+        create_challenge(NewChallenge {
+          opponent: opponent.map(|opponent| opponent.to_string()),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+    };
+    let count = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<u64>(&query(deps.as_ref(), mock_env(), QueryMsg::GetOpenChallengeCount {}).unwrap()).unwrap()
+    };
+    assert_eq!(count(&deps), 0);
+
+    // directed challenges aren't open
+    create(&mut deps, None);
+    create(&mut deps, Some("friend"));
+    create(&mut deps, None);
+    create(&mut deps, None);
+    assert_eq!(count(&deps), 3);
+
+    execute(
+      deps.as_mut(),
+      block_env(2),
+      mock_info("player", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(count(&deps), 2);
+    execute(
+      deps.as_mut(),
+      block_env(2),
+      mock_info("friend", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 2 },
+    )
+    .unwrap();
+    assert_eq!(count(&deps), 2);
+    execute(
+      deps.as_mut(),
+      block_env(2),
+      mock_info("creator", &[]),
+      ExecuteMsg::CancelChallenge { challenge_id: 3 },
+    )
+    .unwrap();
+    assert_eq!(count(&deps), 1);
+    execute(
+      deps.as_mut(),
+      block_env(11),
+      mock_info("anyone", &[]),
+      ExecuteMsg::ExpireChallenge { challenge_id: 4 },
+    )
+    .unwrap();
+    assert_eq!(count(&deps), 0);
+  }
+
+  #[test]
+  fn test_resign_confirmation() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        require_resign_confirmation: Some(true),
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // neither an unconfirmed resign nor a resign action ends the game
+    for msg in [
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+      ExecuteMsg::Turn {
+        action: CwChessAction::Resign {},
+        game_id: 1,
+      },
+      ExecuteMsg::BatchTurn {
+        actions: vec![CwChessAction::Resign {}],
+        game_id: 1,
+      },
+    ] {
+      match execute(deps.as_mut(), mock_env(), mock_info("white", &[]), msg).unwrap_err() {
+        ContractError::ResignNotConfirmed { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+    let game: CwChessGame =
+      from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap()).unwrap();
+    assert_eq!(game.status, None);
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Resign { confirm: true, game_id: 1 },
+    )
+    .unwrap();
+    let game: CwChessGame =
+      from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap()).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteResigns));
+  }
+
+  #[test]
+  fn test_annotate_move() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    for (height, (player, san)) in [("white", "e4"), ("black", "e5")].iter().enumerate() {
+      execute(
+        deps.as_mut(),
+        block_env(height as u64 + 1),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(*san),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+
+    let annotate = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player, move_number, text: &str| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::AnnotateMove {
+          game_id: 1,
+          move_number,
+          text: text.to_string(),
+        },
+      )
+    };
+    annotate(&mut deps, "white", 1, "best by test").unwrap();
+    annotate(&mut deps, "black", 2, "symmetrical").unwrap();
+    // a later annotation replaces the earlier one
+    annotate(&mut deps, "black", 2, "open game").unwrap();
+
+    match annotate(&mut deps, "spectator", 1, "nice").unwrap_err() {
+      ContractError::NotYourGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    for move_number in [0, 3] {
+      match annotate(&mut deps, "white", move_number, "not played").unwrap_err() {
+        ContractError::MoveNotFound { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+    for text in ["".to_string(), "x".repeat(281)] {
+      match annotate(&mut deps, "white", 1, &text).unwrap_err() {
+        ContractError::InvalidAnnotation { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+
+    let annotations: Vec<AnnotationSummary> =
+      from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetAnnotations { game_id: 1 }).unwrap()).unwrap();
+    assert_eq!(
+      annotations,
+      vec![
+        AnnotationSummary {
+          move_number: 1,
+          text: "best by test".to_string(),
+        },
+        AnnotationSummary {
+          move_number: 2,
+          text: "open game".to_string(),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn test_game_seed() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // seeds 21 and 24, so both color assignments come up
+    for (challenge_id, height) in [(1, 20), (2, 22)] {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info("challenger", &[]),
+        create_challenge(NewChallenge {
+          chess960: true,
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info("acceptor", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    }
+
+    // the recorded seed reproduces the colors and the starting position
+    let mut white = vec![];
+    for game_id in [1, 2] {
+      let game: CwChessGame =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id }).unwrap()).unwrap();
+      let seed = game.seed.unwrap();
+      assert_eq!(
+        CwChessGame::get_player_order(Addr::unchecked("challenger"), Addr::unchecked("acceptor"), None, seed),
+        (game.player1.clone(), game.player2.clone())
+      );
+      assert_eq!(game.start_fen, Some(chess960_fen(seed)));
+      white.push(game.player1.to_string());
+    }
+    assert_eq!(white, vec!["acceptor", "challenger"]);
+  }
+
+  #[test]
+  fn test_simulate_move() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        block_limit: Some(10),
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    for (height, (player, san)) in [("white", "f3"), ("black", "e5"), ("white", "g4")].iter().enumerate() {
+      execute(
+        deps.as_mut(),
+        block_env(height as u64 + 1),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(*san),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+
+    let simulate = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, san: &str| {
+      from_binary::<SimulatedMove>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::SimulateMove {
+            game_id: 1,
+            move_str: san.to_string(),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    let game = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<CwChessGame>(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap())
+        .unwrap()
+    };
+    let before = game(&deps);
+
+    // a quiet move
+    let simulated = simulate(&deps, "Nc6");
+    assert!(simulated.valid && !simulated.gives_check);
+    assert!(simulated.resulting_fen.unwrap().starts_with("r1bqkbnr/pppp1ppp/2n5/4p3/6P1/5P2/PPPPP2P/RNBQKBNR w"));
+    assert_eq!(simulated.is_game_over, None);
+
+    // mate, even though the clock ran out at the current height
+    let simulated = simulate(&deps, "Qh4");
+    assert!(simulated.valid && simulated.gives_check);
+    assert_eq!(simulated.is_game_over, Some(CwChessGameOver::BlackCheckmates));
+
+    // illegal moves aren't errors
+    assert_eq!(
+      simulate(&deps, "Ke6"),
+      SimulatedMove {
+        gives_check: false,
+        is_game_over: None,
+        resulting_fen: None,
+        valid: false,
+      }
+    );
+
+    // nothing was saved
+    assert_eq!(game(&deps), before);
+  }
+
+  #[test]
+  fn test_matchmaking_queue() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let enter = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player, block_limit, rating_range| {
+      execute(
+        deps.as_mut(),
+        block_env(1),
+        mock_info(player, &[]),
+        ExecuteMsg::EnterQueue {
+          block_limit,
+          rating_range,
+        },
+      )
+    };
+    let queue = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<Vec<QueueEntry>>(&query(deps.as_ref(), mock_env(), QueryMsg::GetQueue {}).unwrap())
+        .unwrap()
+        .iter()
+        .map(|entry| entry.player.to_string())
+        .collect::<Vec<_>>()
+    };
+
+    // a different time control doesn't match
+    enter(&mut deps, "alice", None, 100).unwrap();
+    enter(&mut deps, "bob", Some(100), 100).unwrap();
+    match enter(&mut deps, "alice", None, 100).unwrap_err() {
+      ContractError::AlreadyInQueue { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(queue(&deps), vec!["alice", "bob"]);
+
+    // carol is paired with alice and both leave the queue
+    let response = enter(&mut deps, "carol", None, 50).unwrap();
+    assert!(response.attributes.iter().any(|attr| attr.key == "game_id" && attr.value == "1"));
+    assert_eq!(queue(&deps), vec!["bob"]);
+    let game: CwChessGame =
+      from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap()).unwrap();
+    let mut players = vec![game.player1.to_string(), game.player2.to_string()];
+    players.sort();
+    assert_eq!(players, vec!["alice", "carol"]);
+    assert_eq!(game.block_limit, None);
+
+    execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), ExecuteMsg::LeaveQueue {}).unwrap();
+    assert_eq!(queue(&deps), Vec::<String>::new());
+    match execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), ExecuteMsg::LeaveQueue {}).unwrap_err() {
+      ContractError::NotInQueue { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // the next pairing gives white to whoever had black, whoever waited
+    for players in [["alice", "carol"], ["carol", "alice"]] {
+      let mut deps = mock_dependencies();
+      instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), InstantiateMsg::default()).unwrap();
+      for _ in 0..2 {
+        for player in players {
+          enter(&mut deps, player, None, 100).unwrap();
+        }
+        assert_eq!(queue(&deps), Vec::<String>::new());
+      }
+      let load = |game_id| -> CwChessGame {
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id }).unwrap()).unwrap()
+      };
+      let (first, second) = (load(1), load(2));
+      assert_eq!((second.player1, second.player2), (first.player2, first.player1));
+    }
+  }
+
+  #[test]
+  fn test_move_timing() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let turn = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, height, player, san| {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(san),
+          game_id: 1,
+        },
+      )
+    };
+    turn(&mut deps, 14, "white", "e4").unwrap();
+    turn(&mut deps, 20, "black", "e5").unwrap();
+    // a replayed earlier height is rejected, the same block is fine
+    match turn(&mut deps, 19, "white", "Nf3").unwrap_err() {
+      ContractError::InvalidMoveTiming { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    turn(&mut deps, 20, "white", "Nf3").unwrap();
+    turn(&mut deps, 21, "black", "Nc6").unwrap();
+
+    let metrics: GameMetrics =
+      from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetGameMetrics { game_id: 1 }).unwrap()).unwrap();
+    assert_eq!(
+      metrics,
+      GameMetrics {
+        longest_move_blocks: 6,
+        moves: 4,
+        same_block_moves: 1,
+        total_blocks: 11,
+      }
+    );
+  }
+
+  #[test]
+  fn test_premove() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let turn = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, height, player, san| {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(san),
+          game_id: 1,
+        },
+      )
+    };
+    let premove = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player, san: &str| {
+      execute(
+        deps.as_mut(),
+        block_env(11),
+        mock_info(player, &[]),
+        ExecuteMsg::SetPremove {
+          game_id: 1,
+          move_str: san.to_string(),
+        },
+      )
+    };
+    let get_game = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<CwChessGame>(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap())
+        .unwrap()
+    };
+
+    // only the waiting player can premove
+    match premove(&mut deps, "white", "e4").unwrap_err() {
+      ContractError::CannotPremoveOwnTurn { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    match premove(&mut deps, "stranger", "e5").unwrap_err() {
+      ContractError::NotYourGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // black's premove is played right after white moves
+    premove(&mut deps, "black", "e5").unwrap();
+    let res = turn(&mut deps, 12, "white", "e4").unwrap();
+    let attr = res.attributes.iter().find(|attr| attr.key == "premove").unwrap();
+    assert_eq!(&attr.value, "played");
+    let game = get_game(&deps);
+    assert_eq!(
+      game.moves,
+      vec![
+        (12, CwChessAction::MakeMove("e4".to_string())),
+        (12, CwChessAction::MakeMove("e5".to_string())),
+      ]
+    );
+    assert_eq!(game.premove, None);
+    assert_eq!(game.turn_color(), Some(CwChessColor::White));
+
+    // exd5 needs a pawn on d5, so it's dropped after Nc6
+    turn(&mut deps, 13, "white", "Nf3").unwrap();
+    premove(&mut deps, "white", "exd5").unwrap();
+    let res = turn(&mut deps, 14, "black", "Nc6").unwrap();
+    let attr = res.attributes.iter().find(|attr| attr.key == "premove").unwrap();
+    assert_eq!(&attr.value, "dropped");
+    let game = get_game(&deps);
+    assert_eq!(game.moves.len(), 4);
+    assert_eq!(game.premove, None);
+    assert_eq!(game.turn_color(), Some(CwChessColor::White));
+
+    // no premove queued, no attribute
+    let res = turn(&mut deps, 15, "white", "Bc4").unwrap();
+    assert!(res.attributes.iter().all(|attr| attr.key != "premove"));
+
+    // a batch turn plays the premove too
+    premove(&mut deps, "white", "d3").unwrap();
+    let res = execute(
+      deps.as_mut(),
+      block_env(16),
+      mock_info("black", &[]),
+      ExecuteMsg::BatchTurn {
+        actions: vec![CwChessAction::from("Nf6")],
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    let attr = res.attributes.iter().find(|attr| attr.key == "premove").unwrap();
+    assert_eq!(&attr.value, "played");
+    let game = get_game(&deps);
+    assert_eq!(game.moves[5..], [(16, CwChessAction::from("Nf6")), (16, CwChessAction::from("d3"))]);
+    assert_eq!(game.premove, None);
+    // and nothing is left over to play on a later turn
+    turn(&mut deps, 17, "black", "Be7").unwrap();
+    turn(&mut deps, 18, "white", "O-O").unwrap();
+    assert_eq!(get_game(&deps).moves.len(), 9);
+  }
+
+  #[test]
+  fn test_completion_hook() {
+    let setup = |completion_hook: Option<String>| {
+      let mut deps = mock_dependencies();
+      instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+          completion_hook,
+          ..InstantiateMsg::default()
+        },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+      )
+      .unwrap();
+      deps
+    };
+    let turn = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player, san| {
+      execute(
+        deps.as_mut(),
+        block_env(11),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(san),
+          game_id: 1,
+        },
+      )
+      .unwrap()
+    };
+
+    // fool's mate, only the last move finishes the game
+    let mut deps = setup(Some("hook".to_string()));
+    assert!(turn(&mut deps, "white", "f3").messages.is_empty());
+    turn(&mut deps, "black", "e5");
+    turn(&mut deps, "white", "g4");
+    let res = turn(&mut deps, "black", "Qh4");
+    assert_eq!(
+      res.messages,
+      vec![SubMsg::reply_on_error(
+        WasmMsg::Execute {
+          contract_addr: "hook".to_string(),
+          msg: to_binary(&CompletionHookMsg::GameCompleted {
+            game_id: 1,
+            player1: Addr::unchecked("white"),
+            player2: Addr::unchecked("black"),
+            status: CwChessGameOver::BlackCheckmates,
+            winner: Some(Addr::unchecked("black")),
+          })
+          .unwrap(),
+          funds: vec![],
+        },
+        1,
+      )]
+    );
+
+    // a failing hook is only reported
+    let res = reply(
+      deps.as_mut(),
+      mock_env(),
+      Reply {
+        id: 1,
+        result: SubMsgResult::Err("hook failed".to_string()),
+      },
+    )
+    .unwrap();
+    assert_eq!(res.attributes[0].value, "completion_hook_failed");
+
+    // without a hook nothing is sent
+    let mut deps = setup(None);
+    turn(&mut deps, "white", "f3");
+    turn(&mut deps, "black", "e5");
+    turn(&mut deps, "white", "g4");
+    let res = turn(&mut deps, "black", "Qh4");
+    assert!(res.messages.is_empty());
+    assert_eq!(res.events[0].ty, "game_ended");
+  }
+
+  #[test]
+  fn test_games_started_between() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let start_game = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, challenge_id, height| {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    };
+    start_game(&mut deps, 1, 10);
+    start_game(&mut deps, 2, 20);
+    start_game(&mut deps, 3, 20);
+    start_game(&mut deps, 4, 30);
+    let started_between = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, from_block, to_block, limit| {
+      from_binary::<Vec<GameSummary>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetGamesStartedBetween {
+            from_block,
+            limit,
+            to_block,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .iter()
+      .map(|game| (game.block_start, game.game_id))
+      .collect::<Vec<_>>()
+    };
+
+    // both ends are included
+    assert_eq!(started_between(&deps, 20, 30, None), vec![(20, 2), (20, 3), (30, 4)]);
+    assert_eq!(started_between(&deps, 10, 10, None), vec![(10, 1)]);
+    assert_eq!(started_between(&deps, 11, 29, None), vec![(20, 2), (20, 3)]);
+    assert_eq!(started_between(&deps, 0, 100, Some(2)), vec![(10, 1), (20, 2)]);
+    assert_eq!(started_between(&deps, 31, 100, None), vec![]);
+    assert_eq!(started_between(&deps, 30, 10, None), vec![]);
+  }
+
+  #[test]
+  fn test_draw_offer_cooldown() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        draw_offer_cooldown_blocks: Some(10),
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let turn = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, height, player, action| {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn { action, game_id: 1 },
+      )
+    };
+    let offer_draw = |san: &str| CwChessAction::OfferDraw(san.to_string());
+
+    // black declines by playing on, white can't offer again right away
+    turn(&mut deps, 11, "white", offer_draw("e4")).unwrap();
+    turn(&mut deps, 12, "black", CwChessAction::from("e5")).unwrap();
+    match turn(&mut deps, 13, "white", offer_draw("Nf3")).unwrap_err() {
+      ContractError::DrawOfferTooSoon { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    turn(&mut deps, 13, "white", CwChessAction::from("Nf3")).unwrap();
+
+    // each color has its own cooldown
+    turn(&mut deps, 14, "black", offer_draw("Nc6")).unwrap();
+    match turn(&mut deps, 20, "white", offer_draw("Bc4")).unwrap_err() {
+      ContractError::DrawOfferTooSoon { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    turn(&mut deps, 21, "white", offer_draw("Bc4")).unwrap();
+
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.last_draw_offers, (Some(21), Some(14)));
+  }
+
+  #[test]
+  fn test_contract_stats() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let stats = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<ContractStats>(&query(deps.as_ref(), mock_env(), QueryMsg::GetContractStats {}).unwrap())
+        .unwrap()
+    };
+    let expected = |finished_games, games, open_challenges, players| ContractStats {
+      finished_games,
+      games,
+      open_challenges,
+      players,
+    };
+    assert_eq!(stats(&deps), expected(0, 0, 0, 0));
+
+    let create_challenge = |opponent: Option<&str>| create_challenge(NewChallenge {
+      opponent: opponent.map(|opponent| opponent.to_string()),
+      play_as: Some(CwChessColor::White),
+      ..NewChallenge::default()
+    });
+    execute(deps.as_mut(), block_env(10), mock_info("white", &[]), create_challenge(None)).unwrap();
+    assert_eq!(stats(&deps), expected(0, 0, 1, 1));
+    // a directed challenge isn't open, but its opponent gets a rating
+    execute(deps.as_mut(), block_env(10), mock_info("white", &[]), create_challenge(Some("carol"))).unwrap();
+    assert_eq!(stats(&deps), expected(0, 0, 1, 2));
+
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(stats(&deps), expected(0, 1, 0, 3));
+
+    // fool's mate
+    for (player, san) in [("white", "f3"), ("black", "e5"), ("white", "g4"), ("black", "Qh4")] {
+      execute(
+        deps.as_mut(),
+        block_env(11),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(san),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+    assert_eq!(stats(&deps), expected(1, 1, 0, 3));
+
+    // a returning player isn't counted again
+    execute(
+      deps.as_mut(),
+      block_env(12),
+      mock_info("carol", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 2 },
+    )
+    .unwrap();
+    assert_eq!(stats(&deps), expected(1, 2, 0, 3));
+  }
+
+  #[test]
+  fn test_abort_game() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let start_game = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, challenge_id| {
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    };
+    let turn = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, game_id, player, san| {
+      execute(
+        deps.as_mut(),
+        block_env(11),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(san),
+          game_id,
+        },
+      )
+      .unwrap();
+    };
+    let abort = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, game_id, player| {
+      execute(
+        deps.as_mut(),
+        block_env(12),
+        mock_info(player, &[]),
+        ExecuteMsg::AbortGame { game_id },
+      )
+    };
+    let status = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, game_id| {
+      from_binary::<CwChessGame>(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id }).unwrap())
+        .unwrap()
+        .status
+    };
+    start_game(&mut deps, 1);
+    start_game(&mut deps, 2);
+
+    // only players can abort
+    match abort(&mut deps, 1, "stranger").unwrap_err() {
+      ContractError::NotYourGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // one move played, either player can still abort
+    turn(&mut deps, 1, "white", "e4");
+    let res = abort(&mut deps, 1, "white").unwrap();
+    assert_eq!(res.attributes[0].value, "abort_game");
+    assert_eq!(status(&deps, 1), Some(CwChessGameOver::Voided));
+
+    // once both have moved the game is underway
+    turn(&mut deps, 2, "white", "e4");
+    turn(&mut deps, 2, "black", "e5");
+    match abort(&mut deps, 2, "black").unwrap_err() {
+      ContractError::CannotAbortStartedGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(status(&deps, 2), None);
+
+    // no rating change from the aborted game
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings { time_control: None }).unwrap(),
+    )
+    .unwrap();
+    assert!(ratings.iter().all(|r| r.rating == 1000));
+  }
+
+  #[test]
+  fn test_get_turn_color() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let turn_color = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<Option<CwChessColor>>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetTurnColor { game_id: 1 }).unwrap(),
+      )
+      .unwrap()
+    };
+    assert_eq!(turn_color(&deps), Some(CwChessColor::White));
+
+    // the turn status attribute names the color to move
+    let res = execute(
+      deps.as_mut(),
+      block_env(11),
+      mock_info("white", &[]),
+      ExecuteMsg::Turn {
+        action: CwChessAction::from("e4"),
+        game_id: 1,
+      },
+    )
+    .unwrap();
+    assert_eq!(res.attributes[2].value, "Black");
+    assert_eq!(turn_color(&deps), Some(CwChessColor::Black));
+
+    execute(
+      deps.as_mut(),
+      block_env(12),
+      mock_info("black", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(turn_color(&deps), None);
+  }
+
+  #[test]
+  fn test_rating_history() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let play_game = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, game_id, loser, block_limit| {
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          block_limit,
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: game_id },
+      )
+      .unwrap();
+      let msg = match loser {
+        Some(_) => ExecuteMsg::Resign { confirm: false, game_id },
+        None => ExecuteMsg::AbortGame { game_id },
+      };
+      execute(deps.as_mut(), block_env(11), mock_info(loser.unwrap_or("white"), &[]), msg).unwrap();
+    };
+    let history_in = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, after, limit, time_control| {
+      from_binary::<Vec<RatingHistoryPoint>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetRatingHistory {
+            after,
+            limit,
+            player: "white".to_string(),
+            time_control,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    let history = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, after, limit| history_in(deps, after, limit, None);
+    play_game(&mut deps, 1, Some("black"), None);
+    play_game(&mut deps, 2, Some("white"), None);
+    // an aborted game is unrated and leaves no entry
+    play_game(&mut deps, 3, None, None);
+    play_game(&mut deps, 4, Some("black"), None);
+
+    let points = history(&deps, None, None);
+    assert_eq!(points.iter().map(|point| point.game_id).collect::<Vec<_>>(), vec![1, 2, 4]);
+    assert!(points[0].rating > 1000);
+    assert!(points[1].rating < points[0].rating);
+    assert!(points[2].rating > points[1].rating);
+    let rating = from_binary::<RatingSummary>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRating { player: "white".to_string(), time_control: None }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(points[2].rating, rating.rating);
+
+    // paginated by game_id
+    assert_eq!(history(&deps, Some(1), Some(1)), vec![points[1].clone()]);
+    assert_eq!(history(&deps, Some(4), None), vec![]);
+
+    // each time control keeps its own history
+    play_game(&mut deps, 5, Some("white"), Some(50));
+    assert_eq!(history(&deps, None, None), points);
+    let blitz = history_in(&deps, None, None, Some(TimeControl::Blitz));
+    assert_eq!(blitz.iter().map(|point| point.game_id).collect::<Vec<_>>(), vec![5]);
+    assert!(blitz[0].rating < 1000);
+    let stats_rating = |time_control| {
+      from_binary::<PlayerStatsSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetPlayerStats {
+            player: "white".to_string(),
+            time_control,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .rating
+    };
+    assert_eq!(stats_rating(None), points[2].rating);
+    assert_eq!(stats_rating(Some(TimeControl::Blitz)), blitz[0].rating);
+
+    // history from before time controls moves to each game's time control
+    let entries = RATING_HISTORY
+      .range(&deps.storage, None, None, cosmwasm_std::Order::Ascending)
+      .collect::<Result<Vec<_>, _>>()
+      .unwrap();
+    for ((player, time_control, game_id), rating) in entries {
+      RATING_HISTORY.remove(&mut deps.storage, (player.clone(), time_control.as_str(), game_id));
+      LEGACY_RATING_HISTORY.save(&mut deps.storage, (player, game_id), &rating).unwrap();
+    }
+    assert_eq!(history(&deps, None, None), vec![]);
+    set_contract_version(&mut deps.storage, "cosmos-chess", "0.0.0").unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(history(&deps, None, None), points);
+    assert_eq!(history_in(&deps, None, None, Some(TimeControl::Blitz)), blitz);
+    assert_eq!(LEGACY_RATING_HISTORY.keys_raw(&deps.storage, None, None, cosmwasm_std::Order::Ascending).count(), 0);
+  }
+
+  #[test]
+  fn test_odds_game() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let create_challenge = |chess960| create_challenge(NewChallenge {
+      chess960,
+      odds: Some(OddsType::Knight),
+      play_as: Some(CwChessColor::Black),
+      ..NewChallenge::default()
+    });
+
+    // odds need the standard start
+    match execute(deps.as_mut(), block_env(10), mock_info("strong", &[]), create_challenge(true)).unwrap_err() {
+      ContractError::InvalidOdds { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // the creator plays black without the b8 knight
+    execute(deps.as_mut(), block_env(10), mock_info("strong", &[]), create_challenge(false)).unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("weak", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let game = from_binary::<CwChessGame>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(game.player2, "strong");
+    assert_eq!(game.odds, Some(OddsType::Knight));
+    assert_eq!(game.fen, odds_fen(&OddsType::Knight, &CwChessColor::Black));
+
+    // the result is recorded, the ratings stay put
+    execute(
+      deps.as_mut(),
+      block_env(11),
+      mock_info("strong", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings { time_control: None }).unwrap(),
+    )
+    .unwrap();
+    assert!(ratings.iter().all(|r| r.rating == 1000));
+    let stats = from_binary::<PlayerStatsSummary>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetPlayerStats { player: "weak".to_string(), time_control: None }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(stats.wins, 1);
+  }
+
+  #[test]
+  fn test_get_game_detailed() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        block_limit: Some(100),
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let detailed = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, height| {
+      from_binary::<GameDetailed>(&query(deps.as_ref(), block_env(height), QueryMsg::GetGameDetailed { game_id: 1 }).unwrap())
+        .unwrap()
+    };
+    let details = detailed(&deps, 1000);
+    assert_eq!(details.move_count, 0);
+    assert_eq!(details.timeout_block, None);
+    assert!(!details.timeout_pending);
+    assert_eq!(details.turn_color, Some(CwChessColor::White));
+
+    for (height, player, san) in [(11, "white", "e4"), (12, "black", "f6"), (15, "white", "Qh5")] {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(san),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+
+    // black is in check, with 99 blocks left on the clock
+    let details = detailed(&deps, 16);
+    assert_eq!(details.game.moves.len(), 3);
+    assert!(details.in_check);
+    assert_eq!(details.move_count, 3);
+    assert_eq!(details.result, None);
+    assert_eq!(details.timeout_block, Some(15 + 99));
+    assert!(!details.timeout_pending);
+    assert_eq!(details.turn_color, Some(CwChessColor::Black));
+
+    // black can still move in the timeout block, the timeout is pending right after it
+    assert!(!detailed(&deps, 15 + 99).timeout_pending);
+    let details = detailed(&deps, 15 + 100);
+    assert!(details.timeout_pending);
+    assert_eq!(details.result, None);
+
+    execute(
+      deps.as_mut(),
+      block_env(16),
+      mock_info("black", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    let details = detailed(&deps, 1000);
+    assert!(!details.in_check);
+    assert_eq!(details.result, Some(CwChessGameOver::BlackResigns));
+    assert_eq!(details.timeout_block, None);
+    assert!(!details.timeout_pending);
+    assert_eq!(details.turn_color, None);
+  }
+
+  #[test]
+  fn test_rating_update_without_entry() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // black's entry is gone, the game still finishes and rates both players
+    RATINGS.remove(&mut deps.storage, (Addr::unchecked("black"), TimeControl::Correspondence.as_str()));
+    execute(
+      deps.as_mut(),
+      block_env(11),
+      mock_info("white", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    let white = RATINGS.load(&deps.storage, (Addr::unchecked("white"), TimeControl::Correspondence.as_str())).unwrap();
+    let black = RATINGS.load(&deps.storage, (Addr::unchecked("black"), TimeControl::Correspondence.as_str())).unwrap();
+    assert!(white < 1000);
+    assert!(black > 1000);
+  }
+
+  #[test]
+  fn test_challenge_dedup_key() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let create = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, creator, dedup_key: &str| {
+      let res = execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info(creator, &[]),
+        create_challenge(NewChallenge {
+          dedup_key: Some(dedup_key.to_string()),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      let attr = res.attributes.iter().find(|attr| attr.key == "challenge_id").unwrap();
+      attr.value.parse::<u64>().unwrap()
+    };
+    let open_challenges = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<u64>(&query(deps.as_ref(), mock_env(), QueryMsg::GetOpenChallengeCount {}).unwrap()).unwrap()
+    };
+
+    // a retry returns the same challenge
+    assert_eq!(create(&mut deps, "alice", "retry"), 1);
+    assert_eq!(create(&mut deps, "alice", "retry"), 1);
+    assert_eq!(open_challenges(&deps), 1);
+    // keys are per creator
+    assert_eq!(create(&mut deps, "alice", "other"), 2);
+    assert_eq!(create(&mut deps, "bob", "retry"), 3);
+    assert_eq!(open_challenges(&deps), 3);
+
+    // cancelling or accepting frees the key
+    execute(
+      deps.as_mut(),
+      block_env(11),
+      mock_info("alice", &[]),
+      ExecuteMsg::CancelChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(create(&mut deps, "alice", "retry"), 4);
+    execute(
+      deps.as_mut(),
+      block_env(11),
+      mock_info("bob", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 4 },
+    )
+    .unwrap();
+    assert_eq!(create(&mut deps, "alice", "retry"), 5);
+
+    // once a challenge expires its key goes to the next one, and taking down
+    // the expired one leaves the new mapping alone
+    let create_expiring = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, height| {
+      let res = execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info("carol", &[]),
+        create_challenge(NewChallenge {
+          dedup_key: Some("k".to_string()),
+          expires_in_blocks: Some(5),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      let attr = res.attributes.iter().find(|attr| attr.key == "challenge_id").unwrap();
+      attr.value.parse::<u64>().unwrap()
+    };
+    let expired = create_expiring(&mut deps, 10);
+    let current = create_expiring(&mut deps, 20);
+    assert_eq!(current, expired + 1);
+    execute(
+      deps.as_mut(),
+      block_env(21),
+      mock_info("carol", &[]),
+      ExecuteMsg::CancelChallenge { challenge_id: expired },
+    )
+    .unwrap();
+    assert_eq!(create_expiring(&mut deps, 22), current);
+  }
+
+  #[test]
+  fn test_get_games_player_pagination() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let start_game = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>,
+                      challenge_id,
+                      creator: &str,
+                      opponent: &str,
+                      play_as| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(creator, &[]),
+        create_challenge(NewChallenge {
+          allow_self_play: creator == opponent,
+          play_as: Some(play_as),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(opponent, &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    };
+
+    // 64 games alternating colors, with a self-play game every tenth game
+    let mut expected = vec![];
+    for challenge_id in 1..=64u64 {
+      let play_as = if challenge_id % 2 == 0 {
+        CwChessColor::White
+      } else {
+        CwChessColor::Black
+      };
+      let opponent = if challenge_id % 10 == 0 { "one" } else { "two" };
+      start_game(&mut deps, challenge_id, "one", opponent, play_as);
+      expected.push(challenge_id);
+    }
+    // games between other players are not included
+    start_game(&mut deps, 65, "two", "three", CwChessColor::White);
+
+    let mut after = None;
+    let mut game_ids = vec![];
+    loop {
+      let games = from_binary::<Vec<GameSummary>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetGames {
+            after,
+            game_over: Some(true),
+            limit: Some(25),
+            player: Some("one".to_string()),
+            status_filter: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap();
+      if games.is_empty() {
+        break;
+      }
+      assert!(games.len() <= 25);
+      after = games.last().map(|game| game.game_id);
+      game_ids.extend(games.iter().map(|game| game.game_id));
+    }
+    assert_eq!(game_ids, expected);
+  }
+
+  #[test]
+  fn test_get_challenges_block_limit_range() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // challenges 1 to 4, the last one without a clock
+    for block_limit in [Some(10), Some(100), Some(1000), None] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("creator", &[]),
+        create_challenge(NewChallenge {
+          block_limit,
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+    }
+    let challenge_ids = |deps: &cosmwasm_std::OwnedDeps<_, _, _>,
+                         min_block_limit,
+                         max_block_limit,
+                         player: Option<&str>| {
+      from_binary::<Vec<Challenge>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetChallenges {
+            after: None,
+            hide_expired: None,
+            limit: Some(1),
+            max_block_limit,
+            min_block_limit,
+            player: player.map(String::from),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .iter()
+      .map(|challenge| challenge.challenge_id)
+      .collect::<Vec<_>>()
+    };
+
+    // the limit counts matching challenges, not scanned ones
+    assert_eq!(challenge_ids(&deps, None, None, None), vec![1]);
+    assert_eq!(challenge_ids(&deps, Some(100), None, None), vec![2]);
+    assert_eq!(challenge_ids(&deps, Some(101), Some(1000), None), vec![3]);
+    assert_eq!(challenge_ids(&deps, Some(1001), None, None), Vec::<u64>::new());
+    assert_eq!(challenge_ids(&deps, None, Some(9), Some("creator")), Vec::<u64>::new());
+    assert_eq!(challenge_ids(&deps, Some(50), Some(500), Some("creator")), vec![2]);
+  }
+
+  #[test]
+  fn test_get_opponent() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let get_opponent = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetOpponent {
+          game_id: 1,
+          player: player.to_string(),
+        },
+      )
+      .map(|binary| from_binary::<Addr>(&binary).unwrap())
+    };
+
+    assert_eq!(get_opponent(&deps, "white").unwrap(), Addr::unchecked("black"));
+    assert_eq!(get_opponent(&deps, "black").unwrap(), Addr::unchecked("white"));
+    assert!(get_opponent(&deps, "someone").is_err());
+  }
+
+  #[test]
+  fn test_valid_move_reasons() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let valid_move = |deps: &cosmwasm_std::OwnedDeps<_, _, _>,
+                      game_id,
+                      player: &str,
+                      move_str: &str| {
+      query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ValidMove {
+          game_id,
+          player: player.to_string(),
+          move_str: move_str.to_string(),
+        },
+      )
+      .map(|binary| from_binary::<MoveValidation>(&binary).unwrap())
+    };
+    let get_turn = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, game_id, player: &str| {
+      query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetTurn {
+          game_id,
+          player: player.to_string(),
+        },
+      )
+      .map(|binary| from_binary::<bool>(&binary).unwrap())
+    };
+    let illegal = |error: ContractError| MoveValidation {
+      legal: false,
+      reason: Some(error.to_string()),
+    };
+
+    assert_eq!(
+      valid_move(&deps, 1, "white", "e4").unwrap(),
+      MoveValidation { legal: true, reason: None }
+    );
+    assert_eq!(valid_move(&deps, 1, "white", "e5").unwrap(), illegal(ContractError::InvalidMove {}));
+    assert_eq!(valid_move(&deps, 1, "white", "Ke2").unwrap(), illegal(ContractError::InvalidMove {}));
+    assert_eq!(valid_move(&deps, 1, "black", "e5").unwrap(), illegal(ContractError::NotYourTurn {}));
+    assert_eq!(valid_move(&deps, 1, "someone", "e4").unwrap(), illegal(ContractError::NotYourGame {}));
+    assert!(get_turn(&deps, 1, "white").unwrap());
+    assert!(!get_turn(&deps, 1, "black").unwrap());
+
+    // a missing game is an error rather than an illegal move
+    assert!(valid_move(&deps, 2, "white", "e4").is_err());
+    assert!(get_turn(&deps, 2, "white").is_err());
+
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(valid_move(&deps, 1, "white", "e4").unwrap(), illegal(ContractError::GameAlreadyOver {}));
+    assert!(!get_turn(&deps, 1, "white").unwrap());
+  }
+
+  #[test]
+  fn test_time_control_ratings() {
+    let mut deps = mock_dependencies();
+
+    // thresholds have to grow with the time control
+    match instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        blitz_max_blocks: Some(20),
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap_err()
+    {
+      ContractError::InvalidTimeControls { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // white beats black at blitz, black beats white in correspondence
+    for (challenge_id, block_limit, loser) in [(1, Some(100), "black"), (2, None, "white")] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          block_limit,
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(loser, &[]),
+        ExecuteMsg::Resign {
+          confirm: false,
+          game_id: challenge_id,
+        },
+      )
+      .unwrap();
+    }
+    let rating = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str, time_control| {
+      from_binary::<RatingSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetRating {
+            player: player.to_string(),
+            time_control,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .rating
+    };
+    let ratings = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, time_control| {
+      from_binary::<Vec<RatingSummary>>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetRatings { time_control }).unwrap(),
+      )
+      .unwrap()
+      .iter()
+      .map(|summary| (summary.player.clone(), summary.rating, summary.time_control))
+      .collect::<Vec<_>>()
+    };
+
+    // each result only moved the ratings of its own time control
+    let blitz_white = rating(&deps, "white", Some(TimeControl::Blitz));
+    let blitz_black = rating(&deps, "black", Some(TimeControl::Blitz));
+    assert!(blitz_white > 1000 && blitz_black < 1000);
+    let correspondence_white = rating(&deps, "white", None);
+    let correspondence_black = rating(&deps, "black", Some(TimeControl::Correspondence));
+    assert!(correspondence_white < 1000 && correspondence_black > 1000);
+    assert_eq!(rating(&deps, "white", Some(TimeControl::Bullet)), 1000);
+    assert_eq!(
+      ratings(&deps, Some(TimeControl::Blitz)),
+      vec![
+        ("black".to_string(), blitz_black, TimeControl::Blitz),
+        ("white".to_string(), blitz_white, TimeControl::Blitz),
+      ]
+    );
+    assert_eq!(
+      ratings(&deps, None),
+      vec![
+        ("black".to_string(), correspondence_black, TimeControl::Correspondence),
+        ("white".to_string(), correspondence_white, TimeControl::Correspondence),
+      ]
+    );
+    assert_eq!(ratings(&deps, Some(TimeControl::Rapid)), vec![]);
+
+    // players rated in two time controls are still counted once
+    let stats = from_binary::<ContractStats>(&query(deps.as_ref(), mock_env(), QueryMsg::GetContractStats {}).unwrap()).unwrap();
+    assert_eq!(stats.players, 2);
+  }
+
+  #[test]
+  fn test_get_player_counts() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let counts = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      let counts = from_binary::<PlayerCounts>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetPlayerCounts {
+            player: player.to_string(),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap();
+      (counts.challenges, counts.games)
+    };
+    assert_eq!(counts(&deps, "one"), (0, 0));
+
+    // an open challenge and one directed at two
+    for opponent in [None, Some("two".to_string())] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("one", &[]),
+        create_challenge(NewChallenge {
+          opponent,
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+    }
+    assert_eq!(counts(&deps, "one"), (2, 0));
+    assert_eq!(counts(&deps, "two"), (0, 0));
+
+    // accepting starts a game for both players
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("two", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(counts(&deps, "one"), (1, 1));
+    assert_eq!(counts(&deps, "two"), (0, 1));
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("one", &[]),
+      ExecuteMsg::CancelChallenge { challenge_id: 2 },
+    )
+    .unwrap();
+    assert_eq!(counts(&deps, "one"), (0, 1));
+
+    // finished games still count, the rematch offer is a challenge
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("two", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 1 },
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("two", &[]),
+      ExecuteMsg::OfferRematch { game_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(counts(&deps, "one"), (0, 1));
+    assert_eq!(counts(&deps, "two"), (1, 1));
+
+    // migrate counts everything again without doubling it
+    set_contract_version(&mut deps.storage, "cosmos-chess", "0.0.0").unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(counts(&deps, "one"), (0, 1));
+    assert_eq!(counts(&deps, "two"), (1, 1));
+  }
+
+  #[test]
+  fn test_max_pairwise_challenges() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        max_pairwise_challenges: Some(2),
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap();
+    let challenge = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, opponent: Option<&str>| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("one", &[]),
+        create_challenge(NewChallenge {
+          opponent: opponent.map(String::from),
+          ..NewChallenge::default()
+        }),
+      )
+    };
+
+    // up to the cap for the same opponent
+    challenge(&mut deps, Some("two")).unwrap();
+    challenge(&mut deps, Some("two")).unwrap();
+    match challenge(&mut deps, Some("two")).unwrap_err() {
+      ContractError::TooManyPairwiseChallenges { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // other opponents and open challenges aren't affected
+    challenge(&mut deps, Some("three")).unwrap();
+    challenge(&mut deps, None).unwrap();
+
+    // a canceled challenge makes room again
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("one", &[]),
+      ExecuteMsg::CancelChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    challenge(&mut deps, Some("two")).unwrap();
+  }
+
+  #[test]
+  fn test_timeout_updates_ratings() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // two blitz games where white stops moving after 1. e4 e5
+    for game_id in [1, 2] {
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          block_limit: Some(100),
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: game_id },
+      )
+      .unwrap();
+      for (height, player, san) in [(11, "white", "e4"), (12, "black", "e5")] {
+        execute(
+          deps.as_mut(),
+          block_env(height),
+          mock_info(player, &[]),
+          ExecuteMsg::Turn {
+            action: CwChessAction::from(san),
+            game_id,
+          },
+        )
+        .unwrap();
+      }
+    }
+    let rating = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      from_binary::<RatingSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetRating {
+            player: player.to_string(),
+            time_control: Some(TimeControl::Blitz),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .rating
+    };
+    let claim = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, height, player: &str, game_id| {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::ClaimTimeoutWin { game_id },
+      )
+    };
+
+    // declaring the timeout rates the game like any other finish
+    execute(
+      deps.as_mut(),
+      block_env(113),
+      mock_info("anyone", &[]),
+      ExecuteMsg::DeclareTimeout { game_id: 1 },
+    )
+    .unwrap();
+    let (white, black) = (rating(&deps, "white"), rating(&deps, "black"));
+    assert!(white < 1000 && black > 1000);
+
+    // only black, who still has time, can claim the win
+    match claim(&mut deps, 112, "black", 2).unwrap_err() {
+      ContractError::GameNotTimedOut { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    match claim(&mut deps, 113, "white", 2).unwrap_err() {
+      ContractError::CannotClaimOwnTurn { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    match claim(&mut deps, 113, "anyone", 2).unwrap_err() {
+      ContractError::NotYourGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    let response = claim(&mut deps, 113, "black", 2).unwrap();
+    assert!(response.events.iter().any(|event| event.ty == "game_ended"));
+    let game = from_binary::<CwChessGame>(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 2 }).unwrap()).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteTimeout));
+    assert!(rating(&deps, "white") < white);
+    assert!(rating(&deps, "black") > black);
+  }
+
+  #[test]
+  fn test_move_wrong_side() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        block_limit: Some(100),
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let turn = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, height, player: &str, san: &str| {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(san),
+          game_id: 1,
+        },
+      )
+    };
+
+    // black is a player, but it's white's move
+    match turn(&mut deps, 11, "black", "e5").unwrap_err() {
+      ContractError::NotYourTurn { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    // someone outside the game gets a different error
+    match turn(&mut deps, 11, "someone", "e4").unwrap_err() {
+      ContractError::NotYourGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    turn(&mut deps, 11, "white", "e4").unwrap();
+    match turn(&mut deps, 12, "white", "d4").unwrap_err() {
+      ContractError::NotYourTurn { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    // an outsider can't end the game by moving after the timeout either
+    match turn(&mut deps, 500, "someone", "e5").unwrap_err() {
+      ContractError::NotYourGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    let game = from_binary::<CwChessGame>(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap()).unwrap();
+    assert_eq!(game.status, None);
+  }
+
+  #[test]
+  fn test_agree_result() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    for game_id in [1, 2] {
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: game_id },
+      )
+      .unwrap();
+    }
+    let agree = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player: &str, game_id, result| {
+      execute(
+        deps.as_mut(),
+        block_env(20),
+        mock_info(player, &[]),
+        ExecuteMsg::AgreeResult { game_id, result },
+      )
+    };
+    let game = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, game_id| {
+      from_binary::<CwChessGame>(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id }).unwrap()).unwrap()
+    };
+
+    // only players can submit, and not a void result
+    match agree(&mut deps, "someone", 1, CwChessGameOver::DrawAccepted).unwrap_err() {
+      ContractError::NotYourGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    match agree(&mut deps, "white", 1, CwChessGameOver::Voided).unwrap_err() {
+      ContractError::InvalidAgreedResult { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // the first submission waits on the opponent
+    let response = agree(&mut deps, "white", 1, CwChessGameOver::WhiteResigns).unwrap();
+    assert!(response.attributes.iter().any(|attr| attr.key == "agreed" && attr.value == "false"));
+    assert_eq!(
+      game(&deps, 1).pending_result,
+      Some((CwChessColor::White, CwChessGameOver::WhiteResigns))
+    );
+    assert_eq!(game(&deps, 1).status, None);
+
+    // a different result from the opponent is rejected and the submission is kept
+    match agree(&mut deps, "black", 1, CwChessGameOver::DrawAccepted).unwrap_err() {
+      ContractError::ResultMismatch { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(
+      game(&deps, 1).pending_result,
+      Some((CwChessColor::White, CwChessGameOver::WhiteResigns))
+    );
+
+    // the matching result ends and rates the game
+    let response = agree(&mut deps, "black", 1, CwChessGameOver::WhiteResigns).unwrap();
+    assert!(response.events.iter().any(|event| event.ty == "game_ended"));
+    let ended = game(&deps, 1);
+    assert_eq!(ended.status, Some(CwChessGameOver::WhiteResigns));
+    assert_eq!(ended.pending_result, None);
+    let rating = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      from_binary::<RatingSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetRating {
+            player: player.to_string(),
+            time_control: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .rating
+    };
+    assert!(rating(&deps, "white") < 1000 && rating(&deps, "black") > 1000);
+    match agree(&mut deps, "white", 1, CwChessGameOver::WhiteResigns).unwrap_err() {
+      ContractError::GameAlreadyOver { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // a player can change their own submission before the opponent agrees
+    agree(&mut deps, "black", 2, CwChessGameOver::BlackResigns).unwrap();
+    agree(&mut deps, "black", 2, CwChessGameOver::DrawAccepted).unwrap();
+    agree(&mut deps, "white", 2, CwChessGameOver::DrawAccepted).unwrap();
+    assert_eq!(game(&deps, 2).status, Some(CwChessGameOver::DrawAccepted));
+  }
+
+  #[test]
+  fn test_get_fen_at_move() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let fen_at = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, ply| {
+      query(deps.as_ref(), mock_env(), QueryMsg::GetFenAtMove { game_id: 1, ply })
+        .map(|bin| from_binary::<String>(&bin).unwrap())
+    };
+    let mut fens = vec![];
+    for (height, player, san) in [(11, "white", "d4"), (12, "black", "d5"), (13, "white", "c4")] {
+      execute(
+        deps.as_mut(),
+        block_env(height),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(san),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+      let game = from_binary::<CwChessGame>(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap()).unwrap();
+      fens.push(game.fen);
+    }
+
+    assert_eq!(fen_at(&deps, 0).unwrap(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    assert_eq!(fen_at(&deps, 1).unwrap(), fens[0]);
+    assert_eq!(fen_at(&deps, 3).unwrap(), fens[2]);
+    assert!(fen_at(&deps, 4).is_err());
+  }
+
+  #[test]
+  fn test_rate_draws() {
+    let mut deps = mock_dependencies();
+
+    // initialize with draws left unrated
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        rate_draws: Some(false),
+        treasury: None,
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap();
+    for game_id in [1, 2] {
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: game_id },
+      )
+      .unwrap();
+    }
+    let agree = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, game_id, result: CwChessGameOver| {
+      for player in ["white", "black"] {
+        execute(
+          deps.as_mut(),
+          block_env(20),
+          mock_info(player, &[]),
+          ExecuteMsg::AgreeResult {
+            game_id,
+            result: result.clone(),
+          },
+        )
+        .unwrap();
+      }
+    };
+    let rating = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      from_binary::<RatingSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetRating {
+            player: player.to_string(),
+            time_control: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .rating
+    };
+
+    // the draw is counted but leaves both ratings alone
+    agree(&mut deps, 1, CwChessGameOver::DrawAccepted);
+    assert_eq!((rating(&deps, "white"), rating(&deps, "black")), (1000, 1000));
+    let stats = from_binary::<PlayerStatsSummary>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPlayerStats {
+          player: "white".to_string(),
+          time_control: None,
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(stats.draws, 1);
+
+    // wins and losses are still rated
+    agree(&mut deps, 2, CwChessGameOver::BlackResigns);
+    assert!(rating(&deps, "white") > 1000 && rating(&deps, "black") < 1000);
+  }
+
+  #[test]
+  fn test_import_game() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let import = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, sender: &str, pgn: &str| {
+      execute(
+        deps.as_mut(),
+        block_env(50),
+        mock_info(sender, &[]),
+        ExecuteMsg::ImportGame {
+          pgn: pgn.to_string(),
+          player1: "white".to_string(),
+          player2: "black".to_string(),
+        },
+      )
+    };
+    let scholars_mate = "[White \"white\"]\n[Black \"black\"]\n\n1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n";
+
+    // only the owner can import
+    match import(&mut deps, "white", scholars_mate).unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    match import(&mut deps, "owner", "1. e4 e5 2. Ke3 0-1").unwrap_err() {
+      ContractError::InvalidMove { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    let response = import(&mut deps, "owner", scholars_mate).unwrap();
+    let game_id = response
+      .attributes
+      .iter()
+      .find(|attr| attr.key == "game_id")
+      .unwrap()
+      .value
+      .parse::<u64>()
+      .unwrap();
+    let game = from_binary::<CwChessGame>(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id }).unwrap()).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteCheckmates));
+    assert_eq!(game.player1, "white");
+    assert_eq!(game.block_start, 50);
+    assert_eq!(game.moves.len(), 7);
+    assert!(game.fen.starts_with("r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq -"));
+
+    // the game is listed as finished, but nobody's rating moved
+    let games = from_binary::<Vec<GameSummary>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetGames {
+          after: None,
+          game_over: Some(true),
+          limit: None,
+          player: Some("black".to_string()),
+          status_filter: None,
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(games.len(), 1);
+    let stats = from_binary::<ContractStats>(&query(deps.as_ref(), mock_env(), QueryMsg::GetContractStats {}).unwrap()).unwrap();
+    assert_eq!(stats.finished_games, 1);
+    let ratings = from_binary::<Vec<RatingSummary>>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetRatings { time_control: None },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert!(ratings.is_empty());
+  }
+
+  #[test]
+  fn test_get_timed_out_games() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // game 1 waits on black, game 2 on white and game 3 has no block_limit
+    for (game_id, block_limit, moves) in [(1, Some(100), 1), (2, Some(100), 2), (3, None, 1)] {
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          block_limit,
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: game_id },
+      )
+      .unwrap();
+      for (height, player, san) in [(11, "white", "e4"), (20, "black", "e5")].into_iter().take(moves) {
+        execute(
+          deps.as_mut(),
+          block_env(height),
+          mock_info(player, &[]),
+          ExecuteMsg::Turn {
+            action: CwChessAction::from(san),
+            game_id,
+          },
+        )
+        .unwrap();
+      }
+    }
+    let timed_out = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, height, after, limit| {
+      from_binary::<Vec<TimedOutGame>>(
+        &query(deps.as_ref(), block_env(height), QueryMsg::GetTimedOutGames { after, limit }).unwrap(),
+      )
+      .unwrap()
+      .into_iter()
+      .map(|timed_out| (timed_out.game.game_id, timed_out.timed_out_color))
+      .collect::<Vec<_>>()
+    };
+
+    // nobody is out of blocks at the deadline itself
+    assert_eq!(timed_out(&deps, 111, None, None), vec![]);
+    assert_eq!(timed_out(&deps, 112, None, None), vec![(1, CwChessColor::Black)]);
+    assert_eq!(
+      timed_out(&deps, 500, None, None),
+      vec![(1, CwChessColor::Black), (2, CwChessColor::White)]
+    );
+    assert_eq!(timed_out(&deps, 500, Some(1), None), vec![(2, CwChessColor::White)]);
+    assert_eq!(timed_out(&deps, 500, None, Some(1)), vec![(1, CwChessColor::Black)]);
+
+    // a declared timeout leaves the list
+    execute(
+      deps.as_mut(),
+      block_env(500),
+      mock_info("anyone", &[]),
+      ExecuteMsg::DeclareTimeout { game_id: 1 },
+    )
+    .unwrap();
+    assert_eq!(timed_out(&deps, 500, None, None), vec![(2, CwChessColor::White)]);
+  }
+
+  #[test]
+  fn test_game_stored_compact() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      block_env(10),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    for (i, (player, move_str)) in [("white", "e4"), ("black", "e5")].into_iter().enumerate() {
+      execute(
+        deps.as_mut(),
+        block_env(11 + i as u64),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn { action: CwChessAction::from(move_str), game_id: 1 },
+      )
+      .unwrap();
+    }
+    let game = get_games_map().load(&deps.storage, 1).unwrap();
+    let fen = game.fen.as_str();
+
+    // the saved game holds the board, not the fen
+    let saved = deps
+      .storage
+      .range(None, None, cosmwasm_std::Order::Ascending)
+      .map(|(_, value)| String::from_utf8(value).unwrap())
+      .find(|value| value.contains("\"game_id\":1,"))
+      .unwrap();
+    assert!(saved.contains("\"board\""));
+    assert!(!saved.contains(fen));
+
+    // queries answer with the fen as before
+    for msg in [
+      QueryMsg::GetGame { game_id: 1 },
+      QueryMsg::GetGameDetailed { game_id: 1 },
+      QueryMsg::GetGamesByIds { ids: vec![1] },
+    ] {
+      let json = String::from_utf8(query(deps.as_ref(), block_env(13), msg).unwrap().to_vec()).unwrap();
+      assert!(json.contains(&format!("\"fen\":\"{}\"", fen)), "{}", json);
+      assert!(!json.contains("\"board\""), "{}", json);
+    }
+    let queried: CwChessGame = from_binary(&query(deps.as_ref(), block_env(13), QueryMsg::GetGame { game_id: 1 }).unwrap()).unwrap();
+    assert_eq!(queried, game);
+  }
+
+  #[test]
+  fn test_challenge_fee() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg {
+        challenge_fee: Some(Coin::new(100, "ucosm")),
+        treasury: Some("treasury".to_string()),
+        ..InstantiateMsg::default()
+      },
+    )
+    .unwrap();
+    let create = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, funds: &[Coin]| {
+      execute(
+        deps.as_mut(),
+        block_env(10),
+        mock_info("white", funds),
+        create_challenge(NewChallenge::default()),
+      )
+    };
+    let send = |to_address: &str, amount: Vec<Coin>| {
+      SubMsg::new(BankMsg::Send {
+        to_address: to_address.to_string(),
+        amount,
+      })
+    };
+    let update_config = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, challenge_fee, refund_challenge_fee| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+          abandon_threshold: None,
+          auto_queen_promotion: None,
+          blitz_max_blocks: None,
+          bullet_max_blocks: None,
+          challenge_fee,
+          completion_hook: None,
+          decay_after_blocks: None,
+          decay_amount: None,
+          draw_offer_cooldown_blocks: None,
+          high_rating_k_factor: None,
+          high_rating_threshold: None,
+          inactivity_blocks: None,
+          k_factor: None,
+          max_block_limit: None,
+          max_open_challenges: None,
+          max_pairwise_challenges: None,
+          provisional_games: None,
+          provisional_k_factor: None,
+          rapid_max_blocks: None,
+          rate_draws: None,
+          rating_ceiling: None,
+          rating_floor: None,
+          refund_challenge_fee,
+          require_resign_confirmation: None,
+          starting_rating: None,
+          treasury: None,
+        },
+      )
+      .unwrap();
+    };
+
+    // too little, or in the wrong denom
+    for funds in [vec![], coins(99, "ucosm"), coins(100, "uatom")] {
+      match create(&mut deps, &funds).unwrap_err() {
+        ContractError::ChallengeFeeNotPaid { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+
+    // the fee goes to the treasury, anything over it back to the creator
+    let res = create(&mut deps, &coins(100, "ucosm")).unwrap();
+    assert_eq!(res.messages, vec![send("treasury", coins(100, "ucosm"))]);
+    let res = create(&mut deps, &[Coin::new(150, "ucosm"), Coin::new(7, "uatom")]).unwrap();
+    assert_eq!(
+      res.messages,
+      vec![
+        send("white", vec![Coin::new(7, "uatom"), Coin::new(50, "ucosm")]),
+        send("treasury", coins(100, "ucosm")),
+      ]
+    );
+    // a paid fee isn't refunded
+    let res = execute(
+      deps.as_mut(),
+      block_env(11),
+      mock_info("white", &[]),
+      ExecuteMsg::CancelChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+
+    // with refunds the fee is held until the challenge is accepted
+    update_config(&mut deps, None, Some(true));
+    let res = create(&mut deps, &coins(100, "ucosm")).unwrap();
+    assert!(res.messages.is_empty());
+    let challenge = from_binary::<Challenge>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetChallenge { challenge_id: 3 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(challenge.fee, Some(Coin::new(100, "ucosm")));
+    let res = execute(
+      deps.as_mut(),
+      block_env(11),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 3 },
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![send("treasury", coins(100, "ucosm"))]);
+
+    // and returned when cancelled or expired
+    create(&mut deps, &coins(100, "ucosm")).unwrap();
+    let res = execute(
+      deps.as_mut(),
+      block_env(11),
+      mock_info("white", &[]),
+      ExecuteMsg::CancelChallenge { challenge_id: 4 },
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![send("white", coins(100, "ucosm"))]);
+    create(&mut deps, &coins(100, "ucosm")).unwrap();
+    let res = execute(
+      deps.as_mut(),
+      block_env(10 + 100_800),
+      mock_info("anyone", &[]),
+      ExecuteMsg::ExpireChallenge { challenge_id: 5 },
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![send("white", coins(100, "ucosm"))]);
+    // a held fee is the one paid, even if the config changes before it's released
+    create(&mut deps, &coins(100, "ucosm")).unwrap();
+    update_config(&mut deps, Some(Coin::new(0, "ucosm")), None);
+    let res = execute(
+      deps.as_mut(),
+      block_env(11),
+      mock_info("white", &[]),
+      ExecuteMsg::CancelChallenge { challenge_id: 6 },
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![send("white", coins(100, "ucosm"))]);
+
+    // a zero fee makes challenges free again
+    let res = create(&mut deps, &[]).unwrap();
+    assert!(res.messages.is_empty());
+    let challenge = from_binary::<Challenge>(
+      &query(deps.as_ref(), mock_env(), QueryMsg::GetChallenge { challenge_id: 7 }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(challenge.fee, None);
+  }
+
+  #[test]
+  fn test_get_player_turn_games() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // white against black twice and other once, then black as white against other
+    for (challenge_id, creator, opponent) in [(1, "white", "black"), (2, "white", "black"), (3, "white", "other"), (4, "black", "other")] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(creator, &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(opponent, &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    }
+    let turn_games = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      from_binary::<Vec<u64>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetPlayerTurnGames {
+            player: player.to_string(),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    let turn = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player: &str, game_id, action| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(action),
+          game_id,
+        },
+      )
+      .unwrap();
+    };
+    // white moves first everywhere
+    assert_eq!(turn_games(&deps, "white"), vec![1, 2, 3]);
+    assert_eq!(turn_games(&deps, "black"), vec![4]);
+    assert_eq!(turn_games(&deps, "other"), Vec::<u64>::new());
+
+    // moving hands the game to the opponent
+    turn(&mut deps, "white", 2, "e4");
+    turn(&mut deps, "white", 3, "d4");
+    assert_eq!(turn_games(&deps, "white"), vec![1]);
+    assert_eq!(turn_games(&deps, "black"), vec![2, 4]);
+    assert_eq!(turn_games(&deps, "other"), vec![3]);
+
+    // finished games drop out for both players
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::Resign { confirm: false, game_id: 2 },
+    )
+    .unwrap();
+    assert_eq!(turn_games(&deps, "white"), vec![1]);
+    assert_eq!(turn_games(&deps, "black"), vec![4]);
+    assert_eq!(turn_games(&deps, "nobody"), Vec::<u64>::new());
+  }
+
+  #[test]
+  fn test_pending_draw_offer() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let turn = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player: &str, action| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn { action, game_id: 1 },
+      )
+      .unwrap();
+    };
+    // (GetGame json, GetGameDetailed, GetActiveGames summary)
+    let pending = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      let game = query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: 1 }).unwrap();
+      let detailed = from_binary::<GameDetailed>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetGameDetailed { game_id: 1 }).unwrap(),
+      )
+      .unwrap();
+      let summaries = from_binary::<Vec<GameSummary>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetActiveGames {
+            player: "white".to_string(),
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap();
+      (
+        String::from_utf8(game.to_vec()).unwrap(),
+        detailed.pending_draw_offer,
+        summaries[0].pending_draw_offer.clone(),
+      )
+    };
+
+    // no offer yet
+    turn(&mut deps, "white", CwChessAction::from("e4"));
+    let (json, detailed, summary) = pending(&deps);
+    assert!(!json.contains("pending_draw_offer"));
+    assert_eq!((detailed, summary), (None, None));
+
+    // black moves with an offer, waiting on white
+    turn(&mut deps, "black", CwChessAction::OfferDraw("e5".to_string()));
+    let (json, detailed, summary) = pending(&deps);
+    assert!(json.contains("\"pending_draw_offer\":\"black\""), "{}", json);
+    assert_eq!((detailed, summary), (Some(CwChessColor::Black), Some(CwChessColor::Black)));
+    // but the saved game doesn't keep it
+    let saved = deps
+      .storage
+      .range(None, None, cosmwasm_std::Order::Ascending)
+      .map(|(_, value)| String::from_utf8(value).unwrap())
+      .find(|value| value.contains("\"game_id\":1,"))
+      .unwrap();
+    assert!(!saved.contains("pending_draw_offer"));
+
+    // moving on declines it
+    turn(&mut deps, "white", CwChessAction::from("Nf3"));
+    let (json, detailed, summary) = pending(&deps);
+    assert!(!json.contains("pending_draw_offer"));
+    assert_eq!((detailed, summary), (None, None));
+  }
+
+  #[test]
+  fn test_migrate_draw_claims() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("white", &[]),
+      create_challenge(NewChallenge {
+        play_as: Some(CwChessColor::White),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+
+    // a claimed draw as saved before DrawDeclared was renamed
+    let mut game = get_games_map().load(&deps.storage, 1).unwrap();
+    game.status = Some(CwChessGameOver::DrawClaimed);
+    get_games_map().save(&mut deps.storage, 1, &game).unwrap();
+    let (key, value) = deps
+      .storage
+      .range(None, None, cosmwasm_std::Order::Ascending)
+      .find(|(_, value)| value.starts_with(b"{\"block_limit\""))
+      .unwrap();
+    let legacy = String::from_utf8(value).unwrap().replace("draw_claimed", "draw_declared");
+    deps.storage.set(&key, legacy.as_bytes());
+    let pk = 1u64.joined_key();
+    get_games_map().idx.status.remove(&mut deps.storage, &pk, &game).unwrap();
+    let legacy_index: MultiIndex<String, CwChessGame, u64> =
+      MultiIndex::new(|_| "DrawDeclared".to_string(), "games", "games__status");
+    legacy_index.save(&mut deps.storage, &pk, &game).unwrap();
+    // it already loads under the new name
+    assert_eq!(get_games_map().load(&deps.storage, 1).unwrap(), game);
+    let draws = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<Vec<GameSummary>>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetGamesByResult {
+            after: None,
+            limit: None,
+            result: CwChessGameOver::DrawClaimed,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+      .len()
+    };
+    assert_eq!(draws(&deps), 0);
+
+    set_contract_version(&mut deps.storage, "cosmos-chess", "0.0.0").unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(draws(&deps), 1);
+    assert!(String::from_utf8(deps.storage.get(&key).unwrap()).unwrap().contains("draw_claimed"));
+    let old_keys = deps
+      .storage
+      .range(None, None, cosmwasm_std::Order::Ascending)
+      .filter(|(key, _)| key.windows(12).any(|window| window == b"DrawDeclared"))
+      .count();
+    assert_eq!(old_keys, 0);
+  }
+
+  #[test]
+  fn test_get_games_status_filter() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // (white, black) of each game
+    let players = [("alice", "bob"), ("bob", "alice"), ("alice", "carol"), ("carol", "alice"), ("alice", "bob"), ("bob", "alice")];
+    for (challenge_id, (white, black)) in players.iter().enumerate() {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(white, &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(black, &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id: challenge_id as u64 + 1 },
+      )
+      .unwrap();
+    }
+    let turn = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player: &str, game_id, action| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn { action, game_id },
+      )
+      .unwrap();
+    };
+    let resign = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player: &str, game_id| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Resign { confirm: false, game_id },
+      )
+      .unwrap();
+    };
+    // alice beats bob as white, loses to bob as black, draws carol, and beats carol as black
+    resign(&mut deps, "bob", 1);
+    resign(&mut deps, "alice", 2);
+    turn(&mut deps, "alice", 3, CwChessAction::from("e4"));
+    turn(&mut deps, "carol", 3, CwChessAction::OfferDraw("e5".to_string()));
+    turn(&mut deps, "alice", 3, CwChessAction::AcceptDraw);
+    resign(&mut deps, "carol", 4);
+    // game 5 goes on, game 6 is called off
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("alice", &[]),
+      ExecuteMsg::AbortGame { game_id: 6 },
+    )
+    .unwrap();
+    let games = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: Option<&str>, status_filter| {
+      query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetGames {
+          after: None,
+          game_over: None,
+          limit: None,
+          player: player.map(|player| player.to_string()),
+          status_filter,
+        },
+      )
+      .map(|bin| {
+        from_binary::<Vec<GameSummary>>(&bin)
+          .unwrap()
+          .iter()
+          .map(|game| game.game_id)
+          .collect::<Vec<_>>()
+      })
+    };
+
+    assert_eq!(games(&deps, Some("alice"), Some(StatusFilter::Ongoing)).unwrap(), vec![5]);
+    assert_eq!(games(&deps, Some("alice"), Some(StatusFilter::Won)).unwrap(), vec![1, 4]);
+    assert_eq!(games(&deps, Some("alice"), Some(StatusFilter::Lost)).unwrap(), vec![2]);
+    assert_eq!(games(&deps, Some("alice"), Some(StatusFilter::Drawn)).unwrap(), vec![3]);
+    // the same games from the opponents' side
+    assert_eq!(games(&deps, Some("bob"), Some(StatusFilter::Won)).unwrap(), vec![2]);
+    assert_eq!(games(&deps, Some("bob"), Some(StatusFilter::Lost)).unwrap(), vec![1]);
+    assert_eq!(games(&deps, Some("carol"), Some(StatusFilter::Lost)).unwrap(), vec![4]);
+    assert_eq!(games(&deps, Some("carol"), Some(StatusFilter::Drawn)).unwrap(), vec![3]);
+    // without a filter game_over still decides
+    assert_eq!(games(&deps, Some("alice"), None).unwrap(), vec![5]);
+    // won and lost mean nothing without a player
+    assert!(games(&deps, None, Some(StatusFilter::Won)).is_err());
+  }
+
+  #[test]
+  fn test_get_config() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+      abandon_threshold: None,
+      auto_queen_promotion: None,
+      blitz_max_blocks: None,
+      bullet_max_blocks: None,
+      challenge_fee: None,
+      completion_hook: None,
+      decay_after_blocks: None,
+      decay_amount: None,
+      draw_offer_cooldown_blocks: None,
+      high_rating_k_factor: None,
+      high_rating_threshold: None,
+      inactivity_blocks: None,
+      k_factor: None,
+      max_block_limit: None,
+      max_open_challenges: None,
+      max_pairwise_challenges: None,
+      provisional_games: None,
+      provisional_k_factor: None,
+      rapid_max_blocks: None,
+      rate_draws: None,
+      rating_ceiling: None,
+      rating_floor: None,
+      refund_challenge_fee: None,
+      require_resign_confirmation: None,
+      starting_rating: None,
+      treasury: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let get_config = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+      from_binary::<Config>(&query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap()).unwrap()
+    };
+    let before = get_config(&deps);
+    assert_eq!(before, CONFIG.load(&deps.storage).unwrap());
+    assert_eq!(before.max_open_challenges, 10);
+    assert_eq!(before.completion_hook, None);
+
+    let update = |completion_hook: Option<&str>, inactivity_blocks: Option<u64>, starting_rating: Option<u64>| ExecuteMsg::UpdateConfig {
+      abandon_threshold: None,
+      auto_queen_promotion: None,
+      blitz_max_blocks: None,
+      bullet_max_blocks: None,
+      challenge_fee: None,
+      completion_hook: completion_hook.map(|hook| hook.to_string()),
+      decay_after_blocks: None,
+      decay_amount: None,
+      draw_offer_cooldown_blocks: None,
+      high_rating_k_factor: None,
+      high_rating_threshold: None,
+      inactivity_blocks,
+      k_factor: None,
+      max_block_limit: None,
+      max_open_challenges: None,
+      max_pairwise_challenges: None,
+      provisional_games: None,
+      provisional_k_factor: None,
+      rapid_max_blocks: None,
+      rate_draws: None,
+      rating_ceiling: None,
+      rating_floor: None,
+      refund_challenge_fee: None,
+      require_resign_confirmation: None,
+      starting_rating,
+      treasury: None,
+    };
+
+    // only the owner can change config
+    match execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), update(None, Some(5), None)).unwrap_err() {
+      ContractError::Unauthorized { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(get_config(&deps), before);
+
+    // fields left out keep their values
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update(None, Some(5), None)).unwrap();
+    let config = get_config(&deps);
+    assert_eq!(config, Config { inactivity_blocks: 5, ..before.clone() });
+
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update(Some("hook"), None, Some(1500))).unwrap();
+    assert_eq!(
+      get_config(&deps),
+      Config {
+        completion_hook: Some(Addr::unchecked("hook")),
+        inactivity_blocks: 5,
+        starting_rating: 1500,
+        ..before
+      }
+    );
+
+    // the starting rating still has to be within the rating bounds
+    match execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update(None, None, Some(50))).unwrap_err() {
+      ContractError::InvalidRatingBounds { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(get_config(&deps).starting_rating, 1500);
+  }
+
+  #[test]
+  fn test_stalemate_and_checkmate_outcomes() {
+    let mut deps = mock_dependencies();
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let play = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, challenge_id: u64, start_fen: &str, move_str: &str| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          start_fen: Some(start_fen.to_string()),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(move_str),
+          game_id: challenge_id,
+        },
+      )
+      .unwrap();
+      from_binary::<CwChessGame>(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id: challenge_id }).unwrap())
+        .unwrap()
+        .status
+    };
+    let stats = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, player: &str| {
+      from_binary::<PlayerStatsSummary>(
+        &query(
+          deps.as_ref(),
+          mock_env(),
+          QueryMsg::GetPlayerStats {
+            player: player.to_string(),
+            time_control: None,
+          },
+        )
+        .unwrap(),
+      )
+      .unwrap()
+    };
+    let starting_rating = CONFIG.load(&deps.storage).unwrap().starting_rating;
+
+    // a stalemate is a draw, which leaves equal ratings where they were
+    let status = play(&mut deps, 1, "7k/8/6Q1/8/8/8/8/K7 w - - 0 1", "Qf7");
+    assert_eq!(status, Some(CwChessGameOver::Stalemate));
+    for player in ["white", "black"] {
+      let stats = stats(&deps, player);
+      assert_eq!((stats.wins, stats.draws, stats.losses), (0, 1, 0));
+      assert_eq!(stats.rating, starting_rating);
+    }
+
+    // a back-rank mate is a win for the side that mates
+    let status = play(&mut deps, 2, "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "Ra8");
+    assert_eq!(status, Some(CwChessGameOver::WhiteCheckmates));
+    let white = stats(&deps, "white");
+    assert_eq!((white.wins, white.draws, white.losses), (1, 1, 0));
+    assert!(white.rating > starting_rating);
+    let black = stats(&deps, "black");
+    assert_eq!((black.wins, black.draws, black.losses), (0, 1, 1));
+    assert!(black.rating < starting_rating);
+  }
+
+  #[test]
+  fn test_get_game_ratings() {
+    let mut deps = mock_dependencies();
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    // creator plays white, returns the new game's id
+    let start_game = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, white: &str, black: &str, block_limit: Option<u64>| {
+      let response = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(white, &[]),
+        create_challenge(NewChallenge {
+          block_limit,
+          play_as: Some(CwChessColor::White),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      let challenge_id = response.attributes.iter().find(|attr| attr.key == "challenge_id").unwrap().value.parse().unwrap();
+      let response = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(black, &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+      response.attributes.iter().find(|attr| attr.key == "game_id").unwrap().value.parse::<u64>().unwrap()
+    };
+    let resign = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, player: &str, game_id: u64| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Resign { confirm: false, game_id },
+      )
+      .unwrap();
+    };
+    let game_ratings = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, game_id| {
+      query(deps.as_ref(), mock_env(), QueryMsg::GetGameRatings { game_id })
+        .map(|binary| from_binary::<GameRatings>(&binary).unwrap())
+    };
+    let starting_rating = CONFIG.load(&deps.storage).unwrap().starting_rating;
+
+    // two new players both start from the starting rating, with no history
+    let first = start_game(&mut deps, "alice", "bob", None);
+    resign(&mut deps, "bob", first);
+    let ratings = game_ratings(&deps, first).unwrap();
+    assert_eq!(ratings.time_control, TimeControl::Correspondence);
+    assert_eq!(ratings.player1.player, "alice");
+    assert!(ratings.player1.rating > starting_rating);
+    assert_eq!(ratings.player1.rating_at_start, None);
+    assert_eq!(ratings.player2.player, "bob");
+    assert!(ratings.player2.rating < starting_rating);
+    assert_eq!(ratings.player2.rating_at_start, None);
+    let alice_rating = ratings.player1.rating;
+
+    // an experienced player against a new one
+    let second = start_game(&mut deps, "alice", "carol", None);
+    assert_eq!(
+      game_ratings(&deps, second).unwrap(),
+      GameRatings {
+        game_id: second,
+        player1: PlayerGameRating {
+          player: "alice".to_string(),
+          rating: alice_rating,
+          rating_at_start: Some(alice_rating),
+        },
+        player2: PlayerGameRating {
+          player: "carol".to_string(),
+          rating: starting_rating,
+          rating_at_start: None,
+        },
+        time_control: TimeControl::Correspondence,
+      }
+    );
+    // once it's over the current ratings move, the ones at the start stay
+    resign(&mut deps, "alice", second);
+    let ratings = game_ratings(&deps, second).unwrap();
+    assert!(ratings.player1.rating < alice_rating);
+    assert_eq!(ratings.player1.rating_at_start, Some(alice_rating));
+    assert!(ratings.player2.rating > starting_rating);
+    assert_eq!(ratings.player2.rating_at_start, None);
+
+    // history from other time controls doesn't count
+    let blitz = start_game(&mut deps, "alice", "bob", Some(50));
+    let ratings = game_ratings(&deps, blitz).unwrap();
+    assert_eq!(ratings.time_control, TimeControl::Blitz);
+    assert_eq!(ratings.player1.rating, starting_rating);
+    assert_eq!(ratings.player1.rating_at_start, None);
+
+    assert!(game_ratings(&deps, 99).is_err());
+  }
+
+  #[test]
+  fn test_auto_queen_promotion() {
+    let mut deps = mock_dependencies();
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let start_game = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, challenge_id: u64| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          start_fen: Some("k7/4P3/8/8/8/8/8/4K3 w - - 0 1".to_string()),
+          ..NewChallenge::default()
+        }),
+      )
+      .unwrap();
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("black", &[]),
+        ExecuteMsg::AcceptChallenge { challenge_id },
+      )
+      .unwrap();
+    };
+    let promote = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, game_id: u64, move_str: &str| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(move_str),
+          game_id,
+        },
+      )
+    };
+    let placement = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, game_id: u64| {
+      let game = get_games_map().load(&deps.storage, game_id).unwrap();
+      game.fen.split(' ').next().unwrap().to_string()
+    };
+
+    // by default a bare promotion is a queen
+    assert!(CONFIG.load(&deps.storage).unwrap().auto_queen_promotion);
+    start_game(&mut deps, 1);
+    promote(&mut deps, 1, "e8").unwrap();
+    assert_eq!(placement(&deps, 1), "k3Q3/8/8/8/8/8/8/4K3");
+
+    // once turned off the piece has to be named
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      ExecuteMsg::UpdateConfig {
+        abandon_threshold: None,
+        auto_queen_promotion: Some(false),
+        blitz_max_blocks: None,
+        bullet_max_blocks: None,
+        challenge_fee: None,
+        completion_hook: None,
+        decay_after_blocks: None,
+        decay_amount: None,
+        draw_offer_cooldown_blocks: None,
+        high_rating_k_factor: None,
+        high_rating_threshold: None,
+        inactivity_blocks: None,
+        k_factor: None,
+        max_block_limit: None,
+        max_open_challenges: None,
+        max_pairwise_challenges: None,
+        provisional_games: None,
+        provisional_k_factor: None,
+        rapid_max_blocks: None,
+        rate_draws: None,
+        rating_ceiling: None,
+        rating_floor: None,
+        refund_challenge_fee: None,
+        require_resign_confirmation: None,
+        starting_rating: None,
+        treasury: None,
+      },
+    )
+    .unwrap();
+    start_game(&mut deps, 2);
+    match promote(&mut deps, 2, "e8").unwrap_err() {
+      ContractError::PromotionRequired { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    let validation = from_binary::<MoveValidation>(
+      &query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ValidMove {
+          game_id: 2,
+          player: "white".to_string(),
+          move_str: "e8".to_string(),
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(validation.reason, Some(ContractError::PromotionRequired {}.to_string()));
+    assert_eq!(placement(&deps, 2), "k7/4P3/8/8/8/8/8/4K3");
+    promote(&mut deps, 2, "e8=N").unwrap();
+    assert_eq!(placement(&deps, 2), "k3N3/8/8/8/8/8/8/4K3");
+  }
+
+  #[test]
+  fn test_fen_at_move_round_trip() {
+    let mut deps = mock_dependencies();
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let create_challenge = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, start_fen: Option<String>| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("white", &[]),
+        create_challenge(NewChallenge {
+          play_as: Some(CwChessColor::White),
+          start_fen,
+          ..NewChallenge::default()
+        }),
+      )
+    };
+    create_challenge(&mut deps, None).unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("black", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    for (player, move_str) in [("white", "e4"), ("black", "c5"), ("white", "Nf3")] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::Turn {
+          action: CwChessAction::from(move_str),
+          game_id: 1,
+        },
+      )
+      .unwrap();
+    }
+
+    // every position can start a new game
+    for ply in 1..=3 {
+      let fen = from_binary::<String>(
+        &query(deps.as_ref(), mock_env(), QueryMsg::GetFenAtMove { game_id: 1, ply }).unwrap(),
+      )
+      .unwrap();
+      validate_fen(&fen).unwrap();
+      create_challenge(&mut deps, Some(fen)).unwrap();
+    }
+  }
+
+  #[test]
+  fn test_challenge_expiry_overflow() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+
+    // expiry past the last block height
+    let response = execute(
+      deps.as_mut(),
+      block_env(100),
+      mock_info("creator", &[]),
+      create_challenge(NewChallenge {
+        expires_in_blocks: Some(u64::MAX),
+        opponent: Some("opponent".to_string()),
+        ..NewChallenge::default()
+      }),
+    );
+    match response.unwrap_err() {
+      ContractError::InvalidExpiry {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_abort_event_game() {
+    let mut deps = mock_dependencies();
+
+    // initialize
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("owner", &[]),
+      InstantiateMsg::default(),
+    )
+    .unwrap();
+    let abort = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, game_id, player: &Addr| {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player.as_str(), &[]),
+        ExecuteMsg::AbortGame { game_id },
+      )
+    };
+    let load = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, game_id| {
+      from_binary::<CwChessGame>(&query(deps.as_ref(), mock_env(), QueryMsg::GetGame { game_id }).unwrap()).unwrap()
+    };
+
+    // a full knockout bracket starts round 1
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("organizer", &[]),
+      ExecuteMsg::CreateTournament {
+        block_limit: None,
+        format: None,
+        name: "cup".to_string(),
+        size: 2,
+      },
+    )
+    .unwrap();
+    for player in ["alice", "bob"] {
+      execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(player, &[]),
+        ExecuteMsg::JoinTournament { tournament_id: 1 },
+      )
+      .unwrap();
+    }
+
+    // black can't take the bracket slot by aborting before moving
+    let game = load(&deps, 1);
+    for player in [&game.player2, &game.player1] {
+      match abort(&mut deps, 1, player).unwrap_err() {
+        ContractError::CannotAbortEventGame { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+    assert_eq!(load(&deps, 1).status, None);
+
+    // and nobody can void a match game
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("carol", &[]),
+      create_challenge(NewChallenge {
+        num_games: Some(2),
+        ..NewChallenge::default()
+      }),
+    )
+    .unwrap();
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("dave", &[]),
+      ExecuteMsg::AcceptChallenge { challenge_id: 1 },
+    )
+    .unwrap();
+    let game = load(&deps, 2);
+    match abort(&mut deps, 2, &game.player2).unwrap_err() {
+      ContractError::CannotAbortEventGame { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
   }
 }
@@ -1,17 +1,32 @@
+use crate::board::Board;
+use crate::eco::OPENINGS;
 use crate::error::ContractError;
-use crate::engine::Color;
+use crate::engine::{Color, Move};
+use crate::movegen::legal_moves;
 use crate::game::{Game, GameAction, GameOver};
-use cosmwasm_std::Addr;
+use crate::piece::Piece;
+use crate::position::Position;
+use crate::util::{compress_fen, decompress_fen, format_san_move, is_castling_move, is_move_into_check, parse_fen, parse_san_move};
+use cosmwasm_std::{Addr, Binary};
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+use sha2::{Digest, Sha256};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum CwChessAction {
   AcceptDraw,
+  // undo the last move, if the opponent requested it
+  AcceptTakeback,
+  // draw by threefold repetition or the fifty-move rule, on the claimer's turn
+  ClaimDraw,
+  // turn down the opponent's draw offer and keep playing, still the decliner's turn
+  DeclineDraw,
   #[serde(rename = "move")]
   MakeMove(String),
   OfferDraw(String),
+  // ask the opponent to undo the last move
+  RequestTakeback,
   Resign,
 }
 
@@ -21,13 +36,22 @@ impl From<&str> for CwChessAction {
   }
 }
 
-impl From<&CwChessAction> for GameAction {
-  fn from(action: &CwChessAction) -> GameAction {
+impl TryFrom<&CwChessAction> for GameAction {
+  type Error = ContractError;
+
+  fn try_from(action: &CwChessAction) -> Result<GameAction, ContractError> {
     match action {
-      CwChessAction::AcceptDraw => GameAction::AcceptDraw,
-      CwChessAction::MakeMove(move_str) => GameAction::MakeMove(move_str.to_string()),
-      CwChessAction::OfferDraw(move_str) => GameAction::OfferDraw(move_str.to_string()),
-      CwChessAction::Resign => GameAction::Resign,
+      CwChessAction::AcceptDraw => Ok(GameAction::AcceptDraw),
+      CwChessAction::MakeMove(move_str) => Ok(GameAction::MakeMove(move_str.to_string())),
+      CwChessAction::OfferDraw(move_str) => Ok(GameAction::OfferDraw(move_str.to_string())),
+      CwChessAction::Resign => Ok(GameAction::Resign),
+      // takebacks, draw claims and declines are handled by CwChessGame, not the engine
+      CwChessAction::AcceptTakeback
+      | CwChessAction::ClaimDraw
+      | CwChessAction::DeclineDraw
+      | CwChessAction::RequestTakeback => {
+        Err(ContractError::InvalidMove {})
+      }
     }
   }
 }
@@ -39,6 +63,24 @@ pub enum CwChessColor {
   Black,
 }
 
+impl CwChessColor {
+  pub fn opponent(&self) -> CwChessColor {
+    match self {
+      CwChessColor::White => CwChessColor::Black,
+      CwChessColor::Black => CwChessColor::White,
+    }
+  }
+
+  // active color field of a FEN, 'w' or 'b'
+  pub fn from_fen_char(c: char) -> Option<CwChessColor> {
+    match c {
+      'w' => Some(CwChessColor::White),
+      'b' => Some(CwChessColor::Black),
+      _ => None,
+    }
+  }
+}
+
 impl From<&Color> for CwChessColor {
   fn from(color: &Color) -> CwChessColor {
     match color {
@@ -57,6 +99,125 @@ impl From<&CwChessColor> for Color {
   }
 }
 
+// piece a side gives up in a handicap game
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OddsType {
+  // the f pawn
+  Pawn,
+  // the queenside knight
+  Knight,
+  // the queenside rook, along with queenside castling
+  Rook,
+  Queen,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CwChessPieceType {
+  King,
+  Queen,
+  Rook,
+  Bishop,
+  Knight,
+  Pawn,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessPiece {
+  pub color: CwChessColor,
+  pub piece_type: CwChessPieceType,
+}
+
+impl From<&Piece> for CwChessPiece {
+  fn from(piece: &Piece) -> CwChessPiece {
+    let piece_type = match piece {
+      Piece::King(..) => CwChessPieceType::King,
+      Piece::Queen(..) => CwChessPieceType::Queen,
+      Piece::Rook(..) => CwChessPieceType::Rook,
+      Piece::Bishop(..) => CwChessPieceType::Bishop,
+      Piece::Knight(..) => CwChessPieceType::Knight,
+      Piece::Pawn(..) => CwChessPieceType::Pawn,
+    };
+    CwChessPiece {
+      color: CwChessColor::from(&piece.get_color()),
+      piece_type,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessCastling {
+  pub black_kingside: bool,
+  pub black_queenside: bool,
+  pub white_kingside: bool,
+  pub white_queenside: bool,
+}
+
+// board position decoded from FEN
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CwChessBoard {
+  pub castling: CwChessCastling,
+  // square a pawn can be captured on en passant, e.g. "e3"
+  pub en_passant: Option<String>,
+  // 64 squares from white's side: a1, b1, ..., h1, a2, ..., h8
+  // index is rank * 8 + file
+  pub squares: Vec<Option<CwChessPiece>>,
+  pub turn: CwChessColor,
+}
+
+impl CwChessBoard {
+  pub fn from_fen(fen: &str) -> Result<CwChessBoard, ContractError> {
+    match parse_fen(fen) {
+      Ok(board) => Ok(CwChessBoard::from_board(&board)),
+      Err(_) => Err(ContractError::InvalidPosition {}),
+    }
+  }
+
+  // from an already parsed board, saving a FEN round trip
+  pub fn from_board(board: &Board) -> CwChessBoard {
+    let squares = (0..64)
+      .map(|i| board.get_piece(Position::new(i / 8, i % 8)).as_ref().map(CwChessPiece::from))
+      .collect();
+    let white = board.get_castling_rights(Color::White);
+    let black = board.get_castling_rights(Color::Black);
+    CwChessBoard {
+      castling: CwChessCastling {
+        black_kingside: black.can_kingside_castle(),
+        black_queenside: black.can_queenside_castle(),
+        white_kingside: white.can_kingside_castle(),
+        white_queenside: white.can_queenside_castle(),
+      },
+      en_passant: board.get_en_passant().map(|pos| pos.to_string()),
+      squares,
+      turn: CwChessColor::from(&board.get_turn_color()),
+    }
+  }
+
+  // neither side can checkmate by any sequence of legal moves (FIDE 5.2.2):
+  // king against king with at most one minor piece on the board,
+  // or only bishops left, all on squares of the same color
+  pub fn is_insufficient_material(&self) -> bool {
+    let mut minors = vec![];
+    for (i, square) in self.squares.iter().enumerate() {
+      match square.as_ref().map(|piece| &piece.piece_type) {
+        None | Some(CwChessPieceType::King) => {}
+        Some(CwChessPieceType::Knight) => minors.push(None),
+        // light squares have rank + file odd
+        Some(CwChessPieceType::Bishop) => minors.push(Some((i / 8 + i % 8) % 2)),
+        Some(_) => return false,
+      }
+    }
+    match minors.as_slice() {
+      [] | [_] => true,
+      [first, rest @ ..] => first.is_some() && rest.iter().all(|minor| minor == first),
+    }
+  }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum CwChessGameOver {
@@ -64,13 +225,20 @@ pub enum CwChessGameOver {
   BlackCheckmates,
   BlackResigns,
   DrawAccepted,
-  DrawDeclared,
+  // threefold repetition or the fifty-move rule, claimed with ClaimDraw
+  // saved as draw_declared before it was renamed
+  #[serde(alias = "draw_declared")]
+  DrawClaimed,
   Stalemate,
   WhiteCheckmates,
   WhiteResigns,
   // custom results
   BlackTimeout,
   WhiteTimeout,
+  // neither side has the material to checkmate
+  InsufficientMaterial,
+  // ended by the contract owner, no winner and no rating change
+  Voided,
 }
 
 impl From<&GameOver> for CwChessGameOver {
@@ -88,29 +256,192 @@ impl From<&GameOver> for CwChessGameOver {
 
 pub type CwChessMove = (u64, CwChessAction);
 
+pub const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", into = "GameRecord", try_from = "GameRecord")]
 pub struct CwChessGame {
   // per player block limit for all moves
   // starts at first move (not game start_height)
   pub block_limit: Option<u64>,
   // when game was created
   pub block_start: u64,
+  // remaining blocks (white, black) as of the last move
+  // None for games without a block_limit
+  pub clocks: Option<(u64, u64)>,
   // board position in FEN
   // cheaper to load board than executing moves
   pub fen: String,
   // game id
   pub game_id: u64,
+  // blocks added to a player's clock after each of their moves
+  pub increment_blocks: Option<u64>,
+  // block (white, black) last offered a draw on, kept through takebacks
+  #[serde(default)]
+  pub last_draw_offers: (Option<u64>, Option<u64>),
+  // list of moves
+  pub moves: Vec<CwChessMove>,
+  // handicap the start_fen was made with, odds games leave ratings alone
+  #[serde(default)]
+  pub odds: Option<OddsType>,
+  // result submitted by one player with AgreeResult, waiting on the other
+  #[serde(default)]
+  pub pending_result: Option<(CwChessColor, CwChessGameOver)>,
+  // color that asked to undo the last move
+  pub pending_takeback: Option<CwChessColor>,
+  // player1 is white
+  pub player1: Addr,
+  // player2 is black
+  pub player2: Addr,
+  // move queued by the player waiting on their opponent,
+  // played as soon as it's their turn if still legal
+  #[serde(default)]
+  pub premove: Option<(CwChessColor, String)>,
+  // decided the colors (unless chosen) and any chess960 position,
+  // see get_player_order and chess960_fen
+  #[serde(default)]
+  pub seed: Option<u64>,
+  // custom starting position in FEN
+  // None for the standard starting position
+  pub start_fen: Option<String>,
+  // status is None while game is being played
+  pub status: Option<CwChessGameOver>,
+}
+
+// CwChessGame as saved in state, with the position compacted when possible
+// so each game stores a few dozen bytes instead of its FEN
+// games saved before keep their fen and load as before
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct GameRecord {
+  // per player block limit for all moves
+  // starts at first move (not game start_height)
+  pub block_limit: Option<u64>,
+  // when game was created
+  pub block_start: u64,
+  // board position from compress_fen, None for positions it can't encode
+  // and in query responses
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub board: Option<Binary>,
+  // remaining blocks (white, black) as of the last move
+  // None for games without a block_limit
+  pub clocks: Option<(u64, u64)>,
+  // board position in FEN, None when saved as board
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub fen: Option<String>,
+  // game id
+  pub game_id: u64,
+  // blocks added to a player's clock after each of their moves
+  pub increment_blocks: Option<u64>,
+  // block (white, black) last offered a draw on, kept through takebacks
+  #[serde(default)]
+  pub last_draw_offers: (Option<u64>, Option<u64>),
   // list of moves
   pub moves: Vec<CwChessMove>,
+  // handicap the start_fen was made with, odds games leave ratings alone
+  #[serde(default)]
+  pub odds: Option<OddsType>,
+  // color whose draw offer waits on the opponent, only filled in for queries
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub pending_draw_offer: Option<CwChessColor>,
+  // result submitted by one player with AgreeResult, waiting on the other
+  #[serde(default)]
+  pub pending_result: Option<(CwChessColor, CwChessGameOver)>,
+  // color that asked to undo the last move
+  pub pending_takeback: Option<CwChessColor>,
   // player1 is white
   pub player1: Addr,
   // player2 is black
   pub player2: Addr,
+  // move queued by the player waiting on their opponent,
+  // played as soon as it's their turn if still legal
+  #[serde(default)]
+  pub premove: Option<(CwChessColor, String)>,
+  // decided the colors (unless chosen) and any chess960 position,
+  // see get_player_order and chess960_fen
+  #[serde(default)]
+  pub seed: Option<u64>,
+  // custom starting position in FEN
+  // None for the standard starting position
+  pub start_fen: Option<String>,
   // status is None while game is being played
   pub status: Option<CwChessGameOver>,
 }
 
+impl GameRecord {
+  // shape returned by queries, with the fen and no board
+  pub fn plain(game: &CwChessGame) -> GameRecord {
+    let mut record = GameRecord::from(game.clone());
+    record.fen = Some(game.fen.clone());
+    record.board = None;
+    record.pending_draw_offer = game.draw_offered();
+    record
+  }
+}
+
+// serialize_with for responses embedding a CwChessGame
+pub fn serialize_plain<S: Serializer>(game: &CwChessGame, serializer: S) -> Result<S::Ok, S::Error> {
+  GameRecord::plain(game).serialize(serializer)
+}
+
+impl From<CwChessGame> for GameRecord {
+  fn from(game: CwChessGame) -> GameRecord {
+    let board = compress_fen(&game.fen).map(Binary::from);
+    GameRecord {
+      block_limit: game.block_limit,
+      block_start: game.block_start,
+      clocks: game.clocks,
+      game_id: game.game_id,
+      increment_blocks: game.increment_blocks,
+      last_draw_offers: game.last_draw_offers,
+      moves: game.moves,
+      odds: game.odds,
+      pending_draw_offer: None,
+      pending_result: game.pending_result,
+      pending_takeback: game.pending_takeback,
+      player1: game.player1,
+      player2: game.player2,
+      premove: game.premove,
+      seed: game.seed,
+      start_fen: game.start_fen,
+      status: game.status,
+      fen: if board.is_some() { None } else { Some(game.fen) },
+      board,
+    }
+  }
+}
+
+impl TryFrom<GameRecord> for CwChessGame {
+  type Error = String;
+
+  fn try_from(record: GameRecord) -> Result<CwChessGame, String> {
+    let fen = match (record.fen, record.board) {
+      (Some(fen), _) => fen,
+      (None, Some(board)) => decompress_fen(board.as_slice())?,
+      (None, None) => return Err("game has no position".to_string()),
+    };
+    Ok(CwChessGame {
+      block_limit: record.block_limit,
+      block_start: record.block_start,
+      clocks: record.clocks,
+      game_id: record.game_id,
+      increment_blocks: record.increment_blocks,
+      last_draw_offers: record.last_draw_offers,
+      moves: record.moves,
+      odds: record.odds,
+      pending_result: record.pending_result,
+      pending_takeback: record.pending_takeback,
+      player1: record.player1,
+      player2: record.player2,
+      premove: record.premove,
+      seed: record.seed,
+      start_fen: record.start_fen,
+      status: record.status,
+      fen,
+    })
+  }
+}
+
 impl CwChessGame {
   // check if game timed out based on block_time_limit
   pub fn check_timeout(
@@ -121,33 +452,35 @@ impl CwChessGame {
     if self.status.is_some() {
       return Err(ContractError::GameAlreadyOver {});
     }
-    self.status = match self.block_limit {
-      None => None,
-      Some(block_time_limit) => {
-        let block_times = self.get_block_times(current_block);
-        if block_times.0 > block_time_limit {
-          Some(CwChessGameOver::WhiteTimeout {})
-        } else if block_times.1 > block_time_limit {
-          Some(CwChessGameOver::BlackTimeout {})
-        } else {
-          None
+    // only the player to move is using their clock
+    self.status = match (self.clocks.or_else(|| self.replay_clocks()), self.moves.last()) {
+      (Some(clocks), Some((height, _))) => {
+        let elapsed = current_block.saturating_sub(*height);
+        match self.turn_color() {
+          Some(CwChessColor::White) if elapsed > clocks.0 => Some(CwChessGameOver::WhiteTimeout {}),
+          Some(CwChessColor::Black) if elapsed > clocks.1 => Some(CwChessGameOver::BlackTimeout {}),
+          _ => None,
         }
       }
+      _ => None,
     };
     Ok(&self.status)
   }
 
+  // (white, black) for challenger player1 and acceptor player2
+  // play_as always fixes the challenger's color, the seed only decides without it,
+  // see game_seed for how it's made
   pub fn get_player_order(
     player1: Addr,
     player2: Addr,
     play_as: Option<CwChessColor>,
-    height: u64,
+    seed: u64,
   ) -> (Addr, Addr) {
     match play_as {
       Some(CwChessColor::White) => (player1, player2),
       Some(CwChessColor::Black) => (player2, player1),
       None => {
-        if height % 2 == 0 {
+        if seed.is_multiple_of(2) {
           (player1, player2)
         } else {
           (player2, player1)
@@ -167,16 +500,310 @@ impl CwChessGame {
     }
   }
 
+  /// replay the game from the starting position, returning the
+  /// (block height, color, SAN) of every move played on the board.
+  ///
+  /// draw acceptances and resignations are not board moves and are skipped.
+  pub fn get_san_moves(&self) -> Result<Vec<(u64, CwChessColor, String)>, ContractError> {
+    let mut game = self.start_game()?;
+    let mut san_moves = vec![];
+    for (height, action) in &self.moves {
+      if let CwChessAction::MakeMove(move_str) | CwChessAction::OfferDraw(move_str) = action {
+        let color = CwChessColor::from(&game.get_turn_color());
+        let san = match parse_san_move(&game.board, move_str)
+          .and_then(|chess_move| format_san_move(&game.board, chess_move))
+        {
+          Ok(san) => san,
+          Err(_) => return Err(ContractError::InvalidMove {}),
+        };
+        if game.make_move(&GameAction::try_from(action)?).is_err() {
+          return Err(ContractError::InvalidMove {});
+        }
+        san_moves.push((*height, color, san));
+      }
+    }
+    Ok(san_moves)
+  }
+
+  // moves that changed the board, leaving out draw claims and the like
+  fn board_moves(&self) -> usize {
+    self
+      .moves
+      .iter()
+      .filter(|(_, action)| matches!(action, CwChessAction::MakeMove(_) | CwChessAction::OfferDraw(_)))
+      .count()
+  }
+
+  // fullmove number ply board moves after the start, counting on from the start position's
+  fn fullmove_number(&self, ply: usize) -> u32 {
+    let start_fen = self.start_fen.as_deref().unwrap_or(DEFAULT_FEN);
+    let mut fields = start_fen.split(' ').skip(1);
+    let black_first = fields.next() == Some("b");
+    let first = fields.nth(3).and_then(|n| n.parse::<u32>().ok()).unwrap_or(1).max(1);
+    let moves = (ply + black_first as usize) / 2;
+    first.saturating_add(u32::try_from(moves).unwrap_or(u32::MAX))
+  }
+
+  /// replay the game from the starting position through the first ply board moves,
+  /// returning the FEN at that point, the same as stored after that move.
+  ///
+  /// a ply past the last board move is an error.
+  pub fn fen_at_ply(&self, ply: usize) -> Result<String, ContractError> {
+    let mut game = self.start_game()?;
+    let board_moves = self
+      .moves
+      .iter()
+      .filter(|(_, action)| matches!(action, CwChessAction::MakeMove(_) | CwChessAction::OfferDraw(_)));
+    let mut played = 0;
+    for (_, action) in board_moves.take(ply) {
+      if game.make_move(&GameAction::try_from(action)?).is_err() {
+        return Err(ContractError::InvalidMove {});
+      }
+      played += 1;
+    }
+    if played < ply {
+      return Err(ContractError::MoveNotFound {});
+    }
+    match ply {
+      0 => Ok(self.start_fen.clone().unwrap_or_else(|| DEFAULT_FEN.to_string())),
+      _ => game.to_fen(0, self.fullmove_number(ply)).map_err(|_| ContractError::InvalidPosition {}),
+    }
+  }
+
+  /// replay the game, returning the position after the start and after every
+  /// board move, each with whether it was reached by a pawn move or capture.
+  ///
+  /// positions are FENs without the move counters.
+  fn position_history(&self) -> Result<Vec<(String, bool)>, ContractError> {
+    let position = |game: &Game| -> Result<String, ContractError> {
+      match game.to_fen(0, 1) {
+        Ok(fen) => Ok(repetition_key(&fen)),
+        Err(_) => Err(ContractError::InvalidPosition {}),
+      }
+    };
+    let mut game = self.start_game()?;
+    let mut history = vec![(position(&game)?, false)];
+    for (_, action) in &self.moves {
+      if let CwChessAction::MakeMove(_) | CwChessAction::OfferDraw(_) = action {
+        if game.make_move(&GameAction::try_from(action)?).is_err() {
+          return Err(ContractError::InvalidMove {});
+        }
+        let after = position(&game)?;
+        let irreversible = is_irreversible(&history[history.len() - 1].0, &after);
+        history.push((after, irreversible));
+      }
+    }
+    Ok(history)
+  }
+
+  /// whether the side to move can claim a draw: the current position occurred
+  /// three times, or both sides made fifty moves without a pawn move or capture.
+  ///
+  /// only positions since the last pawn move or capture can repeat.
+  pub fn can_claim_draw(&self) -> Result<bool, ContractError> {
+    let history = self.position_history()?;
+    let since = history
+      .iter()
+      .rposition(|(_, irreversible)| *irreversible)
+      .unwrap_or(0);
+    // a custom start position can carry over a halfmove clock
+    let start_halfmoves = match (since, &self.start_fen) {
+      (0, Some(fen)) => fen.split(' ').nth(4).and_then(|n| n.parse().ok()).unwrap_or(0),
+      _ => 0,
+    };
+    let positions = &history[since..];
+    let halfmoves = start_halfmoves + positions.len() - 1;
+    let current = &positions[positions.len() - 1].0;
+    let repetitions = positions.iter().filter(|(position, _)| position == current).count();
+    Ok(halfmoves >= 100 || repetitions >= 3)
+  }
+
+  /// classify the opening by the longest matching move prefix in the ECO table,
+  /// returning (ECO code, opening name).
+  ///
+  /// games from a custom starting position have no opening.
+  pub fn get_opening(&self) -> Result<Option<(&'static str, &'static str)>, ContractError> {
+    if self.start_fen.as_deref().unwrap_or(DEFAULT_FEN) != DEFAULT_FEN {
+      return Ok(None);
+    }
+    let san_moves = self.get_san_moves()?;
+    let played = san_moves
+      .iter()
+      .map(|(_, _, san)| san.trim_end_matches(['+', '#']))
+      .collect::<Vec<&str>>();
+    Ok(
+      OPENINGS
+        .iter()
+        .filter(|(_, _, moves)| {
+          let moves = moves.split(' ').collect::<Vec<&str>>();
+          played.starts_with(&moves)
+        })
+        .max_by_key(|(_, _, moves)| moves.split(' ').count())
+        .map(|(eco, name, _)| (*eco, *name)),
+    )
+  }
+
+  // game at the starting position
+  fn start_game(&self) -> Result<Game, ContractError> {
+    match &self.start_fen {
+      Some(fen) => match Game::from_fen(fen, None, None) {
+        Ok(game) => Ok(game),
+        Err(_) => Err(ContractError::InvalidPosition {}),
+      },
+      None => Ok(Game::default()),
+    }
+  }
+
+  /// export the game as PGN, with the Seven Tag Roster and SAN movetext.
+  ///
+  /// games don't record wall clock time, so the Date tag is unknown.
+  pub fn to_pgn(&self, site: &str) -> Result<String, ContractError> {
+    let result = self.pgn_result();
+    let mut tags = vec![
+      ("Event", format!("cosmos-chess game {}", self.game_id)),
+      ("Site", site.to_string()),
+      ("Date", "????.??.??".to_string()),
+      ("Round", "-".to_string()),
+      ("White", self.player1.to_string()),
+      ("Black", self.player2.to_string()),
+      ("Result", result.to_string()),
+    ];
+    if let Some(fen) = &self.start_fen {
+      tags.push(("SetUp", "1".to_string()));
+      tags.push(("FEN", fen.clone()));
+    }
+    let mut pgn = tags
+      .iter()
+      .map(|(tag, value)| format!("[{} \"{}\"]\n", tag, value))
+      .collect::<String>();
+    pgn.push('\n');
+
+    // movetext, wrapped to 80 columns
+    let mut tokens = vec![];
+    let black_first = self.start_color() == CwChessColor::Black;
+    for (i, (_, color, san)) in self.get_san_moves()?.into_iter().enumerate() {
+      let move_number = (i + black_first as usize) / 2 + 1;
+      match color {
+        CwChessColor::White => tokens.push(format!("{}.", move_number)),
+        CwChessColor::Black if i == 0 => tokens.push(format!("{}...", move_number)),
+        CwChessColor::Black => {}
+      }
+      tokens.push(san);
+    }
+    tokens.push(result.to_string());
+    let mut line = String::new();
+    for token in tokens {
+      if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+        pgn.push_str(&line);
+        pgn.push('\n');
+        line.clear();
+      }
+      if !line.is_empty() {
+        line.push(' ');
+      }
+      line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push('\n');
+    Ok(pgn)
+  }
+
+  /// rebuild a finished game from PGN, playing every move through the rules engine.
+  ///
+  /// a game the moves end takes its result from the board, which has to match
+  /// the PGN's. otherwise a decisive result is recorded as a resignation and a
+  /// draw as an accepted one. unfinished games can't be imported.
+  pub fn from_pgn(
+    game_id: u64,
+    block_start: u64,
+    pgn: &str,
+    player1: Addr,
+    player2: Addr,
+  ) -> Result<CwChessGame, ContractError> {
+    let (tags, san_moves, result) = parse_pgn(pgn)?;
+    let start_fen = tags
+      .into_iter()
+      .find(|(tag, _)| tag == "FEN")
+      .map(|(_, fen)| fen);
+    if let Some(fen) = &start_fen {
+      if validate_fen(fen).is_err() {
+        return Err(ContractError::InvalidFen {});
+      }
+    }
+    let mut game = CwChessGame {
+      block_limit: None,
+      block_start,
+      clocks: None,
+      fen: start_fen.clone().unwrap_or_else(|| DEFAULT_FEN.to_string()),
+      game_id,
+      increment_blocks: None,
+      last_draw_offers: (None, None),
+      moves: vec![],
+      odds: None,
+      pending_result: None,
+      pending_takeback: None,
+      player1,
+      player2,
+      premove: None,
+      seed: None,
+      start_fen,
+      status: None,
+    };
+    for san in san_moves {
+      // no moves after the board ends the game
+      let player = match game.turn_color() {
+        Some(CwChessColor::White) => game.player1.clone(),
+        Some(CwChessColor::Black) => game.player2.clone(),
+        None => return Err(ContractError::InvalidMove {}),
+      };
+      game.make_move(&player, (block_start, CwChessAction::MakeMove(san)))?;
+    }
+    if game.status.is_some() {
+      if game.pgn_result() != result {
+        return Err(ContractError::InvalidPgn {});
+      }
+      return Ok(game);
+    }
+    game.status = match result.as_str() {
+      "1-0" => Some(CwChessGameOver::BlackResigns),
+      "0-1" => Some(CwChessGameOver::WhiteResigns),
+      "1/2-1/2" => Some(CwChessGameOver::DrawAccepted),
+      _ => return Err(ContractError::InvalidPgn {}),
+    };
+    Ok(game)
+  }
+
+  // PGN game termination marker
+  pub fn pgn_result(&self) -> &'static str {
+    match self.status {
+      None => "*",
+      Some(CwChessGameOver::WhiteCheckmates)
+      | Some(CwChessGameOver::BlackResigns)
+      | Some(CwChessGameOver::BlackTimeout) => "1-0",
+      Some(CwChessGameOver::BlackCheckmates)
+      | Some(CwChessGameOver::WhiteResigns)
+      | Some(CwChessGameOver::WhiteTimeout) => "0-1",
+      Some(CwChessGameOver::DrawAccepted)
+      | Some(CwChessGameOver::DrawClaimed)
+      | Some(CwChessGameOver::Stalemate)
+      | Some(CwChessGameOver::InsufficientMaterial) => "1/2-1/2",
+      Some(CwChessGameOver::Voided) => "*",
+    }
+  }
+
   /// validate the move
   pub fn valid_move(
     &self,
     player: &Addr,
-    move_str: &String
+    move_str: &str
   ) -> Result<bool, ContractError> {
     // check if game already over
     if self.status.is_some() {
       return Err(ContractError::GameAlreadyOver {});
     }
+    if self.player_color(player).is_none() {
+      return Err(ContractError::NotYourGame {});
+    }
 
     // validate the move
     let game = self.load_game()?;
@@ -191,10 +818,85 @@ impl CwChessGame {
 
     match game.valid_move(move_str) {
       Ok(valid) => Ok(valid),
+      Err(_) if is_move_into_check(&game.board, move_str) => Err(ContractError::KingInCheck {}),
       Err(_) => Err(ContractError::InvalidMove {}),
     }
   }
 
+  // the game after the player to move plays move_str, None if it isn't legal
+  // played at the last move's height, so clocks can't run out
+  pub fn simulate_move(&self, move_str: &str) -> Option<CwChessGame> {
+    let player = match self.turn_color()? {
+      CwChessColor::White => self.player1.clone(),
+      CwChessColor::Black => self.player2.clone(),
+    };
+    let height = self.moves.last().map_or(self.block_start, |(height, _)| *height);
+    let mut game = self.clone();
+    game
+      .make_move(&player, (height, CwChessAction::MakeMove(move_str.to_string())))
+      .ok()?;
+    Some(game)
+  }
+
+  // queue a move for the player's next turn, replacing any earlier one
+  pub fn set_premove(&mut self, player: &Addr, move_str: String) -> Result<(), ContractError> {
+    let color = match self.player_color(player) {
+      Some(color) => color,
+      None => return Err(ContractError::NotYourGame {}),
+    };
+    if self.status.is_some() {
+      return Err(ContractError::GameAlreadyOver {});
+    }
+    if self.turn_color() == Some(color.clone()) {
+      return Err(ContractError::CannotPremoveOwnTurn {});
+    }
+    self.premove = Some((color, move_str));
+    Ok(())
+  }
+
+  // play the premove once it's its player's turn, at the same height
+  // an illegal premove is dropped, returns whether one was played
+  // None while no premove is due
+  pub fn play_premove(&mut self, height: u64) -> Option<bool> {
+    match (&self.premove, self.turn_color()) {
+      (Some((color, _)), Some(turn)) if *color == turn => {}
+      _ => return None,
+    }
+    let (color, move_str) = self.premove.take()?;
+    let player = match color {
+      CwChessColor::White => self.player1.clone(),
+      CwChessColor::Black => self.player2.clone(),
+    };
+    let mut game = self.clone();
+    match game.make_move(&player, (height, CwChessAction::MakeMove(move_str))) {
+      Ok(_) => {
+        *self = game;
+        Some(true)
+      }
+      Err(_) => Some(false),
+    }
+  }
+
+  // a player has to wait cooldown_blocks after offering a draw to offer another
+  pub fn check_draw_offer_cooldown(
+    &self,
+    player: &Addr,
+    height: u64,
+    cooldown_blocks: u64,
+  ) -> Result<(), ContractError> {
+    let last_offer = match self.player_color(player) {
+      Some(CwChessColor::White) => self.last_draw_offers.0,
+      Some(CwChessColor::Black) => self.last_draw_offers.1,
+      None => None,
+    };
+    match last_offer {
+      Some(last_offer) if height < last_offer.saturating_add(cooldown_blocks) => {
+        Err(ContractError::DrawOfferTooSoon {})
+      }
+      _ => Ok(()),
+    }
+  }
+
   // get dirty state
   pub fn get_turn(
     &self,
@@ -225,11 +927,27 @@ impl CwChessGame {
     if self.status.is_some() {
       return Err(ContractError::GameAlreadyOver {});
     }
+    // only the two players can act, whoever's turn it is
+    if self.player_color(player).is_none() {
+      return Err(ContractError::NotYourGame {});
+    }
+    // moves can share a block, but can't come before the previous one
+    if let Some((last_height, _)) = self.moves.last() {
+      if chess_move.0 < *last_height {
+        return Err(ContractError::InvalidMoveTiming {});
+      }
+    }
     // check if game timed out
     if self.check_timeout(chess_move.0)?.is_some() {
       // check_timeout updates and returns status
       return Ok(&self.status);
     }
+    // takebacks can be made on either player's turn
+    match chess_move.1 {
+      CwChessAction::RequestTakeback => return self.request_takeback(player),
+      CwChessAction::AcceptTakeback => return self.accept_takeback(player),
+      _ => {}
+    }
     let mut game = self.load_game()?;
     let player_to_move = match game.get_turn_color() {
       Color::White => &self.player1,
@@ -238,66 +956,1769 @@ impl CwChessGame {
     if player_to_move != player {
       return Err(ContractError::NotYourTurn {});
     }
-    match game.make_move(&GameAction::from(&chess_move.1)) {
-      Err(_) => Err(ContractError::InvalidMove {}),
+    if chess_move.1 == CwChessAction::ClaimDraw {
+      if !self.can_claim_draw()? {
+        return Err(ContractError::DrawClaimNotValid {});
+      }
+      self.moves.push(chess_move);
+      self.pending_takeback = None;
+      self.clocks = self.replay_clocks();
+      self.status = Some(CwChessGameOver::DrawClaimed);
+      return Ok(&self.status);
+    }
+    if chess_move.1 == CwChessAction::DeclineDraw {
+      return self.decline_draw();
+    }
+    match game.make_move(&GameAction::try_from(&chess_move.1)?) {
+      Err(_) => match &chess_move.1 {
+        CwChessAction::MakeMove(move_str) | CwChessAction::OfferDraw(move_str)
+          if is_castling_move(move_str) =>
+        {
+          Err(ContractError::IllegalCastle {})
+        }
+        CwChessAction::MakeMove(move_str) | CwChessAction::OfferDraw(move_str)
+          if is_move_into_check(&game.board, move_str) =>
+        {
+          Err(ContractError::KingInCheck {})
+        }
+        _ => Err(ContractError::InvalidMove {}),
+      },
       Ok(status) => {
+        if let CwChessAction::OfferDraw(_) = chess_move.1 {
+          match self.player_color(player) {
+            Some(CwChessColor::White) => self.last_draw_offers.0 = Some(chess_move.0),
+            Some(CwChessColor::Black) => self.last_draw_offers.1 = Some(chess_move.0),
+            None => {}
+          }
+        }
+        // a move by hand replaces the player's own premove
+        if let Some((color, _)) = &self.premove {
+          if self.player_color(player).as_ref() == Some(color) {
+            self.premove = None;
+          }
+        }
         self.moves.push(chess_move);
+        self.pending_takeback = None;
+        self.clocks = self.replay_clocks();
         self.status = status.as_ref().map(CwChessGameOver::from);
-        self.fen = game.to_fen(0, (self.moves.len() / 2) as u8).unwrap();
+        self.fen = game.to_fen(0, self.fullmove_number(self.board_moves())).unwrap();
+        let board = CwChessBoard::from_board(&game.board);
+        if self.status.is_none() && board.is_insufficient_material() {
+          self.status = Some(CwChessGameOver::InsufficientMaterial);
+        }
         Ok(&self.status)
       }
     }
   }
 
-  pub fn turn_color(&self) -> Option<CwChessColor> {
-    match self.status {
-      None => match self.moves.len() % 2 {
-        0 => Some(CwChessColor::White),
-        1 => Some(CwChessColor::Black),
-        // rust can't tell this is impossible
-        _ => None,
-      },
-      _ => None,
+  // the offer becomes a plain move, so the game goes on as if it was never made
+  // last_draw_offers still has it for the cooldown
+  fn decline_draw(&mut self) -> Result<&Option<CwChessGameOver>, ContractError> {
+    let action = match self.moves.last_mut() {
+      Some((_, action)) => action,
+      None => return Err(ContractError::NoDrawOffered {}),
+    };
+    match action {
+      CwChessAction::OfferDraw(move_str) => {
+        *action = CwChessAction::MakeMove(std::mem::take(move_str));
+        Ok(&self.status)
+      }
+      _ => Err(ContractError::NoDrawOffered {}),
     }
   }
 
-  // check whether draw was offered on previous turn
-  // return color that offered draw
-  fn draw_offered(&self) -> Option<CwChessColor> {
-    match &self.moves.last() {
-      Some((_, CwChessAction::OfferDraw(_))) => {
-        match self.turn_color() {
-          None => None,
-          // current turn means opposite color offered draw
-          Some(CwChessColor::Black) => Some(CwChessColor::White),
-          Some(CwChessColor::White) => Some(CwChessColor::Black),
+  fn request_takeback(
+    &mut self,
+    player: &Addr,
+  ) -> Result<&Option<CwChessGameOver>, ContractError> {
+    let color = match self.player_color(player) {
+      Some(color) => color,
+      None => return Err(ContractError::NotYourGame {}),
+    };
+    if self.moves.is_empty() {
+      return Err(ContractError::NoMovesToTakeBack {});
+    }
+    self.pending_takeback = Some(color);
+    Ok(&self.status)
+  }
+
+  // undo the last move, recomputing the position from the remaining moves
+  fn accept_takeback(
+    &mut self,
+    player: &Addr,
+  ) -> Result<&Option<CwChessGameOver>, ContractError> {
+    let color = match self.player_color(player) {
+      Some(color) => color,
+      None => return Err(ContractError::NotYourGame {}),
+    };
+    // only the opponent of the requester can accept
+    match &self.pending_takeback {
+      Some(requester) if requester != &color => {}
+      _ => return Err(ContractError::NoTakebackRequested {}),
+    }
+    self.moves.pop();
+    self.fen = match self.moves.len() {
+      0 => self.start_fen.clone().unwrap_or_else(|| DEFAULT_FEN.to_string()),
+      _ => {
+        let mut game = self.start_game()?;
+        for (_, action) in &self.moves {
+          if game.make_move(&GameAction::try_from(action)?).is_err() {
+            return Err(ContractError::InvalidMove {});
+          }
         }
+        game.to_fen(0, self.fullmove_number(self.board_moves())).unwrap()
       }
-      _ => None,
+    };
+    self.clocks = self.replay_clocks();
+    self.pending_takeback = None;
+    Ok(&self.status)
+  }
+
+  // get the color the player is playing as
+  pub fn player_color(&self, player: &Addr) -> Option<CwChessColor> {
+    if player == &self.player1 {
+      Some(CwChessColor::White)
+    } else if player == &self.player2 {
+      Some(CwChessColor::Black)
+    } else {
+      None
     }
   }
 
-  // get number of blocks used by each player
-  fn get_block_times(&self, current_block: u64) -> (u64, u64) {
-    // block times for (white, black)
-    let mut block_times: (u64, u64) = (0, 0);
-    // block time starts at first move
-    if self.moves.is_empty() {
-      return block_times;
+  // the other player in the game, the player themselves in a self-play game
+  pub fn opponent_of(&self, player: &Addr) -> Option<Addr> {
+    if player == &self.player1 {
+      Some(self.player2.clone())
+    } else if player == &self.player2 {
+      Some(self.player1.clone())
+    } else {
+      None
+    }
+  }
+
+  // resign regardless of whose turn it is
+  pub fn resign(
+    &mut self,
+    player: &Addr,
+  ) -> Result<&Option<CwChessGameOver>, ContractError> {
+    let color = match self.player_color(player) {
+      Some(color) => color,
+      None => return Err(ContractError::NotYourGame {}),
+    };
+    // check if game already over
+    if self.status.is_some() {
+      return Err(ContractError::GameAlreadyOver {});
+    }
+    self.status = Some(match color {
+      CwChessColor::White => CwChessGameOver::WhiteResigns,
+      CwChessColor::Black => CwChessGameOver::BlackResigns,
+    });
+    Ok(&self.status)
+  }
+
+  // the game ends with the result once both players submit the same one,
+  // a player can replace their own submission until the opponent submits
+  pub fn agree_result(
+    &mut self,
+    player: &Addr,
+    result: CwChessGameOver,
+  ) -> Result<&Option<CwChessGameOver>, ContractError> {
+    let color = match self.player_color(player) {
+      Some(color) => color,
+      None => return Err(ContractError::NotYourGame {}),
+    };
+    if self.status.is_some() {
+      return Err(ContractError::GameAlreadyOver {});
     }
-    let mut blocks: Vec<u64> = self.moves.iter().map(|m| -> u64 { m.0 }).collect();
-    // if game not over, add current block to end
-    if self.status.is_none() {
-      blocks.push(current_block);
+    // voiding is left to aborts and the owner
+    if result == CwChessGameOver::Voided {
+      return Err(ContractError::InvalidAgreedResult {});
     }
-    for i in 1..blocks.len() {
-      let move_time = blocks[i] - blocks[i - 1];
-      if i % 2 == 0 {
-        block_times.0 += move_time;
-      } else {
-        block_times.1 += move_time;
+    match &self.pending_result {
+      Some((submitter, pending)) if submitter != &color => {
+        if pending != &result {
+          return Err(ContractError::ResultMismatch {});
+        }
+        self.pending_result = None;
+        self.status = Some(result);
       }
+      _ => self.pending_result = Some((color, result)),
+    }
+    Ok(&self.status)
+  }
+
+  // either player can call the game off, unrated, until both have moved
+  pub fn abort(&mut self, player: &Addr) -> Result<&Option<CwChessGameOver>, ContractError> {
+    if self.player_color(player).is_none() {
+      return Err(ContractError::NotYourGame {});
+    }
+    if self.status.is_some() {
+      return Err(ContractError::GameAlreadyOver {});
+    }
+    if self.moves.len() >= 2 {
+      return Err(ContractError::CannotAbortStartedGame {});
+    }
+    self.void()
+  }
+
+  // end the game without a result
+  pub fn void(&mut self) -> Result<&Option<CwChessGameOver>, ContractError> {
+    // check if game already over
+    if self.status.is_some() {
+      return Err(ContractError::GameAlreadyOver {});
     }
-    block_times
+    self.status = Some(CwChessGameOver::Voided);
+    Ok(&self.status)
+  }
+
+  // claim victory when the player to move has been inactive
+  // for inactivity_blocks, the inactive player resigns
+  pub fn claim_abandonment(
+    &mut self,
+    player: &Addr,
+    current_block: u64,
+    inactivity_blocks: u64,
+  ) -> Result<&Option<CwChessGameOver>, ContractError> {
+    let color = match self.player_color(player) {
+      Some(color) => color,
+      None => return Err(ContractError::NotYourGame {}),
+    };
+    // check if game already over
+    if self.status.is_some() {
+      return Err(ContractError::GameAlreadyOver {});
+    }
+    let inactive = fen_turn_color(&self.fen);
+    if inactive == color {
+      return Err(ContractError::CannotClaimOwnTurn {});
+    }
+    let last_active = self.moves.last().map(|m| m.0).unwrap_or(self.block_start);
+    if current_block < last_active.saturating_add(inactivity_blocks) {
+      return Err(ContractError::GameNotAbandoned {});
+    }
+    self.status = Some(match inactive {
+      CwChessColor::White => CwChessGameOver::WhiteResigns,
+      CwChessColor::Black => CwChessGameOver::BlackResigns,
+    });
+    Ok(&self.status)
+  }
+
+  // current position, decoded for clients that don't parse FEN
+  pub fn get_board(&self) -> Result<CwChessBoard, ContractError> {
+    CwChessBoard::from_fen(&self.fen)
+  }
+
+  // legal destination squares for the piece on square, sorted
+  // empty if the square is empty, the piece isn't the side to move's,
+  // or the game is over
+  pub fn get_legal_moves(&self, square: &str) -> Result<Vec<String>, ContractError> {
+    let from = match Position::pgn(square) {
+      Ok(pos) if pos.is_on_board() => pos,
+      _ => return Err(ContractError::InvalidSquare {}),
+    };
+    if self.status.is_some() {
+      return Ok(vec![]);
+    }
+    let board = self.load_game()?.board;
+    let color = board.get_turn_color();
+    let piece = match board.get_piece(from) {
+      Some(piece) if piece.get_color() == color => piece,
+      _ => return Ok(vec![]),
+    };
+    let row = from.get_row();
+    let mut destinations = legal_moves(&board, color)
+      .into_iter()
+      .filter(|m| match m {
+        Move::Piece(move_from, _) | Move::Promotion(move_from, _, _) => *move_from == from,
+        Move::KingSideCastle | Move::QueenSideCastle => piece.is_king(),
+        Move::Resign => false,
+      })
+      .map(|m| match m {
+        Move::Piece(_, to) | Move::Promotion(_, to, _) => to,
+        Move::KingSideCastle => Position::new(row, 6),
+        Move::QueenSideCastle => Position::new(row, 2),
+        Move::Resign => from,
+      })
+      .collect::<Vec<Position>>();
+    destinations.sort();
+    destinations.dedup();
+    Ok(destinations.iter().map(|pos| pos.to_string()).collect())
+  }
+
+  pub fn turn_color(&self) -> Option<CwChessColor> {
+    match self.status {
+      None => Some(fen_turn_color(&self.fen)),
+      _ => None,
+    }
+  }
+
+  // side to move is in check, which a checkmate also counts as
+  pub fn in_check(&self) -> Result<bool, ContractError> {
+    match self.status {
+      Some(CwChessGameOver::WhiteCheckmates) | Some(CwChessGameOver::BlackCheckmates) => Ok(true),
+      Some(_) => Ok(false),
+      None => match parse_fen(&self.fen) {
+        Ok(board) => Ok(board.is_in_check(board.get_turn_color())),
+        Err(_) => Err(ContractError::InvalidPosition {}),
+      },
+    }
+  }
+
+  // color that made the first move
+  pub fn start_color(&self) -> CwChessColor {
+    match &self.start_fen {
+      None => CwChessColor::White,
+      Some(fen) => fen_turn_color(fen),
+    }
+  }
+
+  // check whether draw was offered on previous turn
+  // return color that offered draw
+  pub fn draw_offered(&self) -> Option<CwChessColor> {
+    match &self.moves.last() {
+      // current turn means opposite color offered draw
+      Some((_, CwChessAction::OfferDraw(_))) => self.turn_color().map(|color| color.opponent()),
+      _ => None,
+    }
+  }
+
+  // get remaining blocks (white, black) at current_block
+  // None for games without a block_limit
+  pub fn get_clocks(&self, current_block: u64) -> Option<(u64, u64)> {
+    let mut clocks = self.clocks.or_else(|| self.replay_clocks())?;
+    // clock of the player to move runs from the last move
+    if let (None, Some((height, _))) = (&self.status, self.moves.last()) {
+      let elapsed = current_block.saturating_sub(*height);
+      match self.turn_color() {
+        Some(CwChessColor::White) => clocks.0 = clocks.0.saturating_sub(elapsed),
+        Some(CwChessColor::Black) => clocks.1 = clocks.1.saturating_sub(elapsed),
+        None => {}
+      }
+    }
+    Some(clocks)
+  }
+
+  // last block the player to move can still move in
+  // None before the first move, for games without a block_limit, or once over
+  pub fn timeout_block(&self) -> Option<u64> {
+    let clocks = self.clocks.or_else(|| self.replay_clocks())?;
+    let (height, _) = self.moves.last()?;
+    match self.turn_color()? {
+      CwChessColor::White => Some(height + clocks.0),
+      CwChessColor::Black => Some(height + clocks.1),
+    }
+  }
+
+  // the player to move ran out of time as of current_block but no one declared the timeout yet
+  pub fn timeout_pending(&self, current_block: u64) -> bool {
+    self.timeout_block().is_some_and(|timeout_block| current_block > timeout_block)
+  }
+
+  // compute remaining blocks (white, black) as of the last move
+  // from the move heights, with Fischer increment
+  fn replay_clocks(&self) -> Option<(u64, u64)> {
+    let block_limit = self.block_limit?;
+    let increment = self.increment_blocks.unwrap_or_default();
+    let mut clocks = (block_limit, block_limit);
+    let white_first = self.start_color() == CwChessColor::White;
+    for i in 0..self.moves.len() {
+      // block time starts at first move
+      let move_time = match i {
+        0 => 0,
+        _ => self.moves[i].0.saturating_sub(self.moves[i - 1].0),
+      };
+      // even moves are made by the color that moved first
+      let clock = match (i % 2 == 0) == white_first {
+        true => &mut clocks.0,
+        false => &mut clocks.1,
+      };
+      *clock = clock.saturating_sub(move_time) + increment;
+    }
+    Some(clocks)
+  }
+}
+
+// FEN placement as 64 squares from a8 to h1, '.' for empty squares
+fn fen_squares(fen: &str) -> Vec<char> {
+  let placement = fen.split(' ').next().unwrap_or_default();
+  placement
+    .chars()
+    .filter(|c| *c != '/')
+    .flat_map(|c| match c.to_digit(10) {
+      Some(empty) => vec!['.'; empty as usize],
+      None => vec![c],
+    })
+    .collect()
+}
+
+// placement, turn, castling and en passant fields for comparing positions,
+// with the en passant square only kept when a pawn could capture there
+fn repetition_key(fen: &str) -> String {
+  let mut fields: Vec<&str> = fen.split(' ').take(4).collect();
+  let en_passant = fields.get(3).and_then(|square| {
+    let mut chars = square.chars();
+    Some((chars.next()? as i32 - 'a' as i32, chars.next()?.to_digit(10)? as i32))
+  });
+  if let Some((file, rank)) = en_passant {
+    let squares = fen_squares(fen);
+    // the capturing pawn stands next to the pawn that just moved
+    let (pawn, pawn_rank) = match fields[1] {
+      "w" => ('P', rank - 1),
+      _ => ('p', rank + 1),
+    };
+    let can_capture = [file - 1, file + 1].iter().any(|file| {
+      (0..8).contains(file) && squares.get(((8 - pawn_rank) * 8 + file) as usize) == Some(&pawn)
+    });
+    if !can_capture {
+      fields[3] = "-";
+    }
+  }
+  fields.join(" ")
+}
+
+// a move between two positions captured a piece or moved a pawn
+fn is_irreversible(before: &str, after: &str) -> bool {
+  let (before, after) = (fen_squares(before), fen_squares(after));
+  let pieces = |squares: &[char]| squares.iter().filter(|c| **c != '.').count();
+  let pawns = |squares: &[char]| -> Vec<(usize, char)> {
+    squares
+      .iter()
+      .enumerate()
+      .filter(|(_, c)| **c == 'P' || **c == 'p')
+      .map(|(i, c)| (i, *c))
+      .collect()
+  };
+  pieces(&after) < pieces(&before) || pawns(&after) != pawns(&before)
+}
+
+// active color field of a FEN
+fn fen_turn_color(fen: &str) -> CwChessColor {
+  fen
+    .split_ascii_whitespace()
+    .nth(1)
+    .and_then(|field| field.chars().next())
+    .and_then(CwChessColor::from_fen_char)
+    .unwrap_or(CwChessColor::White)
+}
+
+/// standard starting position with color giving the odds piece
+pub fn odds_fen(odds: &OddsType, color: &CwChessColor) -> &'static str {
+  match (odds, color) {
+    (OddsType::Pawn, CwChessColor::White) => "rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR w KQkq - 0 1",
+    (OddsType::Pawn, CwChessColor::Black) => "rnbqkbnr/ppppp1pp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    (OddsType::Knight, CwChessColor::White) => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1",
+    (OddsType::Knight, CwChessColor::Black) => "r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    (OddsType::Rook, CwChessColor::White) => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1",
+    (OddsType::Rook, CwChessColor::Black) => "1nbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1",
+    (OddsType::Queen, CwChessColor::White) => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1",
+    (OddsType::Queen, CwChessColor::Black) => "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+  }
+}
+
+// tag pairs, SAN moves and result of a PGN
+pub type PgnParts = (Vec<(String, String)>, Vec<String>, String);
+
+/// split a PGN into its tag pairs, SAN moves and result.
+///
+/// comments, variations, NAGs and move numbers are skipped, and check, mate
+/// and annotation suffixes are removed, leaving moves as the engine parses them.
+pub fn parse_pgn(pgn: &str) -> Result<PgnParts, ContractError> {
+  let mut tags = vec![];
+  let mut movetext = String::new();
+  for line in pgn.lines().map(str::trim) {
+    if movetext.trim().is_empty() && line.starts_with('[') {
+      let (tag, value) = line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+        .and_then(|pair| pair.split_once(' '))
+        .ok_or(ContractError::InvalidPgn {})?;
+      let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or(ContractError::InvalidPgn {})?;
+      tags.push((tag.to_string(), value.to_string()));
+    } else if !line.starts_with('%') {
+      movetext.push_str(line);
+      movetext.push('\n');
+    }
+  }
+
+  // drop {comments}, ; comments to the end of the line and (variations)
+  let mut cleaned = String::new();
+  let mut in_comment = false;
+  let mut in_line_comment = false;
+  let mut variation_depth = 0;
+  for c in movetext.chars() {
+    match c {
+      '\n' if in_line_comment => in_line_comment = false,
+      _ if in_line_comment => {}
+      '}' if in_comment => in_comment = false,
+      _ if in_comment => {}
+      '{' => in_comment = true,
+      ';' => in_line_comment = true,
+      '(' => variation_depth += 1,
+      ')' if variation_depth == 0 => return Err(ContractError::InvalidPgn {}),
+      ')' => variation_depth -= 1,
+      _ if variation_depth > 0 => {}
+      _ => cleaned.push(c),
+    }
+    // keep tokens on either side of a comment apart
+    if !in_comment && !in_line_comment && variation_depth == 0 && "}\n)".contains(c) {
+      cleaned.push(' ');
+    }
+  }
+  if in_comment || variation_depth > 0 {
+    return Err(ContractError::InvalidPgn {});
+  }
+
+  let mut san_moves = vec![];
+  let mut result = None;
+  for token in cleaned.split_whitespace() {
+    if result.is_some() {
+      return Err(ContractError::InvalidPgn {});
+    }
+    if matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+      result = Some(token.to_string());
+      continue;
+    }
+    if token.starts_with('$') {
+      continue;
+    }
+    // move numbers, as 12. or 12... and possibly joined to the move
+    let token = if token.starts_with(|c: char| c.is_ascii_digit()) && token.contains('.') {
+      token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')
+    } else {
+      token
+    };
+    let san = token.trim_end_matches(['+', '#', '!', '?']);
+    if !san.is_empty() {
+      san_moves.push(san.to_string());
+    }
+  }
+  match result {
+    Some(result) => Ok((tags, san_moves, result)),
+    None => Err(ContractError::InvalidPgn {}),
+  }
+}
+
+/// validate a FEN for use as a starting position.
+///
+/// checks that the FEN is well formed, that each side has exactly one king
+/// and a possible set of pieces, that castling and en passant availability
+/// match the placement, and that the side not to move is not in check.
+/// returns a description of the first problem found.
+pub fn validate_fen(fen: &str) -> Result<(), String> {
+  let parts = fen.split_ascii_whitespace().collect::<Vec<_>>();
+  if parts.len() != 6 {
+    return Err("fen must have six fields".to_string());
+  }
+
+  // placement, from rank 8 down to rank 1
+  let ranks = parts[0].split('/').collect::<Vec<_>>();
+  if ranks.len() != 8 {
+    return Err("placement must have eight ranks".to_string());
+  }
+  let mut squares = [[None; 8]; 8];
+  for (row, rank) in ranks.iter().enumerate() {
+    let mut col = 0;
+    for c in rank.chars() {
+      match c {
+        '1'..='8' => {
+          col += c.to_digit(10).unwrap_or_default() as usize;
+        }
+        'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => {
+          if col < 8 {
+            squares[row][col] = Some(c);
+          }
+          col += 1;
+        }
+        _ => {
+          return Err(format!("unexpected character `{}`", c));
+        }
+      }
+      if col > 8 {
+        return Err(format!("rank {} has too many squares", 8 - row));
+      }
+    }
+    if col != 8 {
+      return Err(format!("rank {} has too few squares", 8 - row));
+    }
+  }
+  let piece_at = |square: &str| -> Option<char> {
+    let mut chars = square.chars();
+    let col = chars.next()? as usize - 'a' as usize;
+    let row = 8 - chars.next()?.to_digit(10)? as usize;
+    squares[row][col]
+  };
+
+  // piece counts
+  for color in ["white", "black"] {
+    let count = |piece: char| -> usize {
+      let piece = match color {
+        "white" => piece.to_ascii_uppercase(),
+        _ => piece,
+      };
+      squares.iter().flatten().filter(|s| **s == Some(piece)).count()
+    };
+    if count('k') != 1 {
+      return Err(format!("{} must have exactly one king", color));
+    }
+    let pawns = count('p');
+    if pawns > 8 {
+      return Err(format!("{} has too many pawns", color));
+    }
+    // pieces beyond the starting set must come from promoted pawns
+    let promoted = count('q').saturating_sub(1)
+      + count('r').saturating_sub(2)
+      + count('b').saturating_sub(2)
+      + count('n').saturating_sub(2);
+    if promoted > 8 - pawns {
+      return Err(format!("{} has too many pieces", color));
+    }
+  }
+  if squares[0]
+    .iter()
+    .chain(squares[7].iter())
+    .any(|s| *s == Some('p') || *s == Some('P'))
+  {
+    return Err("pawns cannot be on the first or last rank".to_string());
+  }
+
+  // active color
+  let white_to_move = match parts[1] {
+    "w" => true,
+    "b" => false,
+    _ => {
+      return Err("invalid active color".to_string());
+    }
+  };
+
+  // castling, as KQkq or as rook files for Chess960 (Shredder-FEN)
+  if parts[2] != "-" {
+    let mut seen = vec![];
+    for c in parts[2].chars() {
+      let (king, rook, row) = match c {
+        'K' | 'Q' | 'A'..='H' => ('K', 'R', 7),
+        'k' | 'q' | 'a'..='h' => ('k', 'r', 0),
+        _ => {
+          return Err("invalid castling availability".to_string());
+        }
+      };
+      let home = squares[row];
+      let king_col = home.iter().position(|s| *s == Some(king));
+      let mut rook_cols = (0..8).filter(|col| home[*col] == Some(rook));
+      let rook_col = match (c.to_ascii_lowercase(), king_col) {
+        ('k', Some(king_col)) => rook_cols.rev().find(|col| *col > king_col),
+        ('q', Some(king_col)) => rook_cols.find(|col| *col < king_col),
+        (file, Some(_)) => rook_cols.find(|col| *col == file as usize - 'a' as usize),
+        (_, None) => None,
+      };
+      let side = match (rook_col, king_col) {
+        (Some(rook_col), Some(king_col)) => (king, rook_col > king_col),
+        _ => {
+          return Err(format!("castling `{}` not possible from position", c));
+        }
+      };
+      if seen.contains(&side) {
+        return Err("duplicate castling availability".to_string());
+      }
+      seen.push(side);
+    }
+  }
+
+  // en passant
+  if parts[3] != "-" {
+    let (target_rank, pawn_rank, from_rank, pawn) = match white_to_move {
+      true => ('6', '5', '7', 'p'),
+      false => ('3', '4', '2', 'P'),
+    };
+    let mut chars = parts[3].chars();
+    let file = chars.next().unwrap_or(' ');
+    if !('a'..='h').contains(&file) || chars.next() != Some(target_rank) || chars.next().is_some() {
+      return Err("invalid en passant target".to_string());
+    }
+    if piece_at(&format!("{}{}", file, pawn_rank)) != Some(pawn)
+      || piece_at(&format!("{}{}", file, target_rank)).is_some()
+      || piece_at(&format!("{}{}", file, from_rank)).is_some()
+    {
+      return Err("en passant target not possible from position".to_string());
+    }
+  }
+
+  // move counters
+  if parts[4].parse::<u32>().is_err() {
+    return Err("invalid halfmove clock".to_string());
+  }
+  match parts[5].parse::<u32>() {
+    Ok(n) if n > 0 => {}
+    _ => {
+      return Err("invalid fullmove number".to_string());
+    }
+  }
+
+  // side that just moved cannot be in check
+  let board = parse_fen(fen)?;
+  if board.is_in_check(!board.get_turn_color()) {
+    return Err("side not to move is in check".to_string());
+  }
+
+  Ok(())
+}
+
+// seed for the colors and any chess960 start, the first 8 bytes of
+// sha256(id, block_start, player1, player2), so anyone can check it
+pub fn game_seed(id: u64, block_start: u64, player1: &Addr, player2: &Addr) -> u64 {
+  let mut hasher = Sha256::new();
+  hasher.update(id.to_be_bytes());
+  hasher.update(block_start.to_be_bytes());
+  // length prefixed, so no two address pairs hash the same bytes
+  for player in [player1, player2] {
+    hasher.update((player.as_str().len() as u64).to_be_bytes());
+    hasher.update(player.as_bytes());
+  }
+  let hash = hasher.finalize();
+  let mut seed = [0; 8];
+  seed.copy_from_slice(&hash[..8]);
+  u64::from_be_bytes(seed)
+}
+
+/// generate the starting FEN of Chess960 position `n` (mod 960).
+///
+/// uses the standard (Scharnagl) numbering, so bishops always stand on
+/// opposite colors and the king between the rooks. position 518 is the
+/// regular starting position.
+pub fn chess960_fen(n: u64) -> String {
+  let mut n = (n % 960) as usize;
+  let mut rank = [None; 8];
+  // light and dark squared bishops
+  rank[n % 4 * 2 + 1] = Some('b');
+  n /= 4;
+  rank[n % 4 * 2] = Some('b');
+  n /= 4;
+  // queen and knights go on the nth empty squares
+  let mut place = |piece: char, nth: usize| {
+    if let Some(col) = (0..8).filter(|col| rank[*col].is_none()).nth(nth) {
+      rank[col] = Some(piece);
+    }
+  };
+  place('q', n % 6);
+  n /= 6;
+  let (knight1, knight2) = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)][n];
+  // place the second knight first so the first knight's index is unchanged
+  place('n', knight2);
+  place('n', knight1);
+  // rook, king, rook on the remaining squares
+  place('r', 0);
+  place('k', 0);
+  place('r', 0);
+
+  let black = rank.iter().map(|s| s.unwrap_or('r')).collect::<String>();
+  format!(
+    "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+    black,
+    black.to_ascii_uppercase()
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // a game between "white" and "black" from fen
+  pub(super) fn test_game(fen: &str) -> CwChessGame {
+    CwChessGame {
+      block_limit: None,
+      block_start: 0,
+      clocks: None,
+      fen: fen.to_string(),
+      game_id: 1,
+      increment_blocks: None,
+      last_draw_offers: (None, None),
+      moves: vec![],
+      odds: None,
+      pending_result: None,
+      pending_takeback: None,
+      player1: Addr::unchecked("white"),
+      player2: Addr::unchecked("black"),
+      premove: None,
+      seed: None,
+      start_fen: (fen != DEFAULT_FEN).then(|| fen.to_string()),
+      status: None,
+    }
+  }
+
+  // play moves, alternating from the side to move in fen
+  pub(super) fn play(fen: &str, moves: &[&str]) -> Result<CwChessGame, ContractError> {
+    let mut game = test_game(fen);
+    for (i, move_str) in moves.iter().enumerate() {
+      let player = match fen_turn_color(&game.fen) {
+        CwChessColor::White => game.player1.clone(),
+        CwChessColor::Black => game.player2.clone(),
+      };
+      game.make_move(&player, (i as u64 + 1, CwChessAction::from(*move_str)))?;
+    }
+    Ok(game)
+  }
+
+  fn assert_king_in_check(fen: &str, moves: &[&str]) {
+    match play(fen, moves) {
+      Err(ContractError::KingInCheck {}) => {}
+      result => panic!("expected king in check for {:?}: {:?}", moves, result),
+    }
+  }
+
+  #[test]
+  fn test_color_helpers() {
+    assert_eq!(CwChessColor::White.opponent(), CwChessColor::Black);
+    assert_eq!(CwChessColor::Black.opponent(), CwChessColor::White);
+    assert_eq!(CwChessColor::from_fen_char('w'), Some(CwChessColor::White));
+    assert_eq!(CwChessColor::from_fen_char('b'), Some(CwChessColor::Black));
+    assert_eq!(CwChessColor::from_fen_char('W'), None);
+    assert_eq!(fen_turn_color("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"), CwChessColor::Black);
+    assert_eq!(fen_turn_color(DEFAULT_FEN), CwChessColor::White);
+  }
+
+  #[test]
+  fn test_validate_fen_legal() {
+    for fen in [
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+      "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1",
+      "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+      "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+      "4k3/8/8/8/8/8/8/QQ2K3 w - - 0 40",
+    ] {
+      assert_eq!(validate_fen(fen), Ok(()), "{}", fen);
+    }
+  }
+
+  #[test]
+  fn test_validate_fen_malformed() {
+    for fen in [
+      "",
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+      "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+      "rnbqkbnr/pppppppp/7/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+      "rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1",
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9 0 1",
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - a 1",
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0",
+    ] {
+      assert!(validate_fen(fen).is_err(), "{}", fen);
+    }
+  }
+
+  #[test]
+  fn test_validate_fen_illegal() {
+    for (fen, reason) in [
+      ("8/8/8/8/8/8/8/4K3 w - - 0 1", "black must have exactly one king"),
+      ("4k3/8/8/8/8/8/8/3KK3 w - - 0 1", "white must have exactly one king"),
+      ("4k3/8/8/8/8/P7/PPPPPPPP/4K3 w - - 0 1", "white has too many pawns"),
+      ("4k3/8/8/8/8/8/PPPPPPPP/QQ2K3 w - - 0 1", "white has too many pieces"),
+      ("P3k3/8/8/8/8/8/8/4K3 w - - 0 1", "pawns cannot be on the first or last rank"),
+      ("4k3/8/8/8/8/8/8/4K3 w K - 0 1", "castling `K` not possible from position"),
+      ("4k3/8/8/8/8/8/8/4K3 w - d6 0 1", "en passant target not possible from position"),
+      ("4k3/8/8/8/8/8/4Q3/4K3 w - - 0 1", "side not to move is in check"),
+    ] {
+      assert_eq!(validate_fen(fen), Err(reason.to_string()), "{}", fen);
+    }
+  }
+
+  #[test]
+  fn test_chess960_fen() {
+    assert_eq!(
+      chess960_fen(518),
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+    let mut seen = vec![];
+    for n in 0..960 {
+      let fen = chess960_fen(n);
+      assert_eq!(validate_fen(&fen), Ok(()), "{}", fen);
+      let back_rank = fen.split('/').next().unwrap().chars().collect::<Vec<_>>();
+      let cols = |piece: char| -> Vec<usize> {
+        (0..8).filter(|col| back_rank[*col] == piece).collect()
+      };
+      // bishops on opposite colors
+      let bishops = cols('b');
+      assert_eq!(bishops.len(), 2, "{}", fen);
+      assert_ne!(bishops[0] % 2, bishops[1] % 2, "{}", fen);
+      // king between rooks
+      let rooks = cols('r');
+      let king = cols('k');
+      assert!(rooks[0] < king[0] && king[0] < rooks[1], "{}", fen);
+      assert!(!seen.contains(&fen), "{}", fen);
+      seen.push(fen);
+    }
+    assert_eq!(chess960_fen(960), chess960_fen(0));
+  }
+
+  #[test]
+  fn test_chess960_castling() {
+    let fen = "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1RK3R1 w GBgb - 0 1";
+    assert_eq!(validate_fen(fen), Ok(()));
+    let mut game = test_game(fen);
+    // king c1 to g1, rook g1 to f1
+    game
+      .make_move(&Addr::unchecked("white"), (1, CwChessAction::from("0-0")))
+      .unwrap();
+    assert_eq!(game.fen, "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 b gb - 0 1");
+    // king e8 to c8, rook b8 to d8
+    game
+      .make_move(&Addr::unchecked("black"), (2, CwChessAction::from("0-0-0")))
+      .unwrap();
+    assert_eq!(game.fen, "2kr2r1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 w - - 0 2");
+  }
+
+  #[test]
+  fn test_to_pgn_in_progress() {
+    let mut game = test_game("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1");
+    game.game_id = 2;
+    for (i, (player, action)) in [("black", "Kd7"), ("white", "e4"), ("black", "Kd6")].into_iter().enumerate() {
+      game
+        .make_move(&Addr::unchecked(player), (i as u64 + 1, CwChessAction::from(action)))
+        .unwrap();
+    }
+    assert_eq!(
+      game.to_pgn("site").unwrap(),
+      [
+        "[Event \"cosmos-chess game 2\"]",
+        "[Site \"site\"]",
+        "[Date \"????.??.??\"]",
+        "[Round \"-\"]",
+        "[White \"white\"]",
+        "[Black \"black\"]",
+        "[Result \"*\"]",
+        "[SetUp \"1\"]",
+        "[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]",
+        "",
+        "1... Kd7 2. e4 Kd6 *",
+        "",
+      ]
+      .join("\n")
+    );
+  }
+
+  #[test]
+  fn test_takeback() {
+    let white = Addr::unchecked("white");
+    let black = Addr::unchecked("black");
+    let mut game = test_game(DEFAULT_FEN);
+    // nothing to take back yet
+    match game.make_move(&white, (1, CwChessAction::RequestTakeback)).unwrap_err() {
+      ContractError::NoMovesToTakeBack {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    game.make_move(&white, (1, CwChessAction::from("e4"))).unwrap();
+    let fen = game.fen.clone();
+    game.make_move(&black, (2, CwChessAction::from("e5"))).unwrap();
+
+    // black asks to undo e5, and cannot accept their own request
+    game.make_move(&black, (3, CwChessAction::RequestTakeback)).unwrap();
+    assert_eq!(game.pending_takeback, Some(CwChessColor::Black));
+    match game.make_move(&black, (3, CwChessAction::AcceptTakeback)).unwrap_err() {
+      ContractError::NoTakebackRequested {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    game.make_move(&white, (4, CwChessAction::AcceptTakeback)).unwrap();
+    assert_eq!(game.fen, fen);
+    assert_eq!(game.moves.len(), 1);
+    assert_eq!(game.pending_takeback, None);
+    assert_eq!(game.turn_color(), Some(CwChessColor::Black));
+
+    // a new move clears a pending request
+    game.make_move(&white, (5, CwChessAction::RequestTakeback)).unwrap();
+    game.make_move(&black, (6, CwChessAction::from("c5"))).unwrap();
+    assert_eq!(game.pending_takeback, None);
+    match game.make_move(&white, (7, CwChessAction::AcceptTakeback)).unwrap_err() {
+      ContractError::NoTakebackRequested {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // back to the starting position
+    game.make_move(&white, (7, CwChessAction::RequestTakeback)).unwrap();
+    game.make_move(&black, (8, CwChessAction::AcceptTakeback)).unwrap();
+    game.make_move(&white, (9, CwChessAction::RequestTakeback)).unwrap();
+    game.make_move(&black, (10, CwChessAction::AcceptTakeback)).unwrap();
+    assert_eq!(game.fen, DEFAULT_FEN);
+    assert!(game.moves.is_empty());
+
+    // no takebacks after the game is over
+    game.make_move(&white, (11, CwChessAction::from("e4"))).unwrap();
+    game.resign(&black).unwrap();
+    match game.make_move(&black, (12, CwChessAction::RequestTakeback)).unwrap_err() {
+      ContractError::GameAlreadyOver {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_insufficient_material() {
+    let insufficient = |placement: &str| {
+      CwChessBoard::from_fen(&format!("{} w - - 0 1", placement))
+        .unwrap()
+        .is_insufficient_material()
+    };
+    // K vs K, K+B vs K, K+N vs K
+    assert!(insufficient("k7/8/8/8/8/8/8/K7"));
+    assert!(insufficient("k7/8/8/8/8/8/8/KB6"));
+    assert!(insufficient("k7/8/8/8/8/8/8/KN6"));
+    assert!(insufficient("kn6/8/8/8/8/8/8/K7"));
+    // bishops all on the same color, c1 and f8 are both dark
+    assert!(insufficient("k4b2/8/8/8/8/8/8/K1B5"));
+    assert!(insufficient("k4b2/8/8/8/8/8/8/K1B1B3"));
+
+    // K+B+B on both colors can mate
+    assert!(!insufficient("k7/8/8/8/8/8/8/KBB5"));
+    // opposite colored bishops, b1 is light
+    assert!(!insufficient("k4b2/8/8/8/8/8/8/KB6"));
+    // knights can be mated into a corner
+    assert!(!insufficient("kn6/8/8/8/8/8/8/KN6"));
+    assert!(!insufficient("k7/8/8/8/8/8/8/KNN5"));
+    assert!(!insufficient("kb6/8/8/8/8/8/8/KN6"));
+    // any pawn, rook or queen
+    assert!(!insufficient("k7/8/8/8/8/8/P7/K7"));
+    assert!(!insufficient("k7/8/8/8/8/8/8/KR6"));
+    assert!(!insufficient("k7/8/8/8/8/8/8/Kq6"));
+
+    // capturing the last piece ends the game
+    let fen = "7k/8/8/8/8/8/1n6/K7 w - - 0 1";
+    let mut game = test_game(fen);
+    game.make_move(&Addr::unchecked("white"), (1, CwChessAction::from("Kxb2"))).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::InsufficientMaterial));
+    assert_eq!(game.pgn_result(), "1/2-1/2");
+  }
+
+  #[test]
+  fn test_odds_fen() {
+    let standard = CwChessBoard::from_fen(DEFAULT_FEN).unwrap();
+    for (odds, piece_type, white_square, black_square) in [
+      (OddsType::Pawn, CwChessPieceType::Pawn, 13, 53),
+      (OddsType::Knight, CwChessPieceType::Knight, 1, 57),
+      (OddsType::Rook, CwChessPieceType::Rook, 0, 56),
+      (OddsType::Queen, CwChessPieceType::Queen, 3, 59),
+    ] {
+      for (color, square) in [(CwChessColor::White, white_square), (CwChessColor::Black, black_square)] {
+        let fen = odds_fen(&odds, &color);
+        assert_eq!(validate_fen(fen), Ok(()), "{}", fen);
+        let board = CwChessBoard::from_fen(fen).unwrap();
+        assert_eq!(board.turn, CwChessColor::White);
+        // only the odds piece is missing
+        for i in 0..64 {
+          match i == square {
+            true => {
+              let piece = standard.squares[i].as_ref().unwrap();
+              assert_eq!((&piece.color, &piece.piece_type), (&color, &piece_type), "{}", fen);
+              assert_eq!(board.squares[i], None, "{}", fen);
+            }
+            false => assert_eq!(board.squares[i], standard.squares[i], "{}", fen),
+          }
+        }
+        // rook odds gives up castling on that side
+        let queenside = match color {
+          CwChessColor::White => board.castling.white_queenside,
+          CwChessColor::Black => board.castling.black_queenside,
+        };
+        assert_eq!(queenside, odds != OddsType::Rook, "{}", fen);
+      }
+    }
+  }
+
+  #[test]
+  fn test_board_from_fen() {
+    // rebuild the placement field of a FEN from the decoded squares
+    let placement = |board: &CwChessBoard| {
+      (0..8)
+        .rev()
+        .map(|rank| {
+          let mut row = String::new();
+          let mut empty = 0;
+          for square in &board.squares[rank * 8..rank * 8 + 8] {
+            match square {
+              None => empty += 1,
+              Some(piece) => {
+                if empty > 0 {
+                  row.push_str(&empty.to_string());
+                  empty = 0;
+                }
+                let c = match piece.piece_type {
+                  CwChessPieceType::King => 'k',
+                  CwChessPieceType::Queen => 'q',
+                  CwChessPieceType::Rook => 'r',
+                  CwChessPieceType::Bishop => 'b',
+                  CwChessPieceType::Knight => 'n',
+                  CwChessPieceType::Pawn => 'p',
+                };
+                row.push(match piece.color {
+                  CwChessColor::White => c.to_ascii_uppercase(),
+                  CwChessColor::Black => c,
+                });
+              }
+            }
+          }
+          if empty > 0 {
+            row.push_str(&empty.to_string());
+          }
+          row
+        })
+        .collect::<Vec<String>>()
+        .join("/")
+    };
+
+    let board = CwChessBoard::from_fen(DEFAULT_FEN).unwrap();
+    assert_eq!(board.squares.len(), 64);
+    assert_eq!(
+      board.squares[4],
+      Some(CwChessPiece {
+        color: CwChessColor::White,
+        piece_type: CwChessPieceType::King,
+      })
+    );
+    assert_eq!(
+      board.squares[59],
+      Some(CwChessPiece {
+        color: CwChessColor::Black,
+        piece_type: CwChessPieceType::Queen,
+      })
+    );
+    assert_eq!(placement(&board), DEFAULT_FEN.split(' ').next().unwrap());
+    assert_eq!(
+      board.castling,
+      CwChessCastling {
+        black_kingside: true,
+        black_queenside: true,
+        white_kingside: true,
+        white_queenside: true,
+      }
+    );
+    assert_eq!(board.en_passant, None);
+    assert_eq!(board.turn, CwChessColor::White);
+
+    let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBN1 b Qk e3 0 1";
+    let board = CwChessBoard::from_fen(fen).unwrap();
+    assert_eq!(placement(&board), fen.split(' ').next().unwrap());
+    assert_eq!(
+      board.castling,
+      CwChessCastling {
+        black_kingside: true,
+        black_queenside: false,
+        white_kingside: false,
+        white_queenside: true,
+      }
+    );
+    assert_eq!(board.en_passant, Some("e3".to_string()));
+    assert_eq!(board.turn, CwChessColor::Black);
+
+    match CwChessBoard::from_fen("not a fen").unwrap_err() {
+      ContractError::InvalidPosition {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_get_opening() {
+    let play = |moves: &[&str]| {
+      let mut game = test_game(DEFAULT_FEN);
+      for (i, move_str) in moves.iter().enumerate() {
+        let player = Addr::unchecked(if i % 2 == 0 { "white" } else { "black" });
+        game
+          .make_move(&player, (i as u64 + 1, CwChessAction::from(*move_str)))
+          .unwrap();
+      }
+      game
+    };
+
+    // every table entry is playable and classifies as itself
+    for (eco, name, moves) in OPENINGS {
+      let game = play(&moves.split(' ').collect::<Vec<&str>>());
+      assert_eq!(game.get_opening().unwrap(), Some((*eco, *name)), "{}", moves);
+    }
+
+    // moves past the table keep the longest match
+    let game = play(&["e4", "c5", "Nf3", "d6", "d4", "cxd4", "Nxd4", "Nf6", "Nc3", "a6", "Be3"]);
+    assert_eq!(
+      game.get_opening().unwrap(),
+      Some(("B90", "Sicilian Defense: Najdorf Variation"))
+    );
+
+    // no match
+    assert_eq!(play(&["a3"]).get_opening().unwrap(), None);
+    assert_eq!(play(&[]).get_opening().unwrap(), None);
+  }
+
+  #[test]
+  fn test_under_promotion() {
+    let white = Addr::unchecked("white");
+
+    // e8 is squares[60], black's h pawn keeps minor promotions from
+    // ending the game on insufficient material
+    let fen = "k7/4P2p/8/8/8/8/8/K7 w - - 0 1";
+    for (move_str, piece_type) in [
+      ("e8=Q", CwChessPieceType::Queen),
+      ("e8=R", CwChessPieceType::Rook),
+      ("e8=B", CwChessPieceType::Bishop),
+      ("e8=N", CwChessPieceType::Knight),
+      ("e8N", CwChessPieceType::Knight),
+    ] {
+      let mut game = test_game(fen);
+      game.make_move(&white, (1, CwChessAction::from(move_str))).unwrap();
+      assert_eq!(
+        game.get_board().unwrap().squares[60],
+        Some(CwChessPiece {
+          color: CwChessColor::White,
+          piece_type,
+        }),
+        "{}",
+        move_str
+      );
+    }
+
+    // kings and pawns are not promotion pieces
+    for move_str in ["e8=K", "e8=P", "e8K", "e8="] {
+      let mut game = test_game(fen);
+      match game.make_move(&white, (1, CwChessAction::from(move_str))).unwrap_err() {
+        ContractError::InvalidMove {} => {}
+        e => panic!("unexpected error for {}: {:?}", move_str, e),
+      }
+    }
+
+    // knight promotion mates the boxed in king on h7
+    let mut game = test_game("6bn/5Ppk/6pp/8/8/8/8/K7 w - - 0 1");
+    game.make_move(&white, (1, CwChessAction::from("f8=N"))).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteCheckmates));
+    assert_eq!(game.get_san_moves().unwrap()[0].2, "f8=N#");
+  }
+
+  #[test]
+  fn test_en_passant() {
+    use crate::engine::Move;
+
+    let white = Addr::unchecked("white");
+    let black = Addr::unchecked("black");
+    let mut game = test_game(DEFAULT_FEN);
+    for (player, move_str) in [(&white, "e4"), (&black, "a6"), (&white, "e5"), (&black, "d5")] {
+      game.make_move(player, (1, CwChessAction::from(move_str))).unwrap();
+    }
+    // double step sets the target square behind the pawn
+    assert_eq!(game.fen, "rnbqkbnr/1pp1pppp/p7/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3");
+    // en passant only allows the capture, not other moves by the pawn
+    let board = game.load_game().unwrap().board;
+    let pos = |s: &str| Position::pgn(s).unwrap();
+    assert!(board.is_legal_move(Move::Piece(pos("e5"), pos("d6")), Color::White));
+    assert!(!board.is_legal_move(Move::Piece(pos("e5"), pos("e7")), Color::White));
+    // pawn lands on d6 and the d5 pawn is removed
+    game.make_move(&white, (2, CwChessAction::from("exd6"))).unwrap();
+    assert_eq!(game.fen, "rnbqkbnr/1pp1pppp/p2P4/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3");
+    assert_eq!(game.get_san_moves().unwrap()[4].2, "exd6");
+
+    // target is cleared after the next move
+    let mut game = test_game(DEFAULT_FEN);
+    for (player, move_str) in [
+      (&white, "e4"),
+      (&black, "a6"),
+      (&white, "e5"),
+      (&black, "d5"),
+      (&white, "Nf3"),
+      (&black, "h6"),
+    ] {
+      game.make_move(player, (1, CwChessAction::from(move_str))).unwrap();
+    }
+    assert_eq!(game.fen, "rnbqkbnr/1pp1ppp1/p6p/3pP3/8/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 4");
+    match game.make_move(&white, (2, CwChessAction::from("exd6"))).unwrap_err() {
+      ContractError::InvalidMove {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_get_legal_moves() {
+    let game = test_game(DEFAULT_FEN);
+    assert_eq!(game.get_legal_moves("e2").unwrap(), vec!["e3", "e4"]);
+    assert_eq!(game.get_legal_moves("g1").unwrap(), vec!["f3", "h3"]);
+    // empty square, wrong color, piece with no moves
+    assert!(game.get_legal_moves("e4").unwrap().is_empty());
+    assert!(game.get_legal_moves("e7").unwrap().is_empty());
+    assert!(game.get_legal_moves("a1").unwrap().is_empty());
+    match game.get_legal_moves("z9").unwrap_err() {
+      ContractError::InvalidSquare {} => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // pinned pieces can only move along the pin
+    let game = test_game("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1");
+    assert!(game.get_legal_moves("e2").unwrap().is_empty());
+    let game = test_game("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1");
+    assert_eq!(game.get_legal_moves("e2").unwrap(), vec!["e3", "e4", "e5", "e6", "e7"]);
+
+    // castling and promotion
+    let game = test_game("r3k2r/4P3/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert_eq!(game.get_legal_moves("e1").unwrap(), vec!["c1", "d1", "f1", "g1", "d2", "e2", "f2"]);
+    // pawn blocked by the king
+    assert_eq!(game.get_legal_moves("e7").unwrap(), Vec::<String>::new());
+    let game = test_game("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    // promotion squares are listed once, whatever the promotion piece
+    assert_eq!(game.get_legal_moves("b7").unwrap(), vec!["a8", "b8"]);
+  }
+
+  #[test]
+  fn test_claim_draw() {
+    // play moves, then claim with the side to move
+    let claim = |fen: &str, moves: &[&str]| {
+      let mut game = test_game(fen);
+      let actions = moves.iter().map(|move_str| CwChessAction::from(*move_str));
+      for (i, action) in actions.chain([CwChessAction::ClaimDraw]).enumerate() {
+        let player = match fen_turn_color(&game.fen) {
+          CwChessColor::White => game.player1.clone(),
+          CwChessColor::Black => game.player2.clone(),
+        };
+        game.make_move(&player, (i as u64 + 1, action))?;
+      }
+      Ok::<_, ContractError>(game.status)
+    };
+    let shuffle = ["Nf3", "Nf6", "Ng1", "Ng8"];
+
+    // the start position occurs a third time after two knight shuffles
+    assert!(matches!(claim(DEFAULT_FEN, &shuffle), Err(ContractError::DrawClaimNotValid {})));
+    assert_eq!(claim(DEFAULT_FEN, &shuffle.repeat(2)).unwrap(), Some(CwChessGameOver::DrawClaimed));
+    // a pawn move in between starts the count again
+    let moves = [&shuffle[..], &["e4", "e5"], &shuffle[..]].concat();
+    assert!(matches!(claim(DEFAULT_FEN, &moves), Err(ContractError::DrawClaimNotValid {})));
+    let moves = [&moves[..], &shuffle[..]].concat();
+    assert_eq!(claim(DEFAULT_FEN, &moves).unwrap(), Some(CwChessGameOver::DrawClaimed));
+
+    // fifty moves each, counting the start position's halfmove clock
+    let fen = "7k/8/8/8/8/8/8/K6R w - - 98 60";
+    assert!(matches!(claim(fen, &["Rh2"]), Err(ContractError::DrawClaimNotValid {})));
+    assert_eq!(claim(fen, &["Rh2", "Kg8"]).unwrap(), Some(CwChessGameOver::DrawClaimed));
+    // a capture resets it
+    let fen = "7k/8/8/8/8/8/7r/K6R w - - 98 60";
+    assert!(matches!(claim(fen, &["Rxh2", "Kg8"]), Err(ContractError::DrawClaimNotValid {})));
+  }
+
+  #[test]
+  fn test_premove_cleared_by_own_move() {
+    let mut game = test_game(DEFAULT_FEN);
+    let (white, black) = (game.player1.clone(), game.player2.clone());
+    game.set_premove(&black, "e5".to_string()).unwrap();
+    // without play_premove the premove is still queued on black's turn
+    game.make_move(&white, (1, CwChessAction::from("d4"))).unwrap();
+    assert!(game.premove.is_some());
+    // moving by hand drops it, so it can't be played later
+    game.make_move(&black, (2, CwChessAction::from("d5"))).unwrap();
+    assert_eq!(game.premove, None);
+    game.make_move(&white, (3, CwChessAction::from("c4"))).unwrap();
+    assert_eq!(game.play_premove(3), None);
+    // the opponent's move keeps it
+    game.set_premove(&white, "Nc3".to_string()).unwrap();
+    game.make_move(&black, (4, CwChessAction::from("e6"))).unwrap();
+    assert_eq!(game.premove, Some((CwChessColor::White, "Nc3".to_string())));
+  }
+
+  #[test]
+  fn test_get_player_order() {
+    let challenger = Addr::unchecked("challenger");
+    let acceptor = Addr::unchecked("acceptor");
+    let order = |play_as, seed| {
+      CwChessGame::get_player_order(challenger.clone(), acceptor.clone(), play_as, seed)
+    };
+    for seed in 0..4 {
+      assert_eq!(order(Some(CwChessColor::White), seed), (challenger.clone(), acceptor.clone()));
+      assert_eq!(order(Some(CwChessColor::Black), seed), (acceptor.clone(), challenger.clone()));
+    }
+    assert_eq!(order(None, 10), (challenger.clone(), acceptor.clone()));
+    assert_eq!(order(None, 11), (acceptor.clone(), challenger.clone()));
+  }
+
+  #[test]
+  fn test_game_seed() {
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    // the same inputs always give the same seed, any change gives another
+    let seed = game_seed(1, 100, &alice, &bob);
+    assert_eq!(seed, game_seed(1, 100, &alice, &bob));
+    assert_ne!(seed, game_seed(2, 100, &alice, &bob));
+    assert_ne!(seed, game_seed(1, 101, &alice, &bob));
+    assert_ne!(seed, game_seed(1, 100, &bob, &alice));
+    assert_ne!(game_seed(1, 100, &Addr::unchecked("ab"), &Addr::unchecked("c")), game_seed(1, 100, &Addr::unchecked("a"), &Addr::unchecked("bc")));
+
+    // the challenger is white in about half of many pairings
+    let mut challenger_white = 0;
+    let mut positions = std::collections::BTreeSet::new();
+    for i in 0..2000u64 {
+      let challenger = Addr::unchecked(format!("player{}", i % 37));
+      let seed = game_seed(i, 1000 + i / 3, &challenger, &bob);
+      if CwChessGame::get_player_order(challenger.clone(), bob.clone(), None, seed).0 == challenger {
+        challenger_white += 1;
+      }
+      positions.insert(seed % 960);
+    }
+    assert!((900..1100).contains(&challenger_white), "{}", challenger_white);
+    // and chess960 positions are spread over most of the 960
+    assert!(positions.len() > 800, "{}", positions.len());
+
+    // consecutive blocks don't simply alternate colors like the height did
+    let colors = (0..64).map(|height| game_seed(1, height, &alice, &bob) % 2).collect::<Vec<_>>();
+    assert!(colors.windows(2).any(|pair| pair[0] == pair[1]));
+  }
+
+  #[test]
+  fn test_fen_at_ply() {
+    let mut game = test_game(DEFAULT_FEN);
+    assert_eq!(game.fen_at_ply(0).unwrap(), DEFAULT_FEN);
+    let mut fens = vec![game.fen.clone()];
+    let moves = [
+      ("white", CwChessAction::from("e4")),
+      ("black", CwChessAction::from("e5")),
+      ("white", CwChessAction::from("Nf3")),
+      // the draw offer is a board move too
+      ("black", CwChessAction::OfferDraw("Nc6".to_string())),
+      ("white", CwChessAction::from("Bb5")),
+    ];
+    for (i, (player, action)) in moves.into_iter().enumerate() {
+      game.make_move(&Addr::unchecked(player), (i as u64 + 1, action)).unwrap();
+      fens.push(game.fen.clone());
+    }
+    // midgame positions replay from the start, not the stored fen
+    assert_eq!(game.fen_at_ply(2).unwrap(), fens[2]);
+    assert_eq!(game.fen_at_ply(4).unwrap(), fens[4]);
+    // the last ply is the current position
+    assert_eq!(game.fen_at_ply(5).unwrap(), game.fen);
+    match game.fen_at_ply(6).unwrap_err() {
+      ContractError::MoveNotFound { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    // every position is a valid starting fen, with the fullmove number counting up
+    for (ply, fen) in fens.iter().enumerate() {
+      assert_eq!(&game.fen_at_ply(ply).unwrap(), fen);
+      validate_fen(fen).unwrap();
+    }
+    let fullmoves = fens.iter().map(|fen| fen.rsplit(' ').next().unwrap()).collect::<Vec<_>>();
+    assert_eq!(fullmoves, ["1", "1", "2", "2", "3", "3"]);
+    // and doesn't wrap in long games
+    assert_eq!(game.fullmove_number(1000), 501);
+
+    // games from a position count on from its fullmove number
+    let mut game = test_game("4k3/8/8/8/8/8/8/R3K3 b - - 0 40");
+    game.make_move(&Addr::unchecked("black"), (1, CwChessAction::from("Kd7"))).unwrap();
+    assert_eq!(game.fen, "8/3k4/8/8/8/8/8/R3K3 w - - 0 41");
+    game.make_move(&Addr::unchecked("white"), (2, CwChessAction::from("Ra7"))).unwrap();
+    assert_eq!(game.fen_at_ply(2).unwrap(), "8/R2k4/8/8/8/8/8/4K3 b - - 0 41");
+  }
+
+  const OPERA_GAME: &str = "[Event \"Paris\"]
+[White \"Paul Morphy\"]
+[Black \"Duke Karl / Count Isouard\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 {a poor move} 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6
+7. Qb3 Qe7 8. Nc3 c6 9. Bg5 b5 (9... Qb4+ 10. Qxb4) 10. Nxb5 cxb5 11. Bxb5+ Nbd7
+12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+! Nxb8 17. Rd8# 1-0
+";
+
+  #[test]
+  fn test_parse_pgn() {
+    let (tags, moves, result) = parse_pgn(OPERA_GAME).unwrap();
+    assert_eq!(tags[0], ("Event".to_string(), "Paris".to_string()));
+    assert_eq!(tags.len(), 4);
+    assert_eq!(moves.len(), 33);
+    assert_eq!(moves[..4], ["e4", "e5", "Nf3", "d6"]);
+    // suffixes are dropped, and the variation skipped
+    assert_eq!(moves[17], "b5");
+    assert_eq!(moves[20], "Bxb5");
+    assert_eq!(moves[30], "Qb8");
+    assert_eq!(moves[32], "Rd8");
+    assert_eq!(result, "1-0");
+
+    // move numbers joined to moves, black to move first and a line comment
+    let (_, moves, result) = parse_pgn("12...Nf6 ; black's move\n13.e5 $1 *").unwrap();
+    assert_eq!(moves, ["Nf6", "e5"]);
+    assert_eq!(result, "*");
+
+    for pgn in ["1. e4 e5", "1. e4 {open", "1. e4 (1. d4 1-0", "1. e4 1-0 2. d4"] {
+      match parse_pgn(pgn).unwrap_err() {
+        ContractError::InvalidPgn { .. } => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+  }
+
+  #[test]
+  fn test_from_pgn() {
+    let white = Addr::unchecked("white");
+    let black = Addr::unchecked("black");
+    let game = CwChessGame::from_pgn(1, 5, OPERA_GAME, white.clone(), black.clone()).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteCheckmates));
+    assert_eq!(game.moves.len(), 33);
+    let position = game.fen.split(' ').take(4).collect::<Vec<_>>().join(" ");
+    assert_eq!(position, "1n1Rkb1r/p4ppp/4q3/4p1B1/4P3/8/PPP2PPP/2K5 b k -");
+    // the export reads back as the same game
+    let exported = CwChessGame::from_pgn(2, 5, &game.to_pgn("site").unwrap(), white.clone(), black.clone()).unwrap();
+    assert_eq!(exported.fen, game.fen);
+    assert_eq!(exported.status, game.status);
+
+    // results the board doesn't reach
+    let game = CwChessGame::from_pgn(1, 5, "1. e4 e5 0-1", white.clone(), black.clone()).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteResigns));
+    let game = CwChessGame::from_pgn(1, 5, "1. e4 e5 1/2-1/2", white.clone(), black.clone()).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::DrawAccepted));
+
+    // from a custom position
+    let pgn = "[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n\n1... Kd7 2. e4 1-0";
+    let game = CwChessGame::from_pgn(1, 5, pgn, white.clone(), black.clone()).unwrap();
+    assert_eq!(game.start_fen.as_deref(), Some("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"));
+    assert_eq!(game.moves.len(), 2);
+
+    // illegal moves, a result the board contradicts, moves after mate and unfinished games
+    match CwChessGame::from_pgn(1, 5, "1. e4 e4 1-0", white.clone(), black.clone()).unwrap_err() {
+      ContractError::InvalidMove { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    let fools_mate = "1. f3 e5 2. g4 Qh4#";
+    for (pgn, invalid_move) in [
+      (format!("{} 1-0", fools_mate), false),
+      (format!("{} 3. e4 0-1", fools_mate), true),
+      ("1. e4 e5 *".to_string(), false),
+    ] {
+      match CwChessGame::from_pgn(1, 5, &pgn, white.clone(), black.clone()).unwrap_err() {
+        ContractError::InvalidMove { .. } if invalid_move => {}
+        ContractError::InvalidPgn { .. } if !invalid_move => {}
+        e => panic!("unexpected error: {:?}", e),
+      }
+    }
+  }
+
+  #[test]
+  fn test_game_record() {
+    let mut game = test_game(DEFAULT_FEN);
+    for (i, (player, move_str)) in [("white", "e4"), ("black", "c5"), ("white", "e5"), ("black", "d5")].into_iter().enumerate() {
+      game.make_move(&Addr::unchecked(player), (i as u64 + 1, CwChessAction::from(move_str))).unwrap();
+    }
+    assert_eq!(game.fen, "rnbqkbnr/pp2pppp/8/2ppP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3");
+
+    // state keeps the board, queries the fen
+    let stored = cosmwasm_std::to_vec(&game).unwrap();
+    let plain = cosmwasm_std::to_vec(&GameRecord::plain(&game)).unwrap();
+    let stored_json = String::from_utf8(stored.clone()).unwrap();
+    assert!(!stored_json.contains("\"fen\""));
+    assert!(stored_json.contains("\"board\""));
+    assert!(!String::from_utf8(plain.clone()).unwrap().contains("\"board\""));
+    // base64 eats into it, but the saved position is still at least a quarter smaller
+    assert!(plain.len() - stored.len() >= game.fen.len() / 4, "{} vs {}", stored.len(), plain.len());
+
+    // both load back to the same game
+    assert_eq!(cosmwasm_std::from_slice::<CwChessGame>(&stored).unwrap(), game);
+    assert_eq!(cosmwasm_std::from_slice::<CwChessGame>(&plain).unwrap(), game);
+
+    // a position the board can't hold is saved as its fen
+    let mut odd = game.clone();
+    odd.fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -".to_string();
+    let stored = cosmwasm_std::to_vec(&odd).unwrap();
+    assert!(String::from_utf8(stored.clone()).unwrap().contains(&odd.fen));
+    assert_eq!(cosmwasm_std::from_slice::<CwChessGame>(&stored).unwrap(), odd);
+
+    // a record with no position doesn't load
+    let mut record = GameRecord::plain(&game);
+    record.fen = None;
+    assert!(CwChessGame::try_from(record).is_err());
+  }
+
+  #[test]
+  fn test_decline_draw() {
+    let white = Addr::unchecked("white");
+    let black = Addr::unchecked("black");
+    let mut game = test_game(DEFAULT_FEN);
+    match game.make_move(&white, (1, CwChessAction::DeclineDraw)).unwrap_err() {
+      ContractError::NoDrawOffered { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    game.make_move(&white, (1, CwChessAction::from("e4"))).unwrap();
+    game.make_move(&black, (2, CwChessAction::OfferDraw("e5".to_string()))).unwrap();
+    assert_eq!(game.draw_offered(), Some(CwChessColor::Black));
+    // only the player the offer waits on can decline it
+    match game.make_move(&black, (3, CwChessAction::DeclineDraw)).unwrap_err() {
+      ContractError::NotYourTurn { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    let fen = game.fen.clone();
+
+    // declining leaves the game in progress, on the decliner's turn
+    assert_eq!(game.make_move(&white, (3, CwChessAction::DeclineDraw)).unwrap(), &None);
+    assert_eq!(game.draw_offered(), None);
+    assert_eq!(game.turn_color(), Some(CwChessColor::White));
+    assert_eq!(game.fen, fen);
+    assert_eq!(game.moves, vec![(1, CwChessAction::from("e4")), (2, CwChessAction::from("e5"))]);
+    assert_eq!(game.last_draw_offers, (None, Some(2)));
+    // so the offer can't be accepted or declined again
+    assert!(game.make_move(&white, (3, CwChessAction::AcceptDraw)).is_err());
+    match game.make_move(&white, (3, CwChessAction::DeclineDraw)).unwrap_err() {
+      ContractError::NoDrawOffered { .. } => {}
+      e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(game.status, None);
+    game.make_move(&white, (4, CwChessAction::from("Nf3"))).unwrap();
+    assert_eq!(game.get_san_moves().unwrap().len(), 3);
+  }
+
+  #[test]
+  fn test_king_in_check() {
+    // the bishop is pinned to the king by the rook
+    const PINNED: &str = "4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1";
+    assert_king_in_check(PINNED, &["Bd3"]);
+    assert_king_in_check(PINNED, &["Bb5"]);
+    play(PINNED, &["Kd1"]).unwrap();
+    // staying on the line is fine
+    play("4k3/4r3/8/8/8/8/4Q3/4K3 w - - 0 1", &["Qxe7"]).unwrap();
+
+    // the king can't step onto a square the rook or the pawn attacks
+    const ATTACKED: &str = "4k3/8/8/8/8/3p4/8/r3K3 w - - 0 1";
+    assert_king_in_check(ATTACKED, &["Kd1"]);
+    assert_king_in_check(ATTACKED, &["Ke2"]);
+    assert_king_in_check(ATTACKED, &["Kf1"]);
+    play(ATTACKED, &["Kd2"]).unwrap();
+
+    // in check, a move elsewhere doesn't help, but a block or a capture does
+    const CHECKED: &str = "4k3/8/8/8/8/8/3PP3/r3K1N1 w - - 0 1";
+    assert_king_in_check(CHECKED, &["e4"]);
+    assert_king_in_check(CHECKED, &["Nf3"]);
+    play(CHECKED, &["Kf2"]).unwrap();
+    play("4k3/8/8/8/8/8/1N1PP3/r3K3 w - - 0 1", &["Nd1"]).unwrap();
+    play("4k3/8/8/8/8/8/R2PP3/r3K3 w - - 0 1", &["Rxa1"]).unwrap();
+
+    // a move that isn't possible at all is still just invalid
+    match play(PINNED, &["Bf4"]) {
+      Err(ContractError::InvalidMove {}) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+
+    // validating gives the same reason as playing
+    let game = test_game(PINNED);
+    match game.valid_move(&game.player1, "Bd3") {
+      Err(ContractError::KingInCheck {}) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+    assert!(game.valid_move(&game.player1, "Kd1").unwrap());
+  }
+
+  #[test]
+  fn test_stalemate_or_checkmate() {
+    // no legal moves and not in check is a stalemate
+    let game = play("7k/8/6Q1/8/8/8/8/K7 w - - 0 1", &["Qf7"]).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::Stalemate));
+    let game = play("k7/8/1Q6/8/8/8/8/7K w - - 0 1", &["Qc7"]).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::Stalemate));
+    // the king can't take the pawn that blocks it in
+    let game = play("k7/P7/8/1K6/8/8/8/8 w - - 0 1", &["Ka6"]).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::Stalemate));
+
+    // no legal moves in check is a checkmate, for whoever delivers it
+    let game = play("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &["Ra8"]).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::WhiteCheckmates));
+    let game = play("r5k1/8/8/8/8/8/5PPP/6K1 b - - 0 1", &["Ra1"]).unwrap();
+    assert_eq!(game.status, Some(CwChessGameOver::BlackCheckmates));
+
+    // check with a way out goes on
+    let game = play("6k1/5pp1/8/8/8/8/8/R5K1 w - - 0 1", &["Ra8"]).unwrap();
+    assert_eq!(game.status, None);
+    play("6k1/5pp1/8/8/8/8/8/R5K1 w - - 0 1", &["Ra8", "Kh7"]).unwrap();
+  }
+}
+
+#[cfg(test)]
+mod castling_tests {
+  use super::tests::play;
+  use super::*;
+
+  fn assert_illegal_castle(fen: &str, moves: &[&str]) {
+    match play(fen, moves) {
+      Err(ContractError::IllegalCastle {}) => {}
+      result => panic!("expected illegal castle for {:?}: {:?}", moves, result),
+    }
+  }
+
+  // placement and castling fields
+  fn position(game: &CwChessGame) -> String {
+    let parts = game.fen.split(' ').collect::<Vec<&str>>();
+    format!("{} {}", parts[0], parts[2])
+  }
+
+  const ROOKS: &str = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+  #[test]
+  fn test_kingside() {
+    let game = play(ROOKS, &["0-0"]).unwrap();
+    assert_eq!(position(&game), "r3k2r/8/8/8/8/8/8/R4RK1 kq");
+    let game = play(ROOKS, &["O-O", "O-O-O"]).unwrap();
+    assert_eq!(position(&game), "2kr3r/8/8/8/8/8/8/R4RK1 -");
+  }
+
+  #[test]
+  fn test_queenside() {
+    let game = play(ROOKS, &["0-0-0"]).unwrap();
+    assert_eq!(position(&game), "r3k2r/8/8/8/8/8/8/2KR3R kq");
+    let game = play(ROOKS, &["O-O-O", "O-O"]).unwrap();
+    assert_eq!(position(&game), "r4rk1/8/8/8/8/8/8/2KR3R -");
+    // only the king's path has to be safe, b1 can be attacked
+    play("1r2k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", &["0-0-0"]).unwrap();
+  }
+
+  #[test]
+  fn test_through_check() {
+    assert_illegal_castle("5rk1/8/8/8/8/8/8/R3K2R w KQ - 0 1", &["0-0"]);
+    assert_illegal_castle("3r2k1/8/8/8/8/8/8/R3K2R w KQ - 0 1", &["0-0-0"]);
+  }
+
+  #[test]
+  fn test_out_of_check() {
+    assert_illegal_castle("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1", &["0-0"]);
+    assert_illegal_castle("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1", &["0-0-0"]);
+  }
+
+  #[test]
+  fn test_into_check() {
+    assert_illegal_castle("6rk/8/8/8/8/8/8/R3K2R w KQ - 0 1", &["0-0"]);
+    play("6rk/8/8/8/8/8/8/R3K2R w KQ - 0 1", &["0-0-0"]).unwrap();
+  }
+
+  #[test]
+  fn test_occupied() {
+    assert_illegal_castle("4k3/8/8/8/8/8/8/R3K1NR w KQ - 0 1", &["0-0"]);
+    assert_illegal_castle("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1", &["0-0-0"]);
+    // a piece on the king's destination blocks too
+    assert_illegal_castle("4k3/8/8/8/8/8/8/R1B1K2R w KQ - 0 1", &["0-0-0"]);
+  }
+
+  #[test]
+  fn test_king_moved() {
+    let game = play(ROOKS, &["Kf1", "Kf8", "Ke1", "Ke8"]).unwrap();
+    assert_eq!(position(&game), "r3k2r/8/8/8/8/8/8/R3K2R -");
+    assert_illegal_castle(ROOKS, &["Kf1", "Kf8", "Ke1", "Ke8", "0-0"]);
+    assert_illegal_castle(ROOKS, &["Kf1", "Kf8", "Ke1", "Ke8", "0-0-0"]);
+  }
+
+  #[test]
+  fn test_rook_moved() {
+    let game = play(ROOKS, &["Rh2", "Ra7"]).unwrap();
+    assert_eq!(position(&game), "4k2r/r7/8/8/8/8/7R/R3K3 Qk");
+    // moving the rook back doesn't restore the right
+    let game = play(ROOKS, &["Rh2", "Ra7", "Rh1", "Ra8"]).unwrap();
+    assert_eq!(position(&game), "r3k2r/8/8/8/8/8/8/R3K2R Qk");
+    assert_illegal_castle(ROOKS, &["Rh2", "Ra7", "Rh1", "Ra8", "0-0"]);
+    play(ROOKS, &["Rh2", "Ra7", "Rh1", "Ra8", "0-0-0"]).unwrap();
+  }
+
+  #[test]
+  fn test_rook_captured() {
+    let game = play(ROOKS, &["Rxh8"]).unwrap();
+    assert_eq!(position(&game), "r3k2R/8/8/8/8/8/8/R3K3 Qq");
+    // a different rook recapturing on h8 can't castle
+    let fen = "r3k2r/8/8/8/8/8/7r/B3K3 w kq - 0 1";
+    let game = play(fen, &["Bxh8"]).unwrap();
+    assert_eq!(position(&game), "r3k2B/8/8/8/8/8/7r/4K3 q");
+    let game = play(fen, &["Bxh8", "Rxh8"]).unwrap();
+    assert_eq!(position(&game), "r3k2r/8/8/8/8/8/8/4K3 q");
+    assert_illegal_castle(fen, &["Bxh8", "Rxh8", "Kd1", "0-0"]);
   }
 }
@@ -0,0 +1,85 @@
+//! A compact table of common openings, keyed by ECO code.
+//!
+//! Each entry is (ECO code, opening name, SAN moves from the standard starting position).
+//! Moves are space separated and written without check or mate suffixes.
+//! A game is classified by the longest entry whose moves are a prefix of the game's moves.
+
+pub const OPENINGS: &[(&str, &str, &str)] = &[
+  // flank openings
+  ("A00", "Polish Opening", "b4"),
+  ("A00", "Grob Opening", "g4"),
+  ("A01", "Nimzo-Larsen Attack", "b3"),
+  ("A02", "Bird's Opening", "f4"),
+  ("A04", "Reti Opening", "Nf3"),
+  ("A05", "Reti Opening", "Nf3 Nf6"),
+  ("A06", "Reti Opening", "Nf3 d5"),
+  ("A10", "English Opening", "c4"),
+  ("A20", "English Opening: King's English", "c4 e5"),
+  ("A30", "English Opening: Symmetrical Variation", "c4 c5"),
+  // queen's pawn and indian defenses
+  ("A40", "Queen's Pawn Game", "d4"),
+  ("A45", "Indian Defense", "d4 Nf6"),
+  ("A46", "Indian Defense", "d4 Nf6 Nf3"),
+  ("A50", "Indian Defense", "d4 Nf6 c4"),
+  ("A51", "Budapest Gambit", "d4 Nf6 c4 e5"),
+  ("A56", "Benoni Defense", "d4 Nf6 c4 c5"),
+  ("A57", "Benko Gambit", "d4 Nf6 c4 c5 d5 b5"),
+  ("A80", "Dutch Defense", "d4 f5"),
+  // semi-open games
+  ("B00", "King's Pawn Opening", "e4"),
+  ("B01", "Scandinavian Defense", "e4 d5"),
+  ("B02", "Alekhine's Defense", "e4 Nf6"),
+  ("B06", "Modern Defense", "e4 g6"),
+  ("B07", "Pirc Defense", "e4 d6 d4 Nf6"),
+  ("B10", "Caro-Kann Defense", "e4 c6"),
+  ("B20", "Sicilian Defense", "e4 c5"),
+  ("B22", "Sicilian Defense: Alapin Variation", "e4 c5 c3"),
+  ("B23", "Sicilian Defense: Closed", "e4 c5 Nc3"),
+  ("B27", "Sicilian Defense", "e4 c5 Nf3"),
+  ("B30", "Sicilian Defense", "e4 c5 Nf3 Nc6"),
+  ("B40", "Sicilian Defense", "e4 c5 Nf3 e6"),
+  ("B50", "Sicilian Defense", "e4 c5 Nf3 d6"),
+  ("B70", "Sicilian Defense: Dragon Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6"),
+  ("B90", "Sicilian Defense: Najdorf Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6"),
+  // open games and the french
+  ("C00", "French Defense", "e4 e6"),
+  ("C20", "King's Pawn Game", "e4 e5"),
+  ("C23", "Bishop's Opening", "e4 e5 Bc4"),
+  ("C25", "Vienna Game", "e4 e5 Nc3"),
+  ("C30", "King's Gambit", "e4 e5 f4"),
+  ("C40", "King's Knight Opening", "e4 e5 Nf3"),
+  ("C41", "Philidor Defense", "e4 e5 Nf3 d6"),
+  ("C42", "Petrov's Defense", "e4 e5 Nf3 Nf6"),
+  ("C44", "King's Pawn Game", "e4 e5 Nf3 Nc6"),
+  ("C44", "Scotch Game", "e4 e5 Nf3 Nc6 d4"),
+  ("C46", "Three Knights Opening", "e4 e5 Nf3 Nc6 Nc3"),
+  ("C47", "Four Knights Game", "e4 e5 Nf3 Nc6 Nc3 Nf6"),
+  ("C50", "Italian Game", "e4 e5 Nf3 Nc6 Bc4"),
+  ("C50", "Italian Game: Giuoco Piano", "e4 e5 Nf3 Nc6 Bc4 Bc5"),
+  ("C51", "Italian Game: Evans Gambit", "e4 e5 Nf3 Nc6 Bc4 Bc5 b4"),
+  ("C55", "Italian Game: Two Knights Defense", "e4 e5 Nf3 Nc6 Bc4 Nf6"),
+  ("C60", "Ruy Lopez", "e4 e5 Nf3 Nc6 Bb5"),
+  ("C65", "Ruy Lopez: Berlin Defense", "e4 e5 Nf3 Nc6 Bb5 Nf6"),
+  ("C68", "Ruy Lopez: Exchange Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Bxc6"),
+  ("C70", "Ruy Lopez: Morphy Defense", "e4 e5 Nf3 Nc6 Bb5 a6"),
+  // closed games
+  ("D00", "Queen's Pawn Game", "d4 d5"),
+  ("D00", "London System", "d4 d5 Bf4"),
+  ("D02", "Queen's Pawn Game", "d4 d5 Nf3"),
+  ("D02", "London System", "d4 d5 Nf3 Nf6 Bf4"),
+  ("D06", "Queen's Gambit", "d4 d5 c4"),
+  ("D07", "Queen's Gambit Declined: Chigorin Defense", "d4 d5 c4 Nc6"),
+  ("D10", "Slav Defense", "d4 d5 c4 c6"),
+  ("D20", "Queen's Gambit Accepted", "d4 d5 c4 dxc4"),
+  ("D30", "Queen's Gambit Declined", "d4 d5 c4 e6"),
+  ("D43", "Semi-Slav Defense", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6"),
+  ("D80", "Grunfeld Defense", "d4 Nf6 c4 g6 Nc3 d5"),
+  // indian systems
+  ("E00", "Indian Defense", "d4 Nf6 c4 e6"),
+  ("E01", "Catalan Opening", "d4 Nf6 c4 e6 g3"),
+  ("E10", "Indian Defense", "d4 Nf6 c4 e6 Nf3"),
+  ("E12", "Queen's Indian Defense", "d4 Nf6 c4 e6 Nf3 b6"),
+  ("E20", "Nimzo-Indian Defense", "d4 Nf6 c4 e6 Nc3 Bb4"),
+  ("E60", "King's Indian Defense", "d4 Nf6 c4 g6"),
+  ("E61", "King's Indian Defense", "d4 Nf6 c4 g6 Nc3 Bg7"),
+];
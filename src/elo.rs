@@ -10,7 +10,7 @@
 //! Please take a look at the functions below to see more advanced use cases.
 //!
 //!
-//! use elo::{elo, EloConfig, EloRating, Outcomes},
+//! use elo::{elo_with_games, EloConfig, EloRating, Outcomes},
 //!
 //! // Initialise a new player rating with a rating of 1000.
 //! let player_one = EloRating::new();
@@ -28,10 +28,11 @@
 //! // The config allows you to specify certain values in the Elo calculation.
 //! // Here we modify the k-value to be 20.0, instead of the usual 32.0.
 //! // To simplify massively: This means the ratings will not change as much.
-//! let config = EloConfig { k: 20 };
+//! let config = EloConfig { k: 20, ..EloConfig::new() };
 //!
-//! // The elo function will calculate the new ratings for both players and return them.
-//! let (new_player_one, new_player_two) = elo(&player_one, &player_two, &outcome, &config);
+//! // The elo_with_games function will calculate the new ratings for both players and return them.
+//! // It also takes the number of games each player has played, to pick their k-value.
+//! let (new_player_one, new_player_two) = elo_with_games(&player_one, 50, &player_two, 50, &outcome, &config);
 //!
 //!
 //! # More Information
@@ -91,6 +92,7 @@ fn fp_pow10(x: u64) -> u64 {
 /// Note that this is always from the perspective of player one.  
 /// That means a win is a win for player one and a loss is a win for player two.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Outcomes {
   /// A win, from player_one's perspective.
   WIN,
@@ -158,13 +160,49 @@ pub struct EloConfig {
   /// The higher the number, the more volatile the ranking.  
   /// Here the default is 32.
   pub k: u64,
+  /// The k-value for players who have played fewer than `provisional_games` games.
+  /// Here the default is 32.
+  pub provisional_k: u64,
+  /// The number of games a player needs before their rating is established.
+  /// Here the default is 0, so no player is provisional.
+  pub provisional_games: u64,
+  /// The rating at and above which `high_rating_k` is used instead of `k`.
+  /// Here the default is 2400.
+  pub high_rating: u64,
+  /// The k-value for established players rated at least `high_rating`.
+  /// Here the default is 32, the same as `k`.
+  pub high_rating_k: u64,
 }
 
 impl EloConfig {
   #[must_use]
-  /// Initialise a new `EloConfig` with a k value of `32.0`.
+  /// Initialise a new `EloConfig` with a k value of `32.0` for every player.
   pub const fn new() -> Self {
-    Self { k: 32 }
+    Self {
+      k: 32,
+      provisional_k: 32,
+      provisional_games: 0,
+      high_rating: 2400,
+      high_rating_k: 32,
+    }
+  }
+
+  #[must_use]
+  /// Whether a player's rating is still provisional after the given number of games.
+  pub const fn is_provisional(&self, games: u64) -> bool {
+    games < self.provisional_games
+  }
+
+  #[must_use]
+  /// The k-value for a player with the given rating and number of games played.
+  pub const fn k_for(&self, rating: u64, games: u64) -> u64 {
+    if self.is_provisional(games) {
+      self.provisional_k
+    } else if rating >= self.high_rating {
+      self.high_rating_k
+    } else {
+      self.k
+    }
   }
 }
 
@@ -176,14 +214,18 @@ impl Default for EloConfig {
 
 /// Calculates the [`EloRating`]s of two players based on their old ratings and the outcome of the game.
 ///
-/// Takes in two players as [`EloRating`]s, an [`Outcome`](Outcomes) and an [`EloConfig`].
+/// Takes in two players as [`EloRating`]s with their number of games played, an [`Outcome`](Outcomes) and an [`EloConfig`].
+/// Each player's k-value is picked from their rating and number of games played (see [`EloConfig::k_for`]).
 ///
 /// The outcome of the match is in the perspective of `player_one`.
 /// This means [`Outcomes::WIN`] is a win for `player_one` and [`Outcomes::LOSS`] is a win for `player_two`.
 ///
+/// A provisional player's games don't change their established opponent's rating.
+/// When both players use the same k-value, the rating points exchanged add up to zero.
+///
 /// # Examples
 ///
-/// use elo::{elo, EloConfig, EloRating, Outcomes};
+/// use elo::{elo_with_games, EloConfig, EloRating, Outcomes};
 ///
 /// let player_one = EloRating { rating: 600 };
 /// let player_two = EloRating { rating: 711 };
@@ -192,23 +234,38 @@ impl Default for EloConfig {
 ///
 /// let config = EloConfig::new();
 ///
-/// let (new_one, new_two) = elo(&player_one, &player_two, &outcome, &config);
+/// let (new_one, new_two) = elo_with_games(&player_one, 50, &player_two, 50, &outcome, &config);
 ///
 /// assert!(new_one == 620);
 /// assert!(new_two == 690);
 /// ```
 #[must_use]
-pub fn elo(
+pub fn elo_with_games(
   player_one: &EloRating,
+  games_one: u64,
   player_two: &EloRating,
+  games_two: u64,
   outcome: &Outcomes,
   config: &EloConfig,
 ) -> (EloRating, EloRating) {
   let expected = expected_score(player_one, player_two);
   let outcome = outcome.to_chess_points();
+  let k_one = config.k_for(player_one.rating, games_one);
+  let k_two = config.k_for(player_two.rating, games_two);
 
-  let one_new_elo = ((player_one.rating << PREC) + config.k * outcome - config.k * expected) >> PREC;
-  let two_new_elo = player_one.rating + player_two.rating - one_new_elo;
+  // ratings are unsigned, so a loss saturates at 0 instead of underflowing
+  let one_new_elo = ((player_one.rating << PREC) + k_one * outcome).saturating_sub(k_one * expected) >> PREC;
+  let two_new_elo = match k_one == k_two {
+    true => (player_one.rating + player_two.rating).saturating_sub(one_new_elo),
+    false => ((player_two.rating << PREC) + k_two * expected).saturating_sub(k_two * outcome) >> PREC,
+  };
+
+  // established players are unaffected by provisional opponents
+  let (one_new_elo, two_new_elo) = match (config.is_provisional(games_one), config.is_provisional(games_two)) {
+    (true, false) => (one_new_elo, player_two.rating),
+    (false, true) => (player_one.rating, two_new_elo),
+    _ => (one_new_elo, two_new_elo),
+  };
 
   (
     EloRating {
@@ -240,11 +297,7 @@ pub fn elo(
 ///
 #[must_use]
 pub fn expected_score(player_one: &EloRating, player_two: &EloRating) -> u64 {
-  let diff = if player_one.rating >= player_two.rating {
-    player_one.rating - player_two.rating
-  } else {
-    player_two.rating - player_one.rating
-  };
+  let diff = player_one.rating.abs_diff(player_two.rating);
   let exp_one = (1 << (PREC + PREC)) / ((1 << PREC) + fp_pow10((diff << PREC) / 400));
 
   if player_two.rating >= player_one.rating {
@@ -258,6 +311,11 @@ pub fn expected_score(player_one: &EloRating, player_two: &EloRating) -> u64 {
 mod tests {
   use super::*;
 
+  // rates two established players
+  fn elo(player_one: &EloRating, player_two: &EloRating, outcome: &Outcomes, config: &EloConfig) -> (EloRating, EloRating) {
+    elo_with_games(player_one, u64::MAX, player_two, u64::MAX, outcome, config)
+  }
+
   #[test]
   fn test_elo() {
     let (winner_new_elo, loser_new_elo) = elo(
@@ -297,6 +355,121 @@ mod tests {
     assert!(loser_new_elo.rating == 1469);
   }
 
+  #[test]
+  fn test_elo_zero_rating() {
+    // losing at 0 saturates instead of underflowing, leaving nothing to exchange
+    let (loser_new_elo, winner_new_elo) = elo(
+      &EloRating { rating: 0 },
+      &EloRating { rating: 10 },
+      &Outcomes::LOSS,
+      &EloConfig::new(),
+    );
+    assert_eq!(loser_new_elo.rating, 0);
+    assert_eq!(winner_new_elo.rating, 10);
+  }
+
+  #[test]
+  fn test_elo_k_factor() {
+    // one k for everyone by default
+    assert!(EloConfig::new().k_for(2500, 0) == 32);
+
+    // same result, lower rated player moves more
+    let config = EloConfig {
+      high_rating_k: 16,
+      ..EloConfig::new()
+    };
+    let (low_new_elo, _) = elo(
+      &EloRating { rating: 1200 },
+      &EloRating { rating: 1200 },
+      &Outcomes::WIN,
+      &config,
+    );
+    let (high_new_elo, _) = elo(
+      &EloRating { rating: 2400 },
+      &EloRating { rating: 2400 },
+      &Outcomes::WIN,
+      &config,
+    );
+    assert!(low_new_elo.rating == 1216);
+    assert!(high_new_elo.rating == 2408);
+
+    // each player uses their own k
+    let (low_new_elo, high_new_elo) = elo(
+      &EloRating { rating: 2399 },
+      &EloRating { rating: 2400 },
+      &Outcomes::DRAW,
+      &config,
+    );
+    assert!(low_new_elo.rating == 2399);
+    assert!(high_new_elo.rating == 2399);
+
+    // provisional players use provisional_k
+    let config = EloConfig {
+      provisional_k: 40,
+      provisional_games: 30,
+      ..config
+    };
+    assert!(config.k_for(2500, 29) == 40);
+    assert!(config.k_for(2500, 30) == 16);
+    assert!(config.k_for(1200, 30) == 32);
+    let (new_player, established) = elo_with_games(
+      &EloRating { rating: 1200 },
+      0,
+      &EloRating { rating: 1200 },
+      100,
+      &Outcomes::WIN,
+      &config,
+    );
+    assert!(new_player.rating == 1220);
+    assert!(established.rating == 1200);
+  }
+
+  #[test]
+  fn test_elo_provisional() {
+    let config = EloConfig {
+      provisional_games: 10,
+      ..EloConfig::new()
+    };
+    assert!(config.is_provisional(9));
+    assert!(!config.is_provisional(10));
+
+    // established player beats a provisional player, only the provisional rating moves
+    let (established, provisional) = elo_with_games(
+      &EloRating { rating: 1500 },
+      50,
+      &EloRating { rating: 1500 },
+      3,
+      &Outcomes::WIN,
+      &config,
+    );
+    assert!(established.rating == 1500);
+    assert!(provisional.rating == 1484);
+
+    // same the other way around
+    let (provisional, established) = elo_with_games(
+      &EloRating { rating: 1500 },
+      3,
+      &EloRating { rating: 1500 },
+      50,
+      &Outcomes::WIN,
+      &config,
+    );
+    assert!(provisional.rating == 1516);
+    assert!(established.rating == 1500);
+
+    // two provisional players both move
+    let (one, two) = elo_with_games(
+      &EloRating { rating: 1500 },
+      0,
+      &EloRating { rating: 1500 },
+      0,
+      &Outcomes::LOSS,
+      &config,
+    );
+    assert!(one.rating == 1484);
+    assert!(two.rating == 1516);
+  }
+
   #[test]
   fn test_expected_score() {
     let player_one = EloRating::new();
@@ -8,22 +8,18 @@ pub const BLACK: Color = Color::Black;
 
 /// The result of a move being played on the board.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[allow(clippy::large_enum_variant)]
 pub enum GameResult {
   /// The game is not finished, and the game is still in play.
   Continuing(Board),
   /// One player, the victor, checkmated the other.
   /// This stores the color of the winner.
   Victory(Color),
-  /// The game is drawn. This can be a result of the current player
-  /// having no legal moves and not being in check, or because
-  /// both players have insufficient material on the board.
+  /// The game is drawn, because the current player has no legal moves
+  /// and is not in check.
   ///
-  /// Insufficient material consists of:
-  /// 1. The player only has a king
-  /// 2. The player only has a king and a knight
-  /// 3. The player only has a king and two knights
-  /// 4. The player only has a king and a bishop
-  /// 5. The player only has a king and two bishops
+  /// Insufficient material is not detected here, as it depends on both
+  /// players' material together rather than each player's on its own.
   ///
   /// In a regular game of chess, threefold repetition also triggers
   /// a stalemate, but this engine does not have builtin support for
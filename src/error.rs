@@ -10,24 +10,118 @@ pub enum ContractError {
 
   // Add any other custom errors you like here.
   // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
+  #[error("already in queue")]
+  AlreadyInQueue {},
+  #[error("already in tournament")]
+  AlreadyInTournament {},
+  #[error("cannot abort a match or tournament game")]
+  CannotAbortEventGame {},
+  #[error("cannot abort a started game")]
+  CannotAbortStartedGame {},
+  #[error("cannot claim on your own turn")]
+  CannotClaimOwnTurn {},
   #[error("cannot play self")]
   CannotPlaySelf {},
+  #[error("cannot premove on your own turn")]
+  CannotPremoveOwnTurn {},
+  #[error("challenge expired")]
+  ChallengeExpired {},
+  #[error("challenge fee not paid")]
+  ChallengeFeeNotPaid {},
+  #[error("challenge not abandoned")]
+  ChallengeNotAbandoned {},
   #[error("challenge not found")]
   ChallengeNotFound {},
+  #[error("draw claim not valid")]
+  DrawClaimNotValid {},
+  #[error("draw offer too soon after the last one")]
+  DrawOfferTooSoon {},
   #[error("game already over")]
   GameAlreadyOver {},
+  #[error("game not abandoned")]
+  GameNotAbandoned {},
   #[error("game not found")]
   GameNotFound {},
+  #[error("game not over")]
+  GameNotOver {},
   #[error("game not timed out")]
   GameNotTimedOut {},
+  #[error("game timed out")]
+  GameTimedOut {},
+  #[error("illegal castle")]
+  IllegalCastle {},
+  #[error("result can't be agreed")]
+  InvalidAgreedResult {},
+  #[error("invalid annotation")]
+  InvalidAnnotation {},
+  #[error("invalid batch size")]
+  InvalidBatchSize {},
+  #[error("invalid block limit")]
+  InvalidBlockLimit {},
+  #[error("invalid expiry")]
+  InvalidExpiry {},
+  #[error("invalid fen")]
+  InvalidFen {},
+  #[error("invalid migration")]
+  InvalidMigration {},
   #[error("invalid move")]
   InvalidMove {},
+  #[error("invalid move timing")]
+  InvalidMoveTiming {},
+  #[error("invalid number of games")]
+  InvalidNumGames {},
+  #[error("invalid odds")]
+  InvalidOdds {},
+  #[error("invalid pgn")]
+  InvalidPgn {},
   #[error("invalid position")]
   InvalidPosition {},
+  #[error("invalid rating bounds")]
+  InvalidRatingBounds {},
+  #[error("invalid square")]
+  InvalidSquare {},
+  #[error("invalid time controls")]
+  InvalidTimeControls {},
+  #[error("invalid tournament rounds")]
+  InvalidTournamentRounds {},
+  #[error("invalid tournament size")]
+  InvalidTournamentSize {},
+  #[error("king left in check")]
+  KingInCheck {},
+  #[error("move not found")]
+  MoveNotFound {},
+  #[error("no draw offered")]
+  NoDrawOffered {},
+  #[error("no moves to take back")]
+  NoMovesToTakeBack {},
+  #[error("no takeback requested")]
+  NoTakebackRequested {},
+  #[error("not in queue")]
+  NotInQueue {},
   #[error("not your challenge")]
   NotYourChallenge {},
+  #[error("not your game")]
+  NotYourGame {},
   #[error("not your turn")]
   NotYourTurn {},
-  #[error("game timed out")]
-  GameTimedOut {},
+  #[error("player not inactive")]
+  PlayerNotInactive {},
+  #[error("promotion piece required")]
+  PromotionRequired {},
+  #[error("rating overflow")]
+  RatingOverflow {},
+  #[error("rematch already offered")]
+  RematchAlreadyOffered {},
+  #[error("resign not confirmed")]
+  ResignNotConfirmed {},
+  #[error("result doesn't match the opponent's")]
+  ResultMismatch {},
+  #[error("too many open challenges")]
+  TooManyOpenChallenges {},
+  #[error("too many challenges to the same opponent")]
+  TooManyPairwiseChallenges {},
+  #[error("tournament full")]
+  TournamentFull {},
+  #[error("tournament not found")]
+  TournamentNotFound {},
 }
\ No newline at end of file
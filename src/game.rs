@@ -1,6 +1,7 @@
 use crate::board::Board;
 use crate::util::{format_fen, parse_fen, parse_san_move};
 use crate::engine::{Color, GameResult};
+use crate::movegen::is_legal;
 
 pub enum GameAction {
   // accept draw if previous action was OfferDraw
@@ -72,7 +73,7 @@ impl Game {
     })
   }
 
-  pub fn to_fen(&self, halfmove_clock: u8, fullmove_number: u8) -> Result<String, String> {
+  pub fn to_fen(&self, halfmove_clock: u8, fullmove_number: u32) -> Result<String, String> {
     format_fen(&self.board, halfmove_clock, fullmove_number)
   }
 
@@ -84,7 +85,7 @@ impl Game {
   // validate the move for current turn
   pub fn valid_move(
     &self,
-    move_str: &String,
+    move_str: &str,
   ) -> Result<bool, GameError> {
     // 
     if self.status.is_some() {
@@ -97,7 +98,7 @@ impl Game {
       }
     };
     
-    Ok(self.board.is_legal_move(chess_move, self.get_turn_color()))
+    Ok(is_legal(&self.board, chess_move))
   }
 
   // make a move for current turn
@@ -149,11 +150,18 @@ impl Game {
       GameResult::IllegalMove(_) => {
         return Err(GameError::InvalidMove {});
       }
-      GameResult::Stalemate => Some(GameOver::Stalemate),
-      GameResult::Victory(color) => match color {
-        Color::Black => Some(GameOver::BlackCheckmates),
-        Color::White => Some(GameOver::WhiteCheckmates),
-      },
+      // the final move is kept on the board, so the position shows the mate or stalemate
+      GameResult::Stalemate => {
+        self.board = self.board.apply_move(chess_move).change_turn();
+        Some(GameOver::Stalemate)
+      }
+      GameResult::Victory(color) => {
+        self.board = self.board.apply_move(chess_move).change_turn();
+        match color {
+          Color::Black => Some(GameOver::BlackCheckmates),
+          Color::White => Some(GameOver::WhiteCheckmates),
+        }
+      }
     };
     Ok(&self.status)
   }
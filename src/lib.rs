@@ -2,8 +2,11 @@ pub mod contract;
 mod contract_test;
 pub mod cwchess;
 mod error;
+pub mod matches;
 pub mod msg;
+pub mod queue;
 pub mod state;
+pub mod tournament;
 mod state_test;
 
 mod board;
@@ -13,6 +16,8 @@ mod piece;
 mod position;
 mod util;
 mod engine;
+mod movegen;
 mod elo;
+mod eco;
 
 pub use crate::error::ContractError;
\ No newline at end of file
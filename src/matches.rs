@@ -0,0 +1,64 @@
+use cosmwasm_std::{Addr, StdResult, Storage};
+use cw_storage_plus::{Item, Map};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::cwchess::CwChessGame;
+use crate::tournament::game_points;
+
+// longest match a challenge can ask for
+pub const MAX_MATCH_GAMES: u8 = 10;
+
+// all games are created when the challenge is accepted and played at the same time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Match {
+  pub finished: bool,
+  // game ids, colors alternate starting with player1 as white
+  pub games: Vec<u64>,
+  pub match_id: u64,
+  pub player1: Addr,
+  pub player2: Addr,
+  // set when the last game finishes, None for a drawn match
+  pub winner: Option<Addr>,
+}
+
+impl Match {
+  // half points of player1 and player2 from the finished games
+  pub fn score(&self, games: &[CwChessGame]) -> (u64, u64) {
+    games
+      .iter()
+      .filter_map(|game| {
+        let (white, black) = game_points(game)?;
+        if game.player1 == self.player1 {
+          Some((white, black))
+        } else {
+          Some((black, white))
+        }
+      })
+      .fold((0, 0), |(a, b), (white, black)| (a + white, b + black))
+  }
+
+  // player with more points, None on a tie
+  pub fn leader(&self, games: &[CwChessGame]) -> Option<Addr> {
+    let (player1, player2) = self.score(games);
+    match player1.cmp(&player2) {
+      std::cmp::Ordering::Greater => Some(self.player1.clone()),
+      std::cmp::Ordering::Less => Some(self.player2.clone()),
+      std::cmp::Ordering::Equal => None,
+    }
+  }
+}
+
+pub const MATCHES: Map<u64, Match> = Map::new("matches");
+
+// game_id -> match_id, for games played in a match
+pub const MATCH_GAMES: Map<u64, u64> = Map::new("match_games");
+
+pub const MATCH_ID: Item<u64> = Item::new("match_id");
+
+pub fn next_match_id(store: &mut dyn Storage) -> StdResult<u64> {
+  let id: u64 = MATCH_ID.may_load(store)?.unwrap_or_default() + 1;
+  MATCH_ID.save(store, &id)?;
+  Ok(id)
+}
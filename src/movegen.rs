@@ -0,0 +1,124 @@
+//! Legal move generation on the engine's board.
+//!
+//! Every move the contract accepts, lists or simulates is checked here.
+//! A promotion is a single move to the last rank, to a queen unless the SAN names another piece.
+
+use crate::board::Board;
+use crate::engine::{Color, Evaluate, Move};
+use crate::position::Position;
+
+/// all legal moves of color, as if it were color's turn
+pub fn legal_moves(board: &Board, color: Color) -> Vec<Move> {
+  if board.get_turn_color() == color {
+    board.get_legal_moves()
+  } else {
+    board.set_turn(color).get_legal_moves()
+  }
+}
+
+/// whether the side to move can play mv
+pub fn is_legal(board: &Board, mv: Move) -> bool {
+  board.is_legal_move(mv, board.get_turn_color())
+}
+
+/// moves the side to move's pieces could make, but that leave its own king in check,
+/// so a rejected move can say why
+pub fn moves_into_check(board: &Board) -> Vec<Move> {
+  let color = board.get_turn_color();
+  let squares = || (0..8).flat_map(|row| (0..8).map(move |col| Position::new(row, col)));
+  squares()
+    .filter(|from| board.has_ally_piece(*from, color))
+    .flat_map(|from| squares().map(move |to| Move::Piece(from, to)))
+    .filter(|mv| board.is_pseudo_legal_move(*mv, color) && board.apply_move(*mv).is_in_check(color))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::util::parse_fen;
+
+  const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+  // leaf nodes of the move tree depth plies deep
+  fn perft(board: &Board, depth: u32) -> u64 {
+    if depth == 0 {
+      return 1;
+    }
+    legal_moves(board, board.get_turn_color())
+      .into_iter()
+      .map(|mv| perft(&board.apply_eval_move(mv), depth - 1))
+      .sum()
+  }
+
+  #[test]
+  fn test_perft_start() {
+    let board = Board::default();
+    assert_eq!(perft(&board, 1), 20);
+    assert_eq!(perft(&board, 2), 400);
+    assert_eq!(perft(&board, 3), 8902);
+  }
+
+  #[test]
+  fn test_perft_castling_and_en_passant() {
+    // both sides can castle either way, and pins and checks cut down the replies
+    let board = parse_fen(KIWIPETE).unwrap();
+    assert_eq!(perft(&board, 1), 48);
+    assert_eq!(perft(&board, 2), 2039);
+
+    // the en passant capture is the only way out of check
+    let board = parse_fen("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1").unwrap();
+    assert_eq!(perft(&board, 1), 9);
+  }
+
+  #[test]
+  fn test_legal_moves_are_legal() {
+    let board = parse_fen(KIWIPETE).unwrap();
+    for color in [Color::White, Color::Black] {
+      let moves = legal_moves(&board, color);
+      assert!(!moves.is_empty());
+      let board = board.set_turn(color);
+      assert!(moves.iter().all(|mv| is_legal(&board, *mv)));
+    }
+  }
+
+  #[test]
+  fn test_is_legal() {
+    let board = Board::default();
+    let pos = |square: &str| crate::position::Position::pgn(square).unwrap();
+    assert!(is_legal(&board, Move::Piece(pos("e2"), pos("e4"))));
+    assert!(is_legal(&board, Move::Piece(pos("g1"), pos("f3"))));
+    // blocked, out of reach, or not the side to move
+    assert!(!is_legal(&board, Move::Piece(pos("a1"), pos("a3"))));
+    assert!(!is_legal(&board, Move::Piece(pos("e2"), pos("e5"))));
+    assert!(!is_legal(&board, Move::Piece(pos("e7"), pos("e5"))));
+    assert!(!is_legal(&board, Move::KingSideCastle));
+
+    // a pinned piece can't leave the line to its king
+    let board = parse_fen("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+    assert!(!is_legal(&board, Move::Piece(pos("e2"), pos("d3"))));
+    assert!(is_legal(&board, Move::Piece(pos("e1"), pos("d1"))));
+  }
+
+  #[test]
+  fn test_moves_into_check() {
+    let pos = |square: &str| Position::pgn(square).unwrap();
+    assert!(moves_into_check(&Board::default()).is_empty());
+
+    // the pinned bishop can't move at all, and the king can't step onto the rook's file
+    let board = parse_fen("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+    let moves = moves_into_check(&board);
+    assert!(moves.contains(&Move::Piece(pos("e2"), pos("d3"))));
+    assert!(moves.contains(&Move::Piece(pos("e2"), pos("b5"))));
+    assert!(!moves.contains(&Move::Piece(pos("e1"), pos("d1"))));
+    assert!(!moves.contains(&Move::Piece(pos("e1"), pos("e3"))));
+    assert!(moves.iter().all(|mv| !is_legal(&board, *mv)));
+
+    // in check, every move that doesn't block, capture or step away is into check
+    let board = parse_fen("4k3/8/8/8/8/8/3PPP2/r3K3 w - - 0 1").unwrap();
+    let moves = moves_into_check(&board);
+    assert!(moves.contains(&Move::Piece(pos("e2"), pos("e4"))));
+    assert!(moves.contains(&Move::Piece(pos("e1"), pos("d1"))));
+    assert!(legal_moves(&board, Color::White).is_empty());
+  }
+}
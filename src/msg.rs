@@ -1,31 +1,224 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::cwchess::{CwChessAction, CwChessColor, CwChessGame, CwChessGameOver};
-use cosmwasm_std::Addr;
+use crate::cwchess::{serialize_plain, CwChessAction, CwChessColor, CwChessGame, CwChessGameOver, OddsType};
+use crate::state::TimeControl;
+use crate::tournament::TournamentFormat;
+use cosmwasm_std::{Addr, Coin};
 
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+  pub abandon_threshold: Option<u64>,
+  // default true, false rejects promotions that don't name the piece
+  pub auto_queen_promotion: Option<bool>,
+  // largest block_limit rated as blitz, default 100
+  pub blitz_max_blocks: Option<u64>,
+  // largest block_limit rated as bullet, default 30
+  pub bullet_max_blocks: Option<u64>,
+  // default None, free challenges
+  pub challenge_fee: Option<Coin>,
+  // contract told about every finished game with a CompletionHookMsg
+  pub completion_hook: Option<String>,
+  pub decay_after_blocks: Option<u64>,
+  pub decay_amount: Option<u64>,
+  // default 0, no cooldown
+  pub draw_offer_cooldown_blocks: Option<u64>,
+  // default 32, the same as k_factor
+  pub high_rating_k_factor: Option<u64>,
+  // default 2400
+  pub high_rating_threshold: Option<u64>,
+  pub inactivity_blocks: Option<u64>,
+  // default 32
+  pub k_factor: Option<u64>,
+  pub max_block_limit: Option<u64>,
+  pub max_open_challenges: Option<u64>,
+  // default None, no limit on directed challenges to the same opponent
+  pub max_pairwise_challenges: Option<u64>,
+  pub provisional_games: Option<u64>,
+  // default 32
+  pub provisional_k_factor: Option<u64>,
+  // largest block_limit rated as rapid, default 600, longer games are correspondence
+  pub rapid_max_blocks: Option<u64>,
+  // default true, false leaves ratings unchanged by draws
+  pub rate_draws: Option<bool>,
+  pub rating_ceiling: Option<u64>,
+  pub rating_floor: Option<u64>,
+  // default false, challenge fees go to the treasury on creation
+  pub refund_challenge_fee: Option<bool>,
+  // default false
+  pub require_resign_confirmation: Option<bool>,
+  // default 1000, within the rating floor and ceiling
+  pub starting_rating: Option<u64>,
+  // default None, challenge fees go to the owner
+  pub treasury: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
+// executed on the completion hook contract when a game ends
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct InstantiateMsg {}
+#[serde(rename_all = "snake_case")]
+pub enum CompletionHookMsg {
+  GameCompleted {
+    game_id: u64,
+    player1: Addr,
+    player2: Addr,
+    status: CwChessGameOver,
+    // None for a draw or a voided game
+    winner: Option<Addr>,
+  },
+}
 
+// a message is only parsed once, so UpdateConfig being much larger doesn't matter
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
+  // call off a game before both players have moved, voided and unrated.
+  // match and tournament games can't be aborted
+  AbortGame {
+    game_id: u64,
+    // sender is player
+  },
+  AdminVoidGame {
+    game_id: u64,
+    // sender is owner
+  },
+  // store a finished game from PGN, for archiving games played elsewhere
+  // the moves are checked, but the game is left unrated
+  ImportGame {
+    pgn: String,
+    // white
+    player1: String,
+    // black
+    player2: String,
+    // sender is owner
+  },
+  // note on a move already played, replacing any earlier one, at most 280 characters
+  AnnotateMove {
+    game_id: u64,
+    // position in GetGameHistory, from 1
+    move_number: u16,
+    text: String,
+    // sender is player
+  },
   CreateChallenge {
+    // let the creator accept their own challenge and play both colors, for testing
+    #[serde(default)]
+    allow_self_play: bool,
     block_limit: Option<u64>,
+    // random Chess960 start, unless start_fen is given
+    #[serde(default)]
+    chess960: bool,
+    // a retry with the same key returns the creator's open challenge instead of a new one
+    #[serde(default)]
+    dedup_key: Option<String>,
+    // challenge can't be accepted after this many blocks
+    expires_in_blocks: Option<u64>,
+    // Fischer increment, added to a player's block_limit clock after each move
+    increment_blocks: Option<u64>,
+    // play a match of this many games with alternating colors, at most 10
+    num_games: Option<u8>,
+    // the creator plays without this piece, unrated, standard start only
+    #[serde(default)]
+    odds: Option<OddsType>,
     opponent: Option<String>,
     play_as: Option<CwChessColor>,
+    start_fen: Option<String>,
     // sender is creator
   },
+  CreateTournament {
+    block_limit: Option<u64>,
+    // default is knockout
+    format: Option<TournamentFormat>,
+    name: String,
+    // number of players, a power of two for knockouts
+    size: u64,
+    // sender is organizer
+  },
   AcceptChallenge {
     challenge_id: u64,
     // sender is player
   },
+  AcceptOwnership {
+    // sender is pending owner
+  },
+  // actions applied in order, each on the sender's turn
+  // all or nothing, at most 20 actions
+  BatchTurn {
+    actions: Vec<CwChessAction>,
+    game_id: u64,
+    // sender is player
+  },
   CancelChallenge {
     challenge_id: u64,
     // sender is creator
   },
+  ExpireChallenge {
+    challenge_id: u64,
+    // sender is anyone, after abandon_threshold blocks
+  },
   DeclareTimeout {
     game_id: u64,
+    // sender is anyone, once the player to move ran out of blocks
+  },
+  // same as DeclareTimeout, but only for the player who didn't run out of blocks
+  ClaimTimeoutWin {
+    game_id: u64,
+    // sender is player
+  },
+  // wait for an opponent within rating_range of the sender's rating,
+  // a game starts right away with the closest rated one already waiting
+  EnterQueue {
+    block_limit: Option<u64>,
+    rating_range: u64,
+    // sender is player
+  },
+  LeaveQueue {
+    // sender is player
+  },
+  // move the rating of a player without a game for decay_after_blocks
+  // toward the starting rating
+  DecayInactiveRating {
+    player: String,
+    // sender is anyone
+  },
+  ClaimVictoryOnAbandonment {
+    game_id: u64,
+    // sender is the player waiting on the opponent, after inactivity_blocks
+  },
+  JoinTournament {
+    tournament_id: u64,
+    // sender is player
+  },
+  OfferRematch {
+    game_id: u64,
+    // sender is creator
+  },
+  ProposeNewOwner {
+    new_owner: String,
+    // sender is owner
+  },
+  // move to play automatically once it's the sender's turn, dropped if illegal then
+  SetPremove {
+    game_id: u64,
+    #[serde(rename = "move")]
+    move_str: String,
+    // sender is player, while waiting on the opponent
+  },
+  // ends the game once both players have submitted the same result
+  AgreeResult {
+    game_id: u64,
+    result: CwChessGameOver,
+    // sender is player
+  },
+  Resign {
+    // must be true when require_resign_confirmation is set
+    #[serde(default)]
+    confirm: bool,
+    game_id: u64,
+    // sender is player
   },
   Turn {
     game_id: u64,
@@ -33,6 +226,47 @@ pub enum ExecuteMsg {
     // sender is player
     // block is timestamp
   },
+  UpdateConfig {
+    abandon_threshold: Option<u64>,
+    auto_queen_promotion: Option<bool>,
+    blitz_max_blocks: Option<u64>,
+    bullet_max_blocks: Option<u64>,
+    // a zero amount makes challenges free again
+    challenge_fee: Option<Coin>,
+    completion_hook: Option<String>,
+    decay_after_blocks: Option<u64>,
+    decay_amount: Option<u64>,
+    draw_offer_cooldown_blocks: Option<u64>,
+    high_rating_k_factor: Option<u64>,
+    high_rating_threshold: Option<u64>,
+    inactivity_blocks: Option<u64>,
+    k_factor: Option<u64>,
+    max_block_limit: Option<u64>,
+    max_open_challenges: Option<u64>,
+    max_pairwise_challenges: Option<u64>,
+    provisional_games: Option<u64>,
+    provisional_k_factor: Option<u64>,
+    rapid_max_blocks: Option<u64>,
+    rate_draws: Option<bool>,
+    rating_ceiling: Option<u64>,
+    rating_floor: Option<u64>,
+    refund_challenge_fee: Option<bool>,
+    require_resign_confirmation: Option<bool>,
+    // only affects players rated after the change
+    starting_rating: Option<u64>,
+    treasury: Option<String>,
+    // sender is owner
+  },
+}
+
+// where a game stands for one of its players, voided games are none of these
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusFilter {
+  Ongoing,
+  Won,
+  Lost,
+  Drawn,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -43,27 +277,221 @@ pub enum QueryMsg {
   },
   GetChallenges {
     after: Option<u64>,
+    // leave out challenges past their expires_at_block
+    hide_expired: Option<bool>,
+    // max results, default 25, at most 50
+    limit: Option<u32>,
+    // only challenges with a block_limit in range, both ends included
+    // challenges are filtered while scanning, so a page can take a long scan to fill
+    max_block_limit: Option<u64>,
+    min_block_limit: Option<u64>,
     player: Option<String>,
   },
+  // challenges without an opponent, that anyone can accept
+  GetOpenChallengeCount {},
+  // totals for dashboards, kept as counters
+  GetContractStats {},
+  // tunables set at instantiate and with UpdateConfig
+  GetConfig {},
+  // challenges directed at player
+  GetIncomingChallenges {
+    after: Option<u64>,
+    // max results, default 25, at most 50
+    limit: Option<u32>,
+    player: String,
+  },
+  // challenges created by player
+  GetOutgoingChallenges {
+    after: Option<u64>,
+    // max results, default 25, at most 50
+    limit: Option<u32>,
+    player: String,
+  },
   GetGame {
     game_id: u64,
   },
+  // the game with what clients would otherwise work out from it
+  GetGameDetailed {
+    game_id: u64,
+  },
+  // the other player in the game, error if player is not in it
+  GetOpponent {
+    game_id: u64,
+    player: String,
+  },
+  // current position as squares, turn, castling and en passant
+  GetBoard {
+    game_id: u64,
+  },
+  // destination squares for the piece on square, e.g. "e2"
+  // castling is given as the king's destination
+  GetLegalMoves {
+    game_id: u64,
+    square: String,
+  },
+  GetGameHistory {
+    game_id: u64,
+  },
+  // FEN after the first ply board moves, the starting position for 0
+  GetFenAtMove {
+    game_id: u64,
+    ply: u64,
+  },
+  // move count and timing, for spotting suspicious games
+  GetGameMetrics {
+    game_id: u64,
+  },
+  // move annotations, by move number
+  GetAnnotations {
+    game_id: u64,
+  },
+  // last move and current position only, for polling spectators
+  GetLatestMove {
+    game_id: u64,
+  },
+  ExportPgn {
+    game_id: u64,
+  },
+  GetOpening {
+    game_id: u64,
+  },
+  // games in the order of ids, None for unknown ids, at most 25 ids
+  GetGamesByIds {
+    ids: Vec<u64>,
+  },
+  // unfinished games of player, oldest first
+  GetActiveGames {
+    player: String,
+  },
+  // ids of player's unfinished games where it's their turn, oldest first,
+  // one poll for every game waiting on them
+  GetPlayerTurnGames {
+    player: String,
+  },
+  // unfinished games whose player to move ran out of blocks as of the queried block,
+  // ready for DeclareTimeout, oldest first
+  GetTimedOutGames {
+    after: Option<u64>,
+    // max results, default 25, at most 50
+    limit: Option<u32>,
+  },
+  // finished games with the given result
+  GetGamesByResult {
+    after: Option<u64>,
+    // max results, default 25, at most 50
+    limit: Option<u32>,
+    result: CwChessGameOver,
+  },
+  // games started from from_block to to_block, both included, oldest first
+  GetGamesStartedBetween {
+    from_block: u64,
+    // max results, default 25, at most 50
+    limit: Option<u32>,
+    to_block: u64,
+  },
   GetGames {
     after: Option<u64>,
     game_over: Option<bool>,
+    // max results, default 25, at most 50
+    limit: Option<u32>,
     player: Option<String>,
+    // games that are ongoing or ended that way for player, in place of game_over
+    // needs player
+    status_filter: Option<StatusFilter>,
+  },
+  // checks fen the same way CreateChallenge checks start_fen
+  ValidateFen {
+    fen: String,
   },
+  // legal is false with the reason when player can't play move_str now
+  // errors only for a missing game, bad address or corrupt position
   ValidMove {
     game_id: u64,
     player: String,
     #[serde(rename = "move")]
     move_str: String,
   },
-  GetRatings {},
+  // position after the player to move plays move_str, nothing is saved
+  SimulateMove {
+    game_id: u64,
+    #[serde(rename = "move")]
+    move_str: String,
+  },
+  // time_control defaults to correspondence
+  GetRatings {
+    time_control: Option<TimeControl>,
+  },
+  // rating of one player, the starting rating for new players
+  // time_control defaults to correspondence
+  GetRating {
+    player: String,
+    time_control: Option<TimeControl>,
+  },
+  // rating after each of player's rated games in a time control, oldest first
+  // time_control defaults to correspondence
+  GetRatingHistory {
+    // game_id
+    after: Option<u64>,
+    // max results, default 25, at most 50
+    limit: Option<u32>,
+    player: String,
+    time_control: Option<TimeControl>,
+  },
+  // both players' ratings in the game's time control
+  GetGameRatings {
+    game_id: u64,
+  },
+  // false once the game is over or when not player's turn
   GetTurn {
     game_id: u64,
     player: String,
   },
+  // color to move, None once the game is over
+  GetTurnColor {
+    game_id: u64,
+  },
+  // player to move and their timeout deadline, error once the game is over
+  GetTurnInfo {
+    game_id: u64,
+    // checked against the player to move, if given
+    player: Option<String>,
+  },
+  GetClocks {
+    game_id: u64,
+  },
+  GetHeadToHead {
+    player_a: String,
+    player_b: String,
+  },
+  // rating is in time_control, which defaults to correspondence
+  GetPlayerStats {
+    player: String,
+    time_control: Option<TimeControl>,
+  },
+  // player's challenge and game totals, kept as counters
+  GetPlayerCounts {
+    player: String,
+  },
+  GetMatch {
+    match_id: u64,
+  },
+  // players waiting in the matchmaking queue, by rating
+  GetQueue {},
+  GetTournament {
+    tournament_id: u64,
+  },
+  // players by points, then buchholz
+  GetTournamentStandings {
+    tournament_id: u64,
+  },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TimedOutGame {
+  pub game: GameSummary,
+  // the player to move, who ran out of blocks
+  pub timed_out_color: CwChessColor,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -72,6 +500,8 @@ pub struct GameSummary {
   pub block_limit: Option<u64>,
   pub block_start: u64,
   pub game_id: u64,
+  // color whose draw offer the opponent can accept
+  pub pending_draw_offer: Option<CwChessColor>,
   pub player1: String,
   pub player2: String,
   pub status: Option<CwChessGameOver>,
@@ -84,6 +514,7 @@ impl From<&CwChessGame> for GameSummary {
       block_limit: game.block_limit,
       block_start: game.block_start,
       game_id: game.game_id,
+      pending_draw_offer: game.draw_offered(),
       player1: game.player1.to_string(),
       player2: game.player2.to_string(),
       status: game.status.clone(),
@@ -92,18 +523,251 @@ impl From<&CwChessGame> for GameSummary {
   }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct RatingHistoryPoint {
+  pub game_id: u64,
+  pub rating: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct GameRatings {
+  pub game_id: u64,
+  pub player1: PlayerGameRating,
+  pub player2: PlayerGameRating,
+  pub time_control: TimeControl,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PlayerGameRating {
+  pub player: String,
+  // rating now, the starting rating for new players
+  pub rating: u64,
+  // rating after the player's latest rated game started before this one
+  // in the same time control, None without rating history
+  pub rating_at_start: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct RatingSummary {
   pub player: String,
+  // rating is still unsettled
+  pub provisional: bool,
   pub rating: u64,
+  pub time_control: TimeControl,
 }
 
-impl From<(Addr, u64)> for RatingSummary {
-  fn from(tuple: (Addr, u64)) -> Self {
+impl From<(Addr, u64, bool, TimeControl)> for RatingSummary {
+  fn from(tuple: (Addr, u64, bool, TimeControl)) -> Self {
     RatingSummary {
       player: tuple.0.to_string(), // Convert Addr to String, adjust according to your Addr definition
+      provisional: tuple.2,
       rating: tuple.1,
+      time_control: tuple.3,
     }
   }
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PlayerStatsSummary {
+  // blocks per move, None before any timed move
+  pub average_move_blocks: Option<u64>,
+  pub draws: u64,
+  pub games_played: u64,
+  pub losses: u64,
+  pub player: String,
+  // rating is still unsettled
+  pub provisional: bool,
+  // rating in the requested time control
+  pub rating: u64,
+  pub wins: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MoveSummary {
+  // height of the block the move was played in
+  pub block: u64,
+  // blocks since the previous move (or game start)
+  pub blocks_elapsed: u64,
+  pub color: CwChessColor,
+  pub move_number: u64,
+  pub san: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AnnotationSummary {
+  // position in GetGameHistory, from 1
+  pub move_number: u16,
+  pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PlayerCounts {
+  // challenges the player created that are still waiting, directed ones included
+  pub challenges: u64,
+  // games started as either color, finished or not
+  pub games: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ContractStats {
+  // ended games, voided ones included
+  pub finished_games: u64,
+  pub games: u64,
+  // challenges without an opponent, as in GetOpenChallengeCount
+  pub open_challenges: u64,
+  // players with a rating
+  pub players: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct GameDetailed {
+  #[serde(serialize_with = "serialize_plain")]
+  pub game: CwChessGame,
+  // side to move is in check, true after a checkmate
+  pub in_check: bool,
+  pub move_count: u64,
+  // color whose draw offer the opponent can accept
+  pub pending_draw_offer: Option<CwChessColor>,
+  // None while the game is being played
+  pub result: Option<CwChessGameOver>,
+  // last block to move in, None before the first move, without a block_limit or once over
+  pub timeout_block: Option<u64>,
+  // past timeout_block as of the queried block, settled only by DeclareTimeout
+  pub timeout_pending: bool,
+  // None once the game is over
+  pub turn_color: Option<CwChessColor>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct GameMetrics {
+  // most blocks between two moves (or game start), 0 before the first move
+  pub longest_move_blocks: u64,
+  pub moves: u64,
+  // moves played in the same block as the previous move
+  pub same_block_moves: u64,
+  // blocks from game start to the last move
+  pub total_blocks: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ClockSummary {
+  // remaining blocks at the current height
+  pub black: u64,
+  pub white: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct HeadToHeadSummary {
+  pub draws: u64,
+  // most recent finished game between the players
+  pub last_game_id: Option<u64>,
+  pub player_a: String,
+  pub player_a_wins: u64,
+  pub player_b: String,
+  pub player_b_wins: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct OpeningSummary {
+  // None when no opening in the table matches
+  pub eco: Option<String>,
+  // "Unknown" when no opening in the table matches
+  pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TournamentStanding {
+  // sum of the opponents' points
+  pub buchholz: u64,
+  pub byes: u64,
+  // including unfinished games, not counting byes
+  pub games_played: u64,
+  pub player: String,
+  // in half points, 2 for a win or bye and 1 for a draw
+  pub points: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TurnInfoSummary {
+  // blocks left on the mover's clock at the current height
+  pub blocks_remaining: Option<u64>,
+  // whether the queried player is to move, None without a player
+  pub is_player_turn: Option<bool>,
+  pub player_to_move: String,
+  // last block to move in, None before the first move or without a block_limit
+  pub timeout_block: Option<u64>,
+  pub turn_color: CwChessColor,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct FenValidation {
+  // first problem found, None when valid
+  pub error: Option<String>,
+  pub valid: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LatestMoveSummary {
+  // height of the block the move was played in, None before the first move
+  pub block: Option<u64>,
+  // current position
+  pub fen: String,
+  // None before the first move
+  pub san: Option<String>,
+  // None once the game is over
+  pub turn_color: Option<CwChessColor>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MatchSummary {
+  pub finished: bool,
+  // in the order played, colors alternate
+  pub games: Vec<GameSummary>,
+  pub match_id: u64,
+  pub player1: String,
+  // in half points, 2 for a win and 1 for a draw
+  pub player1_points: u64,
+  pub player2: String,
+  pub player2_points: u64,
+  // None until the last game finishes, or for a drawn match
+  pub winner: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MoveValidation {
+  pub legal: bool,
+  // why the move can't be played, None when legal
+  pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SimulatedMove {
+  // the opponent is left in check, or mated
+  pub gives_check: bool,
+  // status the move would end the game with
+  pub is_game_over: Option<CwChessGameOver>,
+  // None for an illegal move
+  pub resulting_fen: Option<String>,
+  pub valid: bool,
+}
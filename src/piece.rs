@@ -365,32 +365,6 @@ impl Piece {
     }
   }
 
-  /// Is this piece in the starting position for the queenside rook?
-  ///
-  /// This method will only return true for rooks that are in the position
-  /// of the queenside rook, not for any particular rook.
-  #[inline]
-  pub fn is_queenside_rook(&self) -> bool {
-    if let Self::Rook(_, pos) = self {
-      pos.is_queenside_rook()
-    } else {
-      false
-    }
-  }
-
-  /// Is this piece in the starting position for the kingside rook?
-  ///
-  /// This method will only return true for rooks that are in the position
-  /// of the kingside rook, not for any particular rook.
-  #[inline]
-  pub fn is_kingside_rook(&self) -> bool {
-    if let Self::Rook(_, pos) = self {
-      pos.is_kingside_rook()
-    } else {
-      false
-    }
-  }
-
   /// Change the position of this piece to a new position.
   ///
   /// For example, `Pawn(Color::White, E4).move_to(E5)` will result in
@@ -466,7 +440,8 @@ impl Piece {
         }
         if board.can_kingside_castle(ally_color) {
           result.push(Move::KingSideCastle);
-        } else if board.can_queenside_castle(ally_color) {
+        }
+        if board.can_queenside_castle(ally_color) {
           result.push(Move::QueenSideCastle);
         }
       }
@@ -0,0 +1,103 @@
+use cosmwasm_std::Addr;
+use std::cmp::Ordering;
+use cw_storage_plus::Map;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::cwchess::CwChessColor;
+
+// a player waiting for an opponent
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct QueueEntry {
+  pub block_entered: u64,
+  // per player block limit, only matched with the same limit
+  pub block_limit: Option<u64>,
+  pub player: Addr,
+  // rating when entering the queue
+  pub rating: u64,
+  // largest rating difference the player accepts
+  pub rating_range: u64,
+}
+
+impl QueueEntry {
+  // both players accept the rating difference and play the same time control
+  pub fn accepts(&self, other: &QueueEntry) -> bool {
+    let difference = self.rating.abs_diff(other.rating);
+    self.block_limit == other.block_limit
+      && difference <= self.rating_range
+      && difference <= other.rating_range
+  }
+}
+
+// closest rated compatible opponent for entry, the longest waiting on a tie
+pub fn best_opponent<'a>(entry: &QueueEntry, waiting: &'a [QueueEntry]) -> Option<&'a QueueEntry> {
+  waiting
+    .iter()
+    .filter(|other| other.player != entry.player && entry.accepts(other))
+    .min_by_key(|other| (entry.rating.abs_diff(other.rating), other.block_entered))
+}
+
+// color for the waiting player from both players' color surplus, white for
+// whoever has had it less often, or None to leave it to the seed
+pub fn balanced_color(surplus: i128, entrant_surplus: i128) -> Option<CwChessColor> {
+  match surplus.cmp(&entrant_surplus) {
+    Ordering::Less => Some(CwChessColor::White),
+    Ordering::Equal => None,
+    Ordering::Greater => Some(CwChessColor::Black),
+  }
+}
+
+// (rating, player) -> entry, so waiting players can be read in rating order
+pub const QUEUE: Map<(u64, Addr), QueueEntry> = Map::new("queue");
+
+// player -> rating key of their QUEUE entry
+pub const QUEUED_PLAYERS: Map<Addr, u64> = Map::new("queued_players");
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(player: &str, rating: u64, rating_range: u64, block_entered: u64) -> QueueEntry {
+    QueueEntry {
+      block_entered,
+      block_limit: None,
+      player: Addr::unchecked(player),
+      rating,
+      rating_range,
+    }
+  }
+
+  #[test]
+  fn test_best_opponent() {
+    let waiting = vec![
+      entry("a", 900, 200, 1),
+      entry("b", 1050, 100, 2),
+      entry("c", 1100, 500, 3),
+      entry("d", 950, 100, 4),
+    ];
+    let best = |entry: &QueueEntry| best_opponent(entry, &waiting).map(|best| best.player.to_string());
+
+    // closest rating wins
+    assert_eq!(best(&entry("x", 960, 100, 5)), Some("d".to_string()));
+    // the longest waiting player breaks a tie, b and d are both 50 away
+    assert_eq!(best(&entry("x", 1000, 100, 5)), Some("b".to_string()));
+    assert_eq!(best(&entry("x", 1000, 40, 5)), None);
+    // both sides have to accept the difference, b's range is too small
+    assert_eq!(best(&entry("x", 1300, 300, 5)), Some("c".to_string()));
+    // never the player themselves
+    assert_eq!(best(&entry("d", 950, 100, 5)), Some("a".to_string()));
+
+    // block limits have to match
+    let mut timed = entry("x", 1000, 500, 5);
+    timed.block_limit = Some(100);
+    assert_eq!(best(&timed), None);
+  }
+
+  #[test]
+  fn test_balanced_color() {
+    assert_eq!(balanced_color(-1, 2), Some(CwChessColor::White));
+    assert_eq!(balanced_color(3, 0), Some(CwChessColor::Black));
+    assert_eq!(balanced_color(1, 1), None);
+  }
+}
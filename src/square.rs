@@ -20,7 +20,7 @@ impl Square {
   #[inline]
   #[allow(dead_code)]
   pub fn is_empty(&self) -> bool {
-    self.piece == None
+    self.piece.is_none()
   }
 
   /// Get the piece contained in this square.
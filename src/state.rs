@@ -1,30 +1,175 @@
-use cosmwasm_std::{Addr, StdResult, Storage};
+use cosmwasm_std::{Addr, Coin, Order, StdResult, Storage};
 use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::iter::Peekable;
 
-use crate::cwchess::{CwChessColor, CwChessGame};
+use crate::cwchess::{CwChessColor, CwChessGame, CwChessGameOver, OddsType};
+use crate::elo::Outcomes;
 
 // STATE
+// who runs the contract, the settings they control are in CONFIG
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct State {
   pub owner: Addr,
+  // proposed owner, who has to accept before becoming owner
+  pub pending_owner: Option<Addr>,
 }
 
 pub const STATE: Item<State> = Item::new("state");
 
+// receives challenge fees, the owner unless a treasury is set
+pub fn treasury(store: &dyn Storage) -> StdResult<Addr> {
+  match CONFIG.load(store)?.treasury {
+    Some(treasury) => Ok(treasury),
+    None => Ok(STATE.load(store)?.owner),
+  }
+}
+
+// CONFIG
+// tunables the owner sets with UpdateConfig
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Config {
+  // blocks after which anyone can expire an unaccepted challenge
+  pub abandon_threshold: u64,
+  // a pawn move to the last rank without a piece promotes to a queen,
+  // otherwise the piece has to be given
+  pub auto_queen_promotion: bool,
+  // largest block_limit of each rated time control, longer games are correspondence
+  pub blitz_max_blocks: u64,
+  pub bullet_max_blocks: u64,
+  // paid to create a challenge, None or a zero amount for free challenges
+  pub challenge_fee: Option<Coin>,
+  // contract notified of finished games
+  pub completion_hook: Option<Addr>,
+  // blocks without a finished game before a rating can decay
+  pub decay_after_blocks: u64,
+  // rating points an inactive rating moves toward the starting rating
+  pub decay_amount: u64,
+  // blocks a player waits after offering a draw before offering another
+  pub draw_offer_cooldown_blocks: u64,
+  // elo k-factor of established players rated at least high_rating_threshold
+  pub high_rating_k_factor: u64,
+  pub high_rating_threshold: u64,
+  // blocks without a move before the waiting player can claim victory
+  pub inactivity_blocks: u64,
+  // elo k-factor of established players
+  pub k_factor: u64,
+  // largest block_limit a challenge can use
+  pub max_block_limit: u64,
+  // max number of challenges a player can have open at once
+  pub max_open_challenges: u64,
+  // max number of challenges a player can direct at the same opponent at once, None for no limit
+  pub max_pairwise_challenges: Option<u64>,
+  // games before a player's rating is established
+  pub provisional_games: u64,
+  // elo k-factor of players still provisional
+  pub provisional_k_factor: u64,
+  pub rapid_max_blocks: u64,
+  // draws move ratings like wins and losses
+  pub rate_draws: bool,
+  // highest rating a player can reach, None for no limit
+  pub rating_ceiling: Option<u64>,
+  // lowest rating a player can drop to
+  pub rating_floor: u64,
+  // challenge fees are held until accepted and returned on cancel or expiry,
+  // otherwise they go to the treasury when the challenge is created
+  pub refund_challenge_fee: bool,
+  // resignations need confirm: true
+  pub require_resign_confirmation: bool,
+  // rating of new players
+  pub starting_rating: u64,
+  // receives challenge fees, None for the owner
+  pub treasury: Option<Addr>,
+}
+
+impl Config {
+  // time control a game with block_limit is rated in
+  pub fn time_control(&self, block_limit: Option<u64>) -> TimeControl {
+    match block_limit {
+      Some(block_limit) if block_limit <= self.bullet_max_blocks => TimeControl::Bullet,
+      Some(block_limit) if block_limit <= self.blitz_max_blocks => TimeControl::Blitz,
+      Some(block_limit) if block_limit <= self.rapid_max_blocks => TimeControl::Rapid,
+      _ => TimeControl::Correspondence,
+    }
+  }
+
+  // fee a new challenge costs, None when free
+  pub fn challenge_fee(&self) -> Option<&Coin> {
+    self.challenge_fee.as_ref().filter(|fee| !fee.amount.is_zero())
+  }
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+// Config as stored by any earlier version, for migrations
+// fields added since the first release are optional
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct StoredConfig {
+  pub abandon_threshold: Option<u64>,
+  pub auto_queen_promotion: Option<bool>,
+  pub blitz_max_blocks: Option<u64>,
+  pub bullet_max_blocks: Option<u64>,
+  pub challenge_fee: Option<Coin>,
+  pub completion_hook: Option<Addr>,
+  pub decay_after_blocks: Option<u64>,
+  pub decay_amount: Option<u64>,
+  pub draw_offer_cooldown_blocks: Option<u64>,
+  pub high_rating_k_factor: Option<u64>,
+  pub high_rating_threshold: Option<u64>,
+  pub inactivity_blocks: Option<u64>,
+  pub k_factor: Option<u64>,
+  pub max_block_limit: Option<u64>,
+  pub max_open_challenges: Option<u64>,
+  pub max_pairwise_challenges: Option<u64>,
+  pub provisional_games: Option<u64>,
+  pub provisional_k_factor: Option<u64>,
+  pub rapid_max_blocks: Option<u64>,
+  pub rate_draws: Option<bool>,
+  pub rating_ceiling: Option<u64>,
+  pub rating_floor: Option<u64>,
+  pub refund_challenge_fee: Option<bool>,
+  pub require_resign_confirmation: Option<bool>,
+  pub starting_rating: Option<u64>,
+  pub treasury: Option<Addr>,
+}
+
+pub const STORED_CONFIG: Item<StoredConfig> = Item::new("config");
+// versions before CONFIG kept the config alongside the owner
+pub const LEGACY_CONFIG: Item<StoredConfig> = Item::new("state");
+
 // CHALLENGES
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Challenge {
+  #[serde(default)]
+  pub allow_self_play: bool,
   pub block_created: u64,
   pub block_limit: Option<u64>,
   pub challenge_id: u64,
+  #[serde(default)]
+  pub chess960: bool,
   pub created_by: Addr,
+  // creator's key for recognising a retried CreateChallenge
+  #[serde(default)]
+  pub dedup_key: Option<String>,
+  // last block the challenge can be accepted in
+  pub expires_at_block: Option<u64>,
+  // fee the contract holds until the challenge is accepted, cancelled or expired
+  #[serde(default)]
+  pub fee: Option<Coin>,
+  pub increment_blocks: Option<u64>,
+  // games in the match started on accept, None for a single game
+  pub num_games: Option<u8>,
+  // piece the creator plays without
+  #[serde(default)]
+  pub odds: Option<OddsType>,
   pub play_as: Option<CwChessColor>,
   pub opponent: Option<Addr>,
+  pub start_fen: Option<String>,
 }
 
 pub const CHALLENGE_ID: Item<u64> = Item::new("challenge_id");
@@ -67,6 +212,72 @@ pub fn get_challenges_map<'a>() -> IndexedMap<'a, u64, Challenge, ChallengeIndex
   IndexedMap::new("challenges", indexes)
 }
 
+// OPEN CHALLENGES
+// number of challenges without an opponent, so the lobby can show it without a scan
+pub const OPEN_CHALLENGE_COUNT: Item<u64> = Item::new("open_challenge_count");
+
+pub fn add_open_challenge(store: &mut dyn Storage, challenge: &Challenge) -> StdResult<()> {
+  if challenge.opponent.is_some() {
+    return Ok(());
+  }
+  let count = OPEN_CHALLENGE_COUNT.may_load(store)?.unwrap_or_default();
+  OPEN_CHALLENGE_COUNT.save(store, &(count + 1))
+}
+
+pub fn remove_open_challenge(store: &mut dyn Storage, challenge: &Challenge) -> StdResult<()> {
+  if challenge.opponent.is_some() {
+    return Ok(());
+  }
+  let count = OPEN_CHALLENGE_COUNT.may_load(store)?.unwrap_or_default();
+  OPEN_CHALLENGE_COUNT.save(store, &count.saturating_sub(1))
+}
+
+// CHALLENGE DEDUP
+// (creator, dedup_key) -> challenge_id while the challenge is open
+pub const CHALLENGE_DEDUP: Map<(Addr, String), u64> = Map::new("challenge_dedup");
+
+// save a new challenge with its counts and dedup key
+pub fn add_challenge(store: &mut dyn Storage, challenge: &Challenge) -> StdResult<()> {
+  get_challenges_map().save(store, challenge.challenge_id, challenge)?;
+  add_open_challenge(store, challenge)?;
+  if let Some(dedup_key) = &challenge.dedup_key {
+    CHALLENGE_DEDUP.save(store, (challenge.created_by.clone(), dedup_key.clone()), &challenge.challenge_id)?;
+  }
+  PLAYER_STATS.update(store, challenge.created_by.clone(), |stats| -> StdResult<_> {
+    let mut stats = stats.unwrap_or_default();
+    stats.challenges += 1;
+    Ok(stats)
+  })?;
+  Ok(())
+}
+
+// take down an accepted, cancelled or expired challenge
+pub fn remove_challenge(store: &mut dyn Storage, challenge: &Challenge) -> StdResult<()> {
+  get_challenges_map().remove(store, challenge.challenge_id)?;
+  remove_open_challenge(store, challenge)?;
+  // an expired challenge's key may already point to a newer challenge
+  if let Some(dedup_key) = &challenge.dedup_key {
+    let key = (challenge.created_by.clone(), dedup_key.clone());
+    if CHALLENGE_DEDUP.may_load(store, key.clone())? == Some(challenge.challenge_id) {
+      CHALLENGE_DEDUP.remove(store, key);
+    }
+  }
+  PLAYER_STATS.update(store, challenge.created_by.clone(), |stats| -> StdResult<_> {
+    let mut stats = stats.unwrap_or_default();
+    stats.challenges = stats.challenges.saturating_sub(1);
+    Ok(stats)
+  })?;
+  Ok(())
+}
+
+// ANNOTATIONS
+// (game_id, move_number) -> text, move numbers count every move from 1
+pub const ANNOTATIONS: Map<(u64, u16), String> = Map::new("annotations");
+
+// REMATCHES
+// finished game_id -> rematch challenge_id
+pub const REMATCHES: Map<u64, u64> = Map::new("rematches");
+
 // GAMES
 pub const GAME_ID: Item<u64> = Item::new("game_id");
 
@@ -76,20 +287,44 @@ pub fn next_game_id(store: &mut dyn Storage) -> StdResult<u64> {
   Ok(id)
 }
 
+// FINISHED GAMES
+// number of games that have ended, including voided ones
+pub const FINISHED_GAME_COUNT: Item<u64> = Item::new("finished_game_count");
+
+pub fn add_finished_game(store: &mut dyn Storage) -> StdResult<()> {
+  let count = FINISHED_GAME_COUNT.may_load(store)?.unwrap_or_default();
+  FINISHED_GAME_COUNT.save(store, &(count + 1))
+}
+
 pub struct GameIndexes<'a> {
+  pub block_start: MultiIndex<'a, u64, CwChessGame, u64>,
   pub player1: MultiIndex<'a, Addr, CwChessGame, u64>,
   pub player2: MultiIndex<'a, Addr, CwChessGame, u64>,
+  pub status: MultiIndex<'a, String, CwChessGame, u64>,
 }
 
 impl<'a> IndexList<CwChessGame> for GameIndexes<'a> {
   fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<CwChessGame>> + '_> {
-    let v: Vec<&dyn Index<CwChessGame>> = vec![&self.player1, &self.player2];
+    let v: Vec<&dyn Index<CwChessGame>> = vec![&self.block_start, &self.player1, &self.player2, &self.status];
     Box::new(v.into_iter())
   }
 }
 
+// status index key, "none" for games still being played
+pub fn status_key(status: Option<&CwChessGameOver>) -> String {
+  match status {
+    Some(status) => format!("{:?}", status),
+    None => "none".to_string(),
+  }
+}
+
 pub fn get_games_map<'a>() -> IndexedMap<'a, u64, CwChessGame, GameIndexes<'a>> {
   let indexes = GameIndexes {
+    block_start: MultiIndex::new(
+      |c: &CwChessGame| c.block_start,
+      "games",
+      "games__block_start",
+    ),
     player1: MultiIndex::new(
       |c: &CwChessGame| c.player1.clone(),
       "games",
@@ -100,12 +335,207 @@ pub fn get_games_map<'a>() -> IndexedMap<'a, u64, CwChessGame, GameIndexes<'a>>
       "games",
       "games__player2",
     ),
+    status: MultiIndex::new(
+      |c: &CwChessGame| status_key(c.status.as_ref()),
+      "games",
+      "games__status",
+    ),
   };
   IndexedMap::new("games", indexes)
 }
 
+// ACTIVE GAMES
+// (player, game_id) for unfinished games, so a player's active games
+// can be read without scanning their finished ones
+pub const ACTIVE_GAMES: Map<(Addr, u64), ()> = Map::new("active_games");
+
+pub fn add_active_game(store: &mut dyn Storage, game: &CwChessGame) -> StdResult<()> {
+  ACTIVE_GAMES.save(store, (game.player1.clone(), game.game_id), &())?;
+  ACTIVE_GAMES.save(store, (game.player2.clone(), game.game_id), &())
+}
+
+// count a new game for both players, once and without a color for a self-play game
+pub fn add_started_game(store: &mut dyn Storage, game: &CwChessGame) -> StdResult<()> {
+  let self_play = game.player1 == game.player2;
+  let mut players = vec![(game.player1.clone(), CwChessColor::White)];
+  if !self_play {
+    players.push((game.player2.clone(), CwChessColor::Black));
+  }
+  for (player, color) in players {
+    PLAYER_STATS.update(store, player, |stats| -> StdResult<_> {
+      let mut stats = stats.unwrap_or_default();
+      stats.games_started += 1;
+      if !self_play {
+        stats.record_color(color);
+      }
+      Ok(stats)
+    })?;
+  }
+  Ok(())
+}
+
+pub fn remove_active_game(store: &mut dyn Storage, game: &CwChessGame) {
+  ACTIVE_GAMES.remove(store, (game.player1.clone(), game.game_id));
+  ACTIVE_GAMES.remove(store, (game.player2.clone(), game.game_id));
+}
+
 // RATINGS
-pub const RATINGS: Map<Addr, u64> = Map::new("ratings");
+// players are rated separately in each time control, by a game's block_limit
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeControl {
+  Bullet,
+  Blitz,
+  Rapid,
+  // no block_limit, or longer than rapid
+  #[default]
+  Correspondence,
+}
+
+impl TimeControl {
+  pub const ALL: [TimeControl; 4] = [
+    TimeControl::Bullet,
+    TimeControl::Blitz,
+    TimeControl::Rapid,
+    TimeControl::Correspondence,
+  ];
+
+  // key in RATINGS
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      TimeControl::Bullet => "bullet",
+      TimeControl::Blitz => "blitz",
+      TimeControl::Rapid => "rapid",
+      TimeControl::Correspondence => "correspondence",
+    }
+  }
+}
+
+// (player, time control) -> rating
+pub const RATINGS: Map<(Addr, &str), u64> = Map::new("time_control_ratings");
+// player -> rating, from before ratings were split by time control
+// only read by migrate, which moves them to correspondence
+pub const LEGACY_RATINGS: Map<Addr, u64> = Map::new("ratings");
+// number of players with a rating in any time control
+pub const PLAYER_COUNT: Item<u64> = Item::new("player_count");
+
+pub fn add_player(store: &mut dyn Storage) -> StdResult<()> {
+  let count = PLAYER_COUNT.may_load(store)?.unwrap_or_default();
+  PLAYER_COUNT.save(store, &(count + 1))
+}
+
+// the player has a rating in some time control
+pub fn has_rating(store: &dyn Storage, addr: &Addr) -> bool {
+  RATINGS
+    .prefix(addr.clone())
+    .keys_raw(store, None, None, Order::Ascending)
+    .next()
+    .is_some()
+}
+// (player, time control, game_id) -> rating after that rated game, decay isn't recorded
+pub const RATING_HISTORY: Map<(Addr, &str, u64), u64> = Map::new("time_control_rating_history");
+// (player, game_id) -> rating, from before history was split by time control
+// only read by migrate, which moves each entry to its game's time control
+pub const LEGACY_RATING_HISTORY: Map<(Addr, u64), u64> = Map::new("rating_history");
+
+// PLAYER STATS
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PlayerStats {
+  // games started as black, not counting self-play
+  #[serde(default)]
+  pub black_games: u64,
+  // challenges created and not yet accepted, canceled or expired
+  #[serde(default)]
+  pub challenges: u64,
+  pub draws: u64,
+  // rated games finished
+  pub games_played: u64,
+  // games started, finished or not
+  #[serde(default)]
+  pub games_started: u64,
+  // height of the last rating decay
+  #[serde(default)]
+  pub last_decay_block: Option<u64>,
+  // height the last rated game finished at
+  #[serde(default)]
+  pub last_game_block: Option<u64>,
+  pub losses: u64,
+  // blocks taken over all timed moves
+  #[serde(default)]
+  pub move_blocks: u64,
+  // moves with a previous move to time them from
+  #[serde(default)]
+  pub timed_moves: u64,
+  // games started as white, not counting self-play
+  #[serde(default)]
+  pub white_games: u64,
+  pub wins: u64,
+}
+
+impl PlayerStats {
+  // record a finished game, outcome from this player's perspective
+  pub fn record(&mut self, outcome: &Outcomes) {
+    self.games_played += 1;
+    match outcome {
+      Outcomes::WIN => self.wins += 1,
+      Outcomes::LOSS => self.losses += 1,
+      Outcomes::DRAW => self.draws += 1,
+    }
+  }
+
+  // record the blocks a move took
+  pub fn record_move(&mut self, blocks: u64) {
+    self.move_blocks += blocks;
+    self.timed_moves += 1;
+  }
+
+  // last height the player was active, or their rating decayed
+  pub fn last_active_block(&self) -> Option<u64> {
+    self.last_game_block.max(self.last_decay_block)
+  }
+
+  pub fn average_move_blocks(&self) -> Option<u64> {
+    self.move_blocks.checked_div(self.timed_moves)
+  }
+
+  pub fn record_color(&mut self, color: CwChessColor) {
+    match color {
+      CwChessColor::White => self.white_games += 1,
+      CwChessColor::Black => self.black_games += 1,
+    }
+  }
+
+  // games as white over games as black, negative when black is more often
+  pub fn color_surplus(&self) -> i128 {
+    i128::from(self.white_games) - i128::from(self.black_games)
+  }
+}
+
+pub const PLAYER_STATS: Map<Addr, PlayerStats> = Map::new("player_stats");
+
+// HEAD TO HEAD
+// results between two players, keyed by (player_a, player_b)
+// where player_a < player_b
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct HeadToHeadRecord {
+  pub draws: u64,
+  pub last_game_id: u64,
+  pub player_a_wins: u64,
+  pub player_b_wins: u64,
+}
+
+pub const HEAD_TO_HEAD: Map<(Addr, Addr), HeadToHeadRecord> = Map::new("head_to_head");
+
+// normalize player order for HEAD_TO_HEAD keys
+// returns the key and whether the players were swapped
+pub fn head_to_head_key(player1: &Addr, player2: &Addr) -> ((Addr, Addr), bool) {
+  match player1 <= player2 {
+    true => ((player1.clone(), player2.clone()), false),
+    false => ((player2.clone(), player1.clone()), true),
+  }
+}
 
 pub fn merge_iters<I, J, K>(
   iter1: I,
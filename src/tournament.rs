@@ -0,0 +1,408 @@
+use std::cmp::Reverse;
+
+use cosmwasm_std::{Addr, StdResult, Storage};
+use cw_storage_plus::{Item, Map};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::cwchess::{CwChessGame, CwChessGameOver};
+use crate::error::ContractError;
+
+// largest bracket, 6 rounds
+pub const MAX_TOURNAMENT_SIZE: u64 = 64;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TournamentFormat {
+  // single elimination, size must be a power of two
+  Knockout,
+  // players paired by score for a fixed number of rounds,
+  // without rematches while possible
+  Swiss { rounds: u64 },
+}
+
+// games are created once the tournament is full,
+// and each round once the previous round is finished
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Tournament {
+  // per player block limit of every game
+  pub block_limit: Option<u64>,
+  // winner of the final, or top of the swiss standings
+  pub champion: Option<Addr>,
+  pub created_by: Addr,
+  pub format: TournamentFormat,
+  pub name: String,
+  // in join order, which is also bracket order and the last tiebreak
+  pub players: Vec<Addr>,
+  // game ids of each round, in bracket order
+  pub rounds: Vec<Vec<u64>>,
+  // number of players
+  pub size: u64,
+  pub tournament_id: u64,
+}
+
+impl Tournament {
+  // size must be a power of two, so every round pairs up evenly
+  pub fn check_size(size: u64) -> Result<(), ContractError> {
+    if !(2..=MAX_TOURNAMENT_SIZE).contains(&size) || !size.is_power_of_two() {
+      return Err(ContractError::InvalidTournamentSize {});
+    }
+    Ok(())
+  }
+
+  // swiss allows any size, and at most one round per possible opponent
+  // (plus one with an odd size, for the byes)
+  pub fn check_format(format: &TournamentFormat, size: u64) -> Result<(), ContractError> {
+    match format {
+      TournamentFormat::Knockout => Tournament::check_size(size),
+      TournamentFormat::Swiss { rounds } => {
+        if !(2..=MAX_TOURNAMENT_SIZE).contains(&size) {
+          return Err(ContractError::InvalidTournamentSize {});
+        }
+        let max_rounds = if size.is_multiple_of(2) { size - 1 } else { size };
+        if !(1..=max_rounds).contains(rounds) {
+          return Err(ContractError::InvalidTournamentRounds {});
+        }
+        Ok(())
+      }
+    }
+  }
+
+  pub fn is_full(&self) -> bool {
+    self.players.len() as u64 == self.size
+  }
+
+  // add a player, returns true once the bracket is full
+  pub fn join(&mut self, player: Addr) -> Result<bool, ContractError> {
+    if self.players.contains(&player) {
+      return Err(ContractError::AlreadyInTournament {});
+    }
+    if self.is_full() {
+      return Err(ContractError::TournamentFull {});
+    }
+    self.players.push(player);
+    Ok(self.is_full())
+  }
+
+  // players advancing from the current round, in bracket order
+  // None until every game of the round is finished
+  pub fn round_winners(&self, games: &[CwChessGame]) -> Option<Vec<Addr>> {
+    games.iter().map(knockout_winner).collect()
+  }
+}
+
+// true once every game of the round is finished
+pub fn round_finished(games: &[CwChessGame]) -> bool {
+  games.iter().all(|game| game.status.is_some())
+}
+
+// players meet in bracket order, 1st vs 2nd, 3rd vs 4th, ...
+pub fn pairings(players: &[Addr]) -> Vec<(Addr, Addr)> {
+  players
+    .chunks(2)
+    .filter(|pair| pair.len() == 2)
+    .map(|pair| (pair[0].clone(), pair[1].clone()))
+    .collect()
+}
+
+// player advancing from a finished game
+// a knockout needs a winner, so black advances on a draw (as in armageddon)
+pub fn knockout_winner(game: &CwChessGame) -> Option<Addr> {
+  match game.status.as_ref()? {
+    CwChessGameOver::WhiteCheckmates
+    | CwChessGameOver::BlackResigns
+    | CwChessGameOver::BlackTimeout => Some(game.player1.clone()),
+    CwChessGameOver::BlackCheckmates
+    | CwChessGameOver::WhiteResigns
+    | CwChessGameOver::WhiteTimeout
+    | CwChessGameOver::DrawAccepted
+    | CwChessGameOver::DrawClaimed
+    | CwChessGameOver::Stalemate
+    | CwChessGameOver::InsufficientMaterial
+    | CwChessGameOver::Voided => Some(game.player2.clone()),
+  }
+}
+
+// a player's results in a tournament
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TournamentRecord {
+  pub byes: u64,
+  // everyone paired against so far, including unfinished games
+  pub opponents: Vec<Addr>,
+  // in half points, 2 for a win or bye and 1 for a draw
+  pub points: u64,
+}
+
+// half points for white and black from a finished game
+// a voided game scores nothing
+pub fn game_points(game: &CwChessGame) -> Option<(u64, u64)> {
+  match game.status.as_ref()? {
+    CwChessGameOver::WhiteCheckmates
+    | CwChessGameOver::BlackResigns
+    | CwChessGameOver::BlackTimeout => Some((2, 0)),
+    CwChessGameOver::BlackCheckmates
+    | CwChessGameOver::WhiteResigns
+    | CwChessGameOver::WhiteTimeout => Some((0, 2)),
+    CwChessGameOver::DrawAccepted
+    | CwChessGameOver::DrawClaimed
+    | CwChessGameOver::Stalemate
+    | CwChessGameOver::InsufficientMaterial => Some((1, 1)),
+    CwChessGameOver::Voided => Some((0, 0)),
+  }
+}
+
+// sum of the opponents' points, the usual swiss tiebreak
+pub fn buchholz(record: &TournamentRecord, records: &[(Addr, TournamentRecord)]) -> u64 {
+  records
+    .iter()
+    .filter(|(player, _)| record.opponents.contains(player))
+    .map(|(_, opponent)| opponent.points)
+    .sum()
+}
+
+// records ordered by points, then buchholz, then join order,
+// with each player's buchholz
+pub fn standings(records: &[(Addr, TournamentRecord)]) -> Vec<(Addr, TournamentRecord, u64)> {
+  let mut standings: Vec<_> = records
+    .iter()
+    .map(|(player, record)| (player.clone(), record.clone(), buchholz(record, records)))
+    .collect();
+  // stable sort keeps join order for ties
+  standings.sort_by_key(|(_, record, buchholz)| Reverse((record.points, *buchholz)));
+  standings
+}
+
+// calls the rematch search may make, pairing runs in the move that
+// finishes a round so it has to stay well within gas
+const PAIRING_STEPS: u32 = 10_000;
+
+// pair players given in standings order, top down, avoiding rematches
+// with an odd count, the lowest ranked player without a bye sits out
+// falls back to greedy top down pairing once rematches can't be avoided,
+// or the search runs out of steps
+pub fn swiss_pairings(ranked: &[(Addr, TournamentRecord)]) -> (Vec<(Addr, Addr)>, Option<Addr>) {
+  let mut players: Vec<&(Addr, TournamentRecord)> = ranked.iter().collect();
+  let mut bye = None;
+  if players.len() % 2 == 1 {
+    let index = players
+      .iter()
+      .rposition(|(_, record)| record.byes == 0)
+      .unwrap_or(players.len() - 1);
+    bye = Some(players.remove(index).0.clone());
+  }
+  let mut steps = PAIRING_STEPS;
+  let pairs = pair_without_rematches(&players, &mut steps).unwrap_or_else(|| greedy_pairs(&players));
+  (pairs, bye)
+}
+
+// pairs the first player with the best ranked new opponent that still
+// leaves a valid pairing for the rest, None when steps run out
+fn pair_without_rematches(players: &[&(Addr, TournamentRecord)], steps: &mut u32) -> Option<Vec<(Addr, Addr)>> {
+  if *steps == 0 {
+    return None;
+  }
+  *steps -= 1;
+  let (first, rest) = match players.split_first() {
+    Some(split) => split,
+    None => return Some(vec![]),
+  };
+  for (i, opponent) in rest.iter().enumerate() {
+    if first.1.opponents.contains(&opponent.0) {
+      continue;
+    }
+    let mut remaining = rest.to_vec();
+    remaining.remove(i);
+    if let Some(mut pairs) = pair_without_rematches(&remaining, steps) {
+      pairs.insert(0, (first.0.clone(), opponent.0.clone()));
+      return Some(pairs);
+    }
+  }
+  None
+}
+
+// top down, each player with the best ranked remaining new opponent,
+// or the next in line when they've met all of them
+fn greedy_pairs(players: &[&(Addr, TournamentRecord)]) -> Vec<(Addr, Addr)> {
+  let mut remaining = players.to_vec();
+  let mut pairs = vec![];
+  while remaining.len() >= 2 {
+    let first = remaining.remove(0);
+    let index = remaining
+      .iter()
+      .position(|opponent| !first.1.opponents.contains(&opponent.0))
+      .unwrap_or(0);
+    pairs.push((first.0.clone(), remaining.remove(index).0.clone()));
+  }
+  pairs
+}
+
+pub const TOURNAMENTS: Map<u64, Tournament> = Map::new("tournaments");
+
+// game_id -> tournament_id, for games played in a tournament
+pub const TOURNAMENT_GAMES: Map<u64, u64> = Map::new("tournament_games");
+
+// (tournament_id, player) -> record
+pub const TOURNAMENT_RECORDS: Map<(u64, Addr), TournamentRecord> = Map::new("tournament_records");
+
+pub const TOURNAMENT_ID: Item<u64> = Item::new("tournament_id");
+
+pub fn next_tournament_id(store: &mut dyn Storage) -> StdResult<u64> {
+  let id: u64 = TOURNAMENT_ID.may_load(store)?.unwrap_or_default() + 1;
+  TOURNAMENT_ID.save(store, &id)?;
+  Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_check_size() {
+    for size in [2, 4, 8, 64] {
+      assert!(Tournament::check_size(size).is_ok(), "{}", size);
+    }
+    for size in [0, 1, 3, 6, 128] {
+      assert!(Tournament::check_size(size).is_err(), "{}", size);
+    }
+  }
+
+  #[test]
+  fn test_check_format() {
+    assert!(Tournament::check_format(&TournamentFormat::Swiss { rounds: 3 }, 6).is_ok());
+    assert!(Tournament::check_format(&TournamentFormat::Swiss { rounds: 5 }, 5).is_ok());
+    assert!(Tournament::check_format(&TournamentFormat::Swiss { rounds: 6 }, 6).is_err());
+    assert!(Tournament::check_format(&TournamentFormat::Swiss { rounds: 0 }, 6).is_err());
+    assert!(Tournament::check_format(&TournamentFormat::Swiss { rounds: 1 }, 1).is_err());
+    assert!(Tournament::check_format(&TournamentFormat::Knockout, 6).is_err());
+  }
+
+  fn record(points: u64, byes: u64, opponents: &[&str]) -> TournamentRecord {
+    TournamentRecord {
+      byes,
+      opponents: opponents.iter().map(|opponent| Addr::unchecked(*opponent)).collect(),
+      points,
+    }
+  }
+
+  #[test]
+  fn test_standings() {
+    let records = vec![
+      (Addr::unchecked("a"), record(2, 0, &["b"])),
+      (Addr::unchecked("b"), record(0, 0, &["a"])),
+      (Addr::unchecked("c"), record(2, 0, &["d"])),
+      (Addr::unchecked("d"), record(2, 1, &["c"])),
+      (Addr::unchecked("e"), record(0, 0, &[])),
+    ];
+    let ranked: Vec<_> = standings(&records)
+      .into_iter()
+      .map(|(player, record, buchholz)| (player.to_string(), record.points, buchholz))
+      .collect();
+    assert_eq!(
+      ranked,
+      vec![
+        ("c".to_string(), 2, 2),
+        ("d".to_string(), 2, 2),
+        ("a".to_string(), 2, 0),
+        ("b".to_string(), 0, 2),
+        ("e".to_string(), 0, 0),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_swiss_pairings() {
+    // first round pairs top down, bye to the last player
+    let ranked: Vec<_> = ["a", "b", "c"]
+      .iter()
+      .map(|player| (Addr::unchecked(*player), TournamentRecord::default()))
+      .collect();
+    let (pairs, bye) = swiss_pairings(&ranked);
+    assert_eq!(pairs, vec![(Addr::unchecked("a"), Addr::unchecked("b"))]);
+    assert_eq!(bye, Some(Addr::unchecked("c")));
+
+    // bye skips players who already had one, pairings skip rematches
+    let ranked = vec![
+      (Addr::unchecked("a"), record(2, 0, &["b"])),
+      (Addr::unchecked("c"), record(2, 1, &[])),
+      (Addr::unchecked("b"), record(0, 0, &["a"])),
+    ];
+    let (pairs, bye) = swiss_pairings(&ranked);
+    assert_eq!(pairs, vec![(Addr::unchecked("a"), Addr::unchecked("c"))]);
+    assert_eq!(bye, Some(Addr::unchecked("b")));
+
+    // a player who played the next in line is paired further down
+    let ranked = vec![
+      (Addr::unchecked("a"), record(4, 0, &["b", "c"])),
+      (Addr::unchecked("b"), record(2, 0, &["a", "d"])),
+      (Addr::unchecked("c"), record(2, 0, &["d", "a"])),
+      (Addr::unchecked("d"), record(0, 0, &["c", "b"])),
+    ];
+    let (pairs, bye) = swiss_pairings(&ranked);
+    assert_eq!(
+      pairs,
+      vec![
+        (Addr::unchecked("a"), Addr::unchecked("d")),
+        (Addr::unchecked("b"), Addr::unchecked("c")),
+      ]
+    );
+    assert_eq!(bye, None);
+
+    // everyone has met, so rematches are allowed
+    let ranked = vec![
+      (Addr::unchecked("a"), record(2, 0, &["b"])),
+      (Addr::unchecked("b"), record(0, 0, &["a"])),
+    ];
+    let (pairs, _) = swiss_pairings(&ranked);
+    assert_eq!(pairs, vec![(Addr::unchecked("a"), Addr::unchecked("b"))]);
+  }
+
+  #[test]
+  fn test_pairing_steps() {
+    // c and d have met, so a takes c for the rest to pair without rematches
+    let ranked = vec![
+      (Addr::unchecked("a"), record(0, 0, &[])),
+      (Addr::unchecked("b"), record(0, 0, &[])),
+      (Addr::unchecked("c"), record(0, 0, &["d"])),
+      (Addr::unchecked("d"), record(0, 0, &["c"])),
+    ];
+    let players: Vec<_> = ranked.iter().collect();
+    let pair = |a: &str, b: &str| (Addr::unchecked(a), Addr::unchecked(b));
+    assert_eq!(swiss_pairings(&ranked).0, vec![pair("a", "c"), pair("b", "d")]);
+    // out of steps, greedy pairing takes the rematch
+    assert_eq!(pair_without_rematches(&players, &mut 0), None);
+    assert_eq!(greedy_pairs(&players), vec![pair("a", "b"), pair("c", "d")]);
+
+    // the last three have met everyone else, so one of them has to rematch,
+    // which the full search would only find after trying every other pairing
+    let names: Vec<String> = (0..64).map(|i| format!("p{:02}", i)).collect();
+    let last = &names[61..];
+    let ranked: Vec<_> = names
+      .iter()
+      .map(|name| {
+        let opponents: Vec<&str> = if last.contains(name) {
+          names.iter().filter(|other| !last.contains(*other)).map(String::as_str).collect()
+        } else {
+          last.iter().map(String::as_str).collect()
+        };
+        (Addr::unchecked(name), record(0, 0, &opponents))
+      })
+      .collect();
+    let (pairs, bye) = swiss_pairings(&ranked);
+    assert_eq!(pairs.len(), 32);
+    assert_eq!(bye, None);
+  }
+
+  #[test]
+  fn test_pairings() {
+    let players = ["a", "b", "c", "d"].map(Addr::unchecked);
+    assert_eq!(
+      pairings(&players),
+      vec![
+        (Addr::unchecked("a"), Addr::unchecked("b")),
+        (Addr::unchecked("c"), Addr::unchecked("d")),
+      ]
+    );
+  }
+}
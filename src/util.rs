@@ -1,7 +1,8 @@
 use crate::board::{Board, BoardBuilder};
 use crate::piece::Piece;
 use crate::position::Position;
-use crate::engine::{Color, Evaluate, Move};
+use crate::engine::{Color, GameResult, Move};
+use crate::movegen::{legal_moves, moves_into_check};
 
 // generate FEN
 // cannot calculate halfmove or fullmove based on board state,
@@ -9,7 +10,7 @@ use crate::engine::{Color, Evaluate, Move};
 pub fn format_fen(
   board: &Board,
   halfmove_clock: u8,
-  fullmove_number: u8,
+  fullmove_number: u32,
 ) -> Result<String, String> {
   let mut fen: Vec<String> = vec![];
 
@@ -65,23 +66,27 @@ pub fn format_fen(
   // castling
   fen.push(" ".to_string());
   let mut any_castling_rights = false;
-  let white_castling_rights = board.get_castling_rights(Color::White);
-  if white_castling_rights.can_kingside_castle() {
-    fen.push("K".to_string());
-    any_castling_rights = true;
-  }
-  if white_castling_rights.can_queenside_castle() {
-    fen.push("Q".to_string());
-    any_castling_rights = true
-  }
-  let black_castling_rights = board.get_castling_rights(Color::Black);
-  if black_castling_rights.can_kingside_castle() {
-    fen.push("k".to_string());
-    any_castling_rights = true;
-  }
-  if black_castling_rights.can_queenside_castle() {
-    fen.push("q".to_string());
-    any_castling_rights = true
+  // rooks off the h and a files (Chess960) are written as their file letter
+  let castling_letter = |color: Color, rook_col: i32, standard_col: i32, standard: char| {
+    let letter = match rook_col == standard_col {
+      true => standard,
+      false => (b'a' + rook_col as u8) as char,
+    };
+    match color {
+      Color::White => letter.to_ascii_uppercase().to_string(),
+      Color::Black => letter.to_string(),
+    }
+  };
+  for color in [Color::White, Color::Black] {
+    let castling_rights = board.get_castling_rights(color);
+    if castling_rights.can_kingside_castle() {
+      fen.push(castling_letter(color, castling_rights.get_kingside_rook(), 7, 'k'));
+      any_castling_rights = true;
+    }
+    if castling_rights.can_queenside_castle() {
+      fen.push(castling_letter(color, castling_rights.get_queenside_rook(), 0, 'q'));
+      any_castling_rights = true
+    }
   }
   if !any_castling_rights {
     fen.push("-".to_string());
@@ -150,8 +155,7 @@ pub fn parse_fen(fen: &str) -> Result<Board, String> {
           true => Color::White,
           false => Color::Black,
         };
-        // using as because row/col always in u8 range
-        let position = Position::new(row as i32, col as i32);
+        let position = Position::new(row, col);
         let piece = match x.to_ascii_lowercase() {
           'b' => Piece::Bishop(color, position),
           'n' => Piece::Knight(color, position),
@@ -193,6 +197,10 @@ pub fn parse_fen(fen: &str) -> Result<Board, String> {
     }
   });
 
+  // castling rights are given as K/Q/k/q (outermost rook on that side)
+  // or as Shredder-FEN rook files for Chess960 positions
+  let placed = builder.build();
+  builder = BoardBuilder::from(placed);
   match castling.unwrap() {
     "-" => {}
     castling => {
@@ -201,15 +209,33 @@ pub fn parse_fen(fen: &str) -> Result<Board, String> {
           true => Color::White,
           false => Color::Black,
         };
-        match c.to_ascii_lowercase() {
-          'k' => {
-            builder = builder.enable_kingside_castle(color);
+        let row = match color {
+          Color::White => 0,
+          Color::Black => 7,
+        };
+        let is_rook = |col: i32| {
+          placed.get_piece(Position::new(row, col)) == Some(Piece::Rook(color, Position::new(row, col)))
+        };
+        let king_col = (0..8)
+          .find(|col| placed.get_piece(Position::new(row, *col)) == Some(Piece::King(color, Position::new(row, *col))));
+        let rook_col = match (c.to_ascii_lowercase(), king_col) {
+          ('k', Some(king_col)) => (king_col + 1..8).rev().find(|col| is_rook(*col)),
+          ('q', Some(king_col)) => (0..king_col).find(|col| is_rook(*col)),
+          (file @ 'a'..='h', Some(_)) => Some(file as i32 - 'a' as i32).filter(|col| is_rook(*col)),
+          ('k' | 'q' | 'a'..='h', None) => None,
+          _ => {
+            return Err(String::from("invalid castling side"));
+          }
+        };
+        match (rook_col, king_col) {
+          (Some(rook_col), Some(king_col)) if rook_col > king_col => {
+            builder = builder.enable_kingside_castle(color).set_kingside_rook(color, rook_col);
           }
-          'q' => {
-            builder = builder.enable_queenside_castle(color);
+          (Some(rook_col), Some(_)) => {
+            builder = builder.enable_queenside_castle(color).set_queenside_rook(color, rook_col);
           }
           _ => {
-            return Err(String::from("invalid castling side"));
+            return Err(format!("castling `{}` not possible from position", c));
           }
         };
       }
@@ -229,6 +255,18 @@ pub fn parse_fen(fen: &str) -> Result<Board, String> {
   Ok(builder.build())
 }
 
+// castling is written with zeros (0-0) or the letter O (O-O)
+pub fn is_castling_move(move_str: &str) -> bool {
+  matches!(move_str, "0-0" | "0-0-0" | "O-O" | "O-O-O")
+}
+
+// a pawn move to the last rank that doesn't name the piece, like "e8" or "exd1",
+// which parse_san_move promotes to a queen
+pub fn is_bare_promotion(move_str: &str) -> bool {
+  let mut chars = move_str.chars();
+  matches!(chars.next(), Some('a'..='h')) && matches!(chars.next_back(), Some('1' | '8'))
+}
+
 // parse Short Algebraic Notation (SAN)
 //
 // move parsing is strict and should not include any +, #, etc characters.
@@ -240,30 +278,47 @@ pub fn parse_fen(fen: &str) -> Result<Board, String> {
 // returns Err(InvalidMove) if there is a parse error or the move is
 // not valid based on the current board position and turn.
 pub fn parse_san_move(board: &Board, move_str: &str) -> Result<Move, String> {
-  if move_str == "0-0" {
-    return Ok(Move::KingSideCastle {});
-  } else if move_str == "0-0-0" {
-    return Ok(Move::QueenSideCastle {});
+  find_san_move(board, move_str, legal_moves(board, board.get_turn_color()))
+}
+
+// whether move_str is a move the side to move could make,
+// if it didn't leave its own king in check
+pub fn is_move_into_check(board: &Board, move_str: &str) -> bool {
+  !is_castling_move(move_str) && find_san_move(board, move_str, moves_into_check(board)).is_ok()
+}
+
+// the move in moves that move_str names
+fn find_san_move(board: &Board, move_str: &str, moves: Vec<Move>) -> Result<Move, String> {
+  if is_castling_move(move_str) {
+    return match move_str.len() {
+      3 => Ok(Move::KingSideCastle {}),
+      _ => Ok(Move::QueenSideCastle {}),
+    };
   }
 
   // parse in reverse
   let mut chars = move_str.chars();
 
-  // optional pawn promotion
+  // optional pawn promotion, as e8Q or e8=Q
   let mut last = chars.next_back();
   let color = board.get_turn_color();
   let offboard = Position::new(-1, -1);
   let move_promotion = match last {
     Some('Q') => Some(Piece::Queen(color, offboard)),
-    Some('K') => Some(Piece::King(color, offboard)),
     Some('N') => Some(Piece::Knight(color, offboard)),
     Some('B') => Some(Piece::Bishop(color, offboard)),
     Some('R') => Some(Piece::Rook(color, offboard)),
+    Some('K') | Some('P') => {
+      return Err("invalid promotion".to_string());
+    }
     _ => None,
   };
   if move_promotion.is_some() {
     // there is a move promotion, move to next char for parsing
     last = chars.next_back();
+    if last == Some('=') {
+      last = chars.next_back();
+    }
   }
 
   // to position
@@ -329,8 +384,8 @@ pub fn parse_san_move(board: &Board, move_str: &str) -> Result<Move, String> {
 
   // find moves that end on target square and are correct piece type
   let mut candidates = vec![];
-  for legal_move in board.get_legal_moves() {
-    if let Move::Piece(from, to) = legal_move {
+  for candidate in moves {
+    if let Move::Piece(from, to) = candidate {
       if move_to == to {
         if let Some(board_piece) = board.get_piece(from) {
           // filter based on type
@@ -359,6 +414,244 @@ pub fn parse_san_move(board: &Board, move_str: &str) -> Result<Move, String> {
   }
 }
 
+// format Short Algebraic Notation (SAN)
+//
+// the move must be legal for the current board position and turn.
+// unlike parse_san_move, output is standard notation with capture,
+// promotion (=Q), check (+) and checkmate (#) markers, and only as
+// much disambiguation as needed.
+pub fn format_san_move(board: &Board, chess_move: Move) -> Result<String, String> {
+  let mut san = match chess_move {
+    Move::KingSideCastle => "O-O".to_string(),
+    Move::QueenSideCastle => "O-O-O".to_string(),
+    Move::Resign => {
+      return Err("resign is not a move".to_string());
+    }
+    Move::Piece(from, to) | Move::Promotion(from, to, _) => {
+      let piece = match board.get_piece(from) {
+        Some(piece) => piece,
+        None => {
+          return Err("no piece to move".to_string());
+        }
+      };
+      let file = |pos: Position| format!("{}", pos).chars().next().unwrap_or(' ');
+      let rank = |pos: Position| format!("{}", pos).chars().nth(1).unwrap_or(' ');
+      let capture = board.has_piece(to) || (piece.is_pawn() && from.get_col() != to.get_col());
+      let mut san = String::new();
+      match piece_letter(&piece) {
+        None => {
+          if capture {
+            san.push(file(from));
+          }
+        }
+        Some(letter) => {
+          san.push(letter);
+          // other pieces of the same type that could also move there
+          let others = legal_moves(board, board.get_turn_color())
+            .into_iter()
+            .filter_map(|legal_move| match legal_move {
+              Move::Piece(other, other_to) if other_to == to && other != from => Some(other),
+              _ => None,
+            })
+            .filter(|other| board.get_piece(*other).map(|p| p.get_name()) == Some(piece.get_name()))
+            .collect::<Vec<_>>();
+          if !others.is_empty() {
+            if others.iter().all(|other| other.get_col() != from.get_col()) {
+              san.push(file(from));
+            } else if others.iter().all(|other| other.get_row() != from.get_row()) {
+              san.push(rank(from));
+            } else {
+              san.push(file(from));
+              san.push(rank(from));
+            }
+          }
+        }
+      }
+      if capture {
+        san.push('x');
+      }
+      san.push_str(&format!("{}", to));
+      if let Move::Promotion(_, _, promotion) = chess_move {
+        san.push('=');
+        san.push(piece_letter(&promotion).unwrap_or('Q'));
+      }
+      san
+    }
+  };
+
+  match board.play_move(chess_move) {
+    GameResult::Victory(_) => san.push('#'),
+    GameResult::Continuing(next) if next.is_in_check(next.get_turn_color()) => san.push('+'),
+    GameResult::IllegalMove(_) => {
+      return Err("illegal move".to_string());
+    }
+    _ => {}
+  }
+  Ok(san)
+}
+
+// SAN letter for a piece, None for pawns
+fn piece_letter(piece: &Piece) -> Option<char> {
+  match piece {
+    Piece::King(_, _) => Some('K'),
+    Piece::Queen(_, _) => Some('Q'),
+    Piece::Rook(_, _) => Some('R'),
+    Piece::Bishop(_, _) => Some('B'),
+    Piece::Knight(_, _) => Some('N'),
+    Piece::Pawn(_, _) => None,
+  }
+}
+
+// pieces in compact FEN nibbles, 0 is unused
+const FEN_PIECES: &str = " PNBRQKpnbrqk";
+
+// compact binary FEN, for storage
+//
+// 8 bytes marking occupied squares from a8 to h1, a flags byte (black to move,
+// KQkq castling, en passant), an en passant byte if any, the move counters as
+// varints, then a nibble per occupied square's piece.
+// None if the FEN doesn't come back exactly, e.g. it isn't in the usual form.
+pub fn compress_fen(fen: &str) -> Option<Vec<u8>> {
+  let parts = fen.split(' ').collect::<Vec<_>>();
+  if parts.len() != 6 {
+    return None;
+  }
+  let mut occupied = 0u64;
+  let mut pieces = vec![];
+  let mut square = 0;
+  for c in parts[0].chars() {
+    match c {
+      '/' => {}
+      '1'..='8' => square += c.to_digit(10)?,
+      _ => {
+        occupied |= 1 << (63 - square.min(63));
+        pieces.push(FEN_PIECES.find(c).filter(|nibble| *nibble > 0)? as u8);
+        square += 1;
+      }
+    }
+  }
+  let mut flags = match parts[1] {
+    "w" => 0,
+    "b" => 1,
+    _ => return None,
+  };
+  if parts[2] != "-" {
+    for c in parts[2].chars() {
+      flags |= 2 << "KQkq".find(c)?;
+    }
+  }
+  let mut bytes = occupied.to_be_bytes().to_vec();
+  let en_passant = match parts[3] {
+    "-" => None,
+    square => {
+      let mut chars = square.chars();
+      let file = chars.next().filter(|c| ('a'..='h').contains(c))? as u8 - b'a';
+      let upper = match chars.next()? {
+        '3' => 0,
+        '6' => 8,
+        _ => return None,
+      };
+      flags |= 32;
+      Some(file | upper)
+    }
+  };
+  bytes.push(flags);
+  bytes.extend(en_passant);
+  for counter in &parts[4..] {
+    let mut n = counter.parse::<u64>().ok()?;
+    loop {
+      let byte = (n & 0x7f) as u8;
+      n >>= 7;
+      if n == 0 {
+        bytes.push(byte);
+        break;
+      }
+      bytes.push(byte | 0x80);
+    }
+  }
+  for pair in pieces.chunks(2) {
+    bytes.push(pair[0] << 4 | pair.get(1).copied().unwrap_or_default());
+  }
+  match decompress_fen(&bytes) {
+    Ok(decompressed) if decompressed == fen => Some(bytes),
+    _ => None,
+  }
+}
+
+// FEN from compress_fen's bytes
+pub fn decompress_fen(bytes: &[u8]) -> Result<String, String> {
+  let invalid = || "invalid compressed fen".to_string();
+  let occupied = u64::from_be_bytes(bytes.get(..8).ok_or_else(invalid)?.try_into().map_err(|_| invalid())?);
+  let flags = *bytes.get(8).ok_or_else(invalid)?;
+  let mut rest = &bytes[9..];
+  let en_passant = match flags & 32 {
+    0 => "-".to_string(),
+    _ => {
+      let byte = *rest.first().ok_or_else(invalid)?;
+      rest = &rest[1..];
+      format!("{}{}", (b'a' + (byte & 7)) as char, if byte & 8 == 0 { '3' } else { '6' })
+    }
+  };
+  let mut counters = vec![];
+  for _ in 0..2 {
+    let mut n = 0u64;
+    let mut shift = 0;
+    loop {
+      let byte = *rest.first().ok_or_else(invalid)?;
+      rest = &rest[1..];
+      n |= ((byte & 0x7f) as u64).checked_shl(shift).ok_or_else(invalid)?;
+      shift += 7;
+      if byte & 0x80 == 0 {
+        break;
+      }
+    }
+    counters.push(n);
+  }
+  let count = occupied.count_ones() as usize;
+  if rest.len() != count.div_ceil(2) {
+    return Err(invalid());
+  }
+  let mut pieces = rest.iter().flat_map(|byte| [byte >> 4, byte & 15]).take(count);
+
+  let mut placement = String::new();
+  for row in 0..8 {
+    if row > 0 {
+      placement.push('/');
+    }
+    let mut empty = 0;
+    for col in 0..8 {
+      if occupied & (1 << (63 - (row * 8 + col))) == 0 {
+        empty += 1;
+        continue;
+      }
+      if empty > 0 {
+        placement.push_str(&empty.to_string());
+        empty = 0;
+      }
+      let nibble = pieces.next().ok_or_else(invalid)? as usize;
+      placement.push(FEN_PIECES.chars().nth(nibble).filter(|c| *c != ' ').ok_or_else(invalid)?);
+    }
+    if empty > 0 {
+      placement.push_str(&empty.to_string());
+    }
+  }
+  let castling = "KQkq"
+    .chars()
+    .enumerate()
+    .filter(|(i, _)| flags & (2 << i) != 0)
+    .map(|(_, c)| c)
+    .collect::<String>();
+  Ok(format!(
+    "{} {} {} {} {} {}",
+    placement,
+    if flags & 1 == 0 { 'w' } else { 'b' },
+    if castling.is_empty() { "-".to_string() } else { castling },
+    en_passant,
+    counters[0],
+    counters[1],
+  ))
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -404,6 +697,18 @@ mod tests {
     )
   }
 
+  #[test]
+  fn test_is_bare_promotion() {
+    assert!(is_bare_promotion("e8"));
+    assert!(is_bare_promotion("exd1"));
+    assert!(!is_bare_promotion("e8Q"));
+    assert!(!is_bare_promotion("exd1=N"));
+    // pieces and castles never promote
+    assert!(!is_bare_promotion("Re8"));
+    assert!(!is_bare_promotion("O-O"));
+    assert!(!is_bare_promotion("e4"));
+  }
+
   #[test]
   fn test_parse_san_move() {
     let mut board = Board::default();
@@ -439,4 +744,73 @@ mod tests {
       "no matching move".to_string()
     );
   }
+
+  #[test]
+  fn test_format_san_move() {
+    let pos = |s: &str| Position::pgn(s).unwrap();
+    for (fen, chess_move, san) in [
+      // pawn and piece moves
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Move::Piece(pos("e2"), pos("e4")), "e4"),
+      ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Move::Piece(pos("g1"), pos("f3")), "Nf3"),
+      // captures
+      ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", Move::Piece(pos("e4"), pos("d5")), "exd5"),
+      ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", Move::Piece(pos("e5"), pos("d6")), "exd6"),
+      ("4k3/8/8/3p4/8/8/8/3RK3 w - - 0 1", Move::Piece(pos("d1"), pos("d5")), "Rxd5"),
+      // disambiguation by file, rank, and both
+      ("4k3/8/8/8/8/8/8/R4RK1 w - - 0 1", Move::Piece(pos("a1"), pos("d1")), "Rad1"),
+      ("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", Move::Piece(pos("a1"), pos("a3")), "R1a3"),
+      ("k7/8/8/8/7Q/8/8/K3Q2Q w - - 0 1", Move::Piece(pos("h1"), pos("e4")), "Qh1e4+"),
+      // promotion
+      ("k7/4P3/8/8/8/8/8/K7 w - - 0 1", Move::Promotion(pos("e7"), pos("e8"), Piece::Knight(Color::White, pos("e8"))), "e8=N"),
+      ("k7/4P3/8/8/8/8/8/K7 w - - 0 1", Move::Promotion(pos("e7"), pos("e8"), Piece::Queen(Color::White, pos("e8"))), "e8=Q+"),
+      // castling
+      ("4k3/8/8/8/8/8/8/4K2R w K - 0 1", Move::KingSideCastle, "O-O"),
+      ("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1", Move::QueenSideCastle, "O-O-O"),
+      // checkmate
+      ("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2", Move::Piece(pos("d8"), pos("h4")), "Qh4#"),
+    ] {
+      let board = parse_fen(fen).unwrap();
+      assert_eq!(format_san_move(&board, chess_move), Ok(san.to_string()), "{}", fen);
+    }
+
+    // not a move on the board
+    assert!(format_san_move(&Board::default(), Move::Resign).is_err());
+    assert!(format_san_move(&Board::default(), Move::Piece(pos("e3"), pos("e4"))).is_err());
+  }
+
+  #[test]
+  fn test_compress_fen() {
+    for fen in [
+      "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+      // en passant targets for both sides
+      "rnbqkbnr/pppp1ppp/8/8/4Pp2/8/PPPP2PP/RNBQKBNR b KQkq e3 0 3",
+      "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+      "rnbqkbnr/ppppppp1/8/8/7p/8/PPPPPPP1/RNBQKBN1 w Qkq a3 0 5",
+      "rnbqkbn1/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQq h6 0 5",
+      // partial and no castling rights
+      "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 0 1",
+      "r3k2r/8/8/8/8/8/8/R3K2R b Qk - 3 20",
+      "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+      // promoted pieces and large counters
+      "QQQQkQQQ/8/8/8/8/8/8/qqqqKqqq w - - 99 1000",
+      "8/8/8/8/8/8/8/8 w - - 0 1",
+    ] {
+      let bytes = compress_fen(fen).unwrap();
+      assert!(bytes.len() <= fen.len() / 2, "{}", fen);
+      assert_eq!(decompress_fen(&bytes), Ok(fen.to_string()));
+    }
+
+    // FENs that wouldn't come back as written stay uncompressed
+    assert_eq!(compress_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"), None);
+    assert_eq!(compress_fen("rnbqkbnr/pppppppp/44/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), None);
+    assert_eq!(compress_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w qkQK - 0 1"), None);
+    assert_eq!(compress_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e4 0 1"), None);
+    assert_eq!(compress_fen("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), None);
+
+    // truncated or padded bytes
+    let bytes = compress_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert!(decompress_fen(&bytes[..bytes.len() - 1]).is_err());
+    assert!(decompress_fen(&[bytes.clone(), vec![0]].concat()).is_err());
+    assert!(decompress_fen(&[]).is_err());
+  }
 }